@@ -0,0 +1,252 @@
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{parse_macro_input, Attribute, Data, DeriveInput, Fields, LitStr, Token};
+
+/*
+
+derive(Schema)
+
+Implements model::schema::Schema of the converter from the serde attributes of a type,
+so the schema describes the serialized form. The supported serde attributes are:
+
+  container: tag = "...", content = "..." (adjacently tagged enum), deny_unknown_fields
+  field:     rename = "...", with = "string_*" | "binary_*", skip_serializing_if = "...", default, flatten
+  variant:   rename = "..."
+
+Any other serde attribute is a compile error, because the schema would not match the output.
+
+*/
+
+#[proc_macro_derive(Schema, attributes(serde))]
+pub fn derive_schema(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(&input).unwrap_or_else(syn::Error::into_compile_error).into()
+}
+
+#[derive(Default)]
+struct ContainerAttributes {
+    tag: Option<LitStr>,
+    content: Option<LitStr>,
+    deny_unknown_fields: bool,
+}
+
+#[derive(Default)]
+struct FieldAttributes {
+    rename: Option<LitStr>,
+    with: Option<LitStr>,
+    skip_serializing_if: bool,
+    flatten: bool,
+}
+
+#[derive(Default)]
+struct VariantAttributes {
+    rename: Option<LitStr>,
+}
+
+fn expand(input: &DeriveInput) -> syn::Result<TokenStream2> {
+    let name = &input.ident;
+    let name_str = name.to_string();
+    let (impl_generics, type_generics, where_clause) = input.generics.split_for_impl();
+    let container = parse_container_attributes(&input.attrs)?;
+
+    let schema = match &input.data {
+        Data::Struct(data) => {
+            if container.tag.is_some() || container.content.is_some() {
+                return Err(syn::Error::new_spanned(name, "tag and content are only supported on enums"));
+            }
+            let fields = expand_fields(&data.fields)?;
+            let deny_unknown_fields = container.deny_unknown_fields;
+            quote! {
+                crate::model::schema::TypeSchema::Object(crate::model::schema::ObjectSchema {
+                    fields: #fields,
+                    deny_unknown_fields: #deny_unknown_fields,
+                })
+            }
+        }
+        Data::Enum(data) => {
+            if container.deny_unknown_fields {
+                return Err(syn::Error::new_spanned(name, "deny_unknown_fields is only supported on structs"));
+            }
+            let mut variants = Vec::with_capacity(data.variants.len());
+            for variant in data.variants.iter() {
+                let attributes = parse_variant_attributes(&variant.attrs)?;
+                let variant_name = variant.ident.to_string();
+                let value = attributes.rename.map(|rename| rename.value()).unwrap_or_else(|| variant_name.clone());
+                let payload = match &variant.fields {
+                    Fields::Unit => None,
+                    Fields::Unnamed(fields) if fields.unnamed.len() == 1 => {
+                        let ty = &fields.unnamed[0].ty;
+                        Some(quote! { <#ty as crate::model::schema::Schema>::schema(definitions) })
+                    }
+                    Fields::Unnamed(fields) => {
+                        return Err(syn::Error::new_spanned(fields, "tuple variants are not supported"));
+                    }
+                    Fields::Named(_) => {
+                        let fields = expand_fields(&variant.fields)?;
+                        Some(quote! {
+                            crate::model::schema::TypeSchema::Object(crate::model::schema::ObjectSchema {
+                                fields: #fields,
+                                deny_unknown_fields: false,
+                            })
+                        })
+                    }
+                };
+                variants.push((variant, variant_name, value, payload));
+            }
+
+            match (&container.tag, &container.content) {
+                (Some(tag), Some(content)) => {
+                    let variants = variants.into_iter().map(|(_, variant_name, value, payload)| {
+                        let payload = match payload {
+                            Some(payload) => quote! { Some(#payload) },
+                            None => quote! { None },
+                        };
+                        quote! {
+                            crate::model::schema::Variant {
+                                name: #variant_name,
+                                value: #value,
+                                payload: #payload,
+                            }
+                        }
+                    });
+                    quote! {
+                        crate::model::schema::TypeSchema::TaggedEnum(crate::model::schema::TaggedEnumSchema {
+                            tag: #tag,
+                            content: #content,
+                            variants: vec![#(#variants),*],
+                        })
+                    }
+                }
+                (None, None) => {
+                    let mut values = Vec::with_capacity(variants.len());
+                    for (variant, _, value, payload) in variants {
+                        if payload.is_some() {
+                            return Err(syn::Error::new_spanned(variant, "variants of an untagged enum must be unit variants"));
+                        }
+                        values.push(value);
+                    }
+                    quote! { crate::model::schema::TypeSchema::Enum(vec![#(#values),*]) }
+                }
+                _ => {
+                    return Err(syn::Error::new_spanned(name, "internally tagged enums are not supported, use tag and content"));
+                }
+            }
+        }
+        Data::Union(_) => return Err(syn::Error::new_spanned(name, "unions are not supported")),
+    };
+
+    Ok(quote! {
+        impl #impl_generics crate::model::schema::Schema for #name #type_generics #where_clause {
+            // definitions is unused if there are no fields
+            #[allow(unused_variables)]
+            fn schema(definitions: &mut crate::model::schema::Definitions) -> crate::model::schema::TypeSchema {
+                definitions.define(#name_str, |definitions| #schema)
+            }
+        }
+    })
+}
+
+fn expand_fields(fields: &Fields) -> syn::Result<TokenStream2> {
+    let Fields::Named(fields) = fields else {
+        return Err(syn::Error::new_spanned(fields, "only named fields are supported"));
+    };
+
+    let mut expanded = Vec::with_capacity(fields.named.len());
+    for field in fields.named.iter() {
+        let attributes = parse_field_attributes(&field.attrs)?;
+        let ident = field.ident.as_ref().unwrap();
+        let name = ident.to_string();
+        let ty = &field.ty;
+
+        let key = match (attributes.flatten, attributes.rename) {
+            (true, Some(rename)) => return Err(syn::Error::new_spanned(rename, "a flattened field has no key")),
+            (true, None) => quote! { None },
+            (false, Some(rename)) => quote! { Some(#rename) },
+            (false, None) => quote! { Some(#name) },
+        };
+        let schema = match attributes.with {
+            Some(with) if with.value().starts_with("string_") => quote! {
+                <#ty as crate::model::schema::Schema>::schema(definitions).encoded_as_string()
+            },
+            // binary_* modules change only the binary encoding (see model/event/binary.rs)
+            Some(with) if with.value().starts_with("binary_") => quote! {
+                <#ty as crate::model::schema::Schema>::schema(definitions)
+            },
+            Some(with) => return Err(syn::Error::new_spanned(with, "only string_* and binary_* modules of model/serde.rs are supported")),
+            None => quote! { <#ty as crate::model::schema::Schema>::schema(definitions) },
+        };
+        let optional = attributes.skip_serializing_if;
+
+        expanded.push(quote! {
+            crate::model::schema::Field {
+                name: #name,
+                key: #key,
+                schema: #schema,
+                optional: #optional,
+            }
+        });
+    }
+    Ok(quote! { vec![#(#expanded),*] })
+}
+
+fn parse_container_attributes(attrs: &[Attribute]) -> syn::Result<ContainerAttributes> {
+    let mut attributes = ContainerAttributes::default();
+    for attr in attrs.iter().filter(|attr| attr.path().is_ident("serde")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("tag") {
+                attributes.tag = Some(meta.value()?.parse()?);
+            } else if meta.path.is_ident("content") {
+                attributes.content = Some(meta.value()?.parse()?);
+            } else if meta.path.is_ident("deny_unknown_fields") {
+                attributes.deny_unknown_fields = true;
+            } else {
+                return Err(meta.error("unsupported serde attribute for Schema"));
+            }
+            Ok(())
+        })?;
+    }
+    Ok(attributes)
+}
+
+fn parse_field_attributes(attrs: &[Attribute]) -> syn::Result<FieldAttributes> {
+    let mut attributes = FieldAttributes::default();
+    for attr in attrs.iter().filter(|attr| attr.path().is_ident("serde")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("rename") {
+                attributes.rename = Some(meta.value()?.parse()?);
+            } else if meta.path.is_ident("with") {
+                attributes.with = Some(meta.value()?.parse()?);
+            } else if meta.path.is_ident("skip_serializing_if") {
+                meta.value()?.parse::<LitStr>()?;
+                attributes.skip_serializing_if = true;
+            } else if meta.path.is_ident("default") {
+                // deserialization only, with or without a function
+                if meta.input.peek(Token![=]) {
+                    meta.value()?.parse::<LitStr>()?;
+                }
+            } else if meta.path.is_ident("flatten") {
+                attributes.flatten = true;
+            } else {
+                return Err(meta.error("unsupported serde attribute for Schema"));
+            }
+            Ok(())
+        })?;
+    }
+    Ok(attributes)
+}
+
+fn parse_variant_attributes(attrs: &[Attribute]) -> syn::Result<VariantAttributes> {
+    let mut attributes = VariantAttributes::default();
+    for attr in attrs.iter().filter(|attr| attr.path().is_ident("serde")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("rename") {
+                attributes.rename = Some(meta.value()?.parse()?);
+            } else {
+                return Err(meta.error("unsupported serde attribute for Schema"));
+            }
+            Ok(())
+        })?;
+    }
+    Ok(attributes)
+}
@@ -0,0 +1,94 @@
+use crate::{
+  io::event::load_from_local_whirlpool_event_file,
+  model::{
+    arbitrage::detect_arbitrage_cycles,
+    event::{WhirlpoolEvent, WHIRLPOOL_EVENT_FORMAT_VERSION},
+    header::WhirlpoolFileKind,
+  },
+};
+use anyhow::{bail, Result};
+use serde_derive::Serialize;
+
+#[derive(Serialize, Debug)]
+struct ArbitrageDetection {
+  slot: u64,
+  block_time: i64,
+  signature: String,
+  payer: String,
+  hops: usize,
+  // whirlpools and mints along the route, separated by ">"
+  whirlpool_route: String,
+  mint_route: String,
+  // <instruction index>.<sub index> of the trades along the route
+  location_route: String,
+  mint: String,
+  input_amount: u64,
+  output_amount: u64,
+  // output_amount - input_amount, Token-2022 transfer fees are not deducted
+  profit: String,
+}
+
+pub async fn process(
+  whirlpool_event_file_path: String,
+  arbitrage_file_path: String,
+) -> Result<()> {
+  println!("open files...");
+  let (event_header, event_block_iter) = load_from_local_whirlpool_event_file(&whirlpool_event_file_path);
+
+  if let Some(event_header) = &event_header {
+    if !event_header.is_compatible_with(WhirlpoolFileKind::Event, WHIRLPOOL_EVENT_FORMAT_VERSION) {
+      bail!("unsupported event file (kind: {:?}, format version: {})", event_header.kind, event_header.format_version);
+    }
+  }
+
+  println!("detect arbitrage...");
+  let mut writer = csv::Writer::from_path(&arbitrage_file_path)?;
+  let mut detections = 0;
+  for event_block in event_block_iter {
+    for transaction in event_block.transactions.iter() {
+      for cycle in detect_arbitrage_cycles(transaction) {
+        let trades = cycle
+          .event_indexes
+          .iter()
+          .filter_map(|&index| match &transaction.events[index].event {
+            WhirlpoolEvent::Traded(traded) => Some(traded),
+            _ => None,
+          })
+          .collect::<Vec<_>>();
+
+        let location_route = cycle
+          .event_indexes
+          .iter()
+          .map(|&index| transaction.events[index].location())
+          .collect::<Vec<_>>()
+          .join(">");
+        let whirlpool_route = trades.iter().map(|traded| traded.whirlpool.as_str()).collect::<Vec<_>>().join(">");
+        let mint_route = std::iter::once(cycle.mint.as_str())
+          .chain(trades.iter().map(|traded| traded.transfer_out.mint.as_str()))
+          .collect::<Vec<_>>()
+          .join(">");
+
+        writer.serialize(ArbitrageDetection {
+          slot: event_block.slot,
+          block_time: event_block.block_time,
+          signature: transaction.signature.clone(),
+          payer: transaction.payer.clone(),
+          hops: trades.len(),
+          whirlpool_route,
+          mint_route,
+          location_route,
+          mint: cycle.mint.clone(),
+          input_amount: cycle.input_amount,
+          output_amount: cycle.output_amount,
+          profit: cycle.profit().to_string(),
+        })?;
+        detections += 1;
+      }
+    }
+  }
+  writer.flush()?;
+
+  println!("detected: {}", detections);
+
+  Ok(())
+}
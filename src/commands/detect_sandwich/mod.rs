@@ -0,0 +1,224 @@
+use crate::{
+  io::event::load_from_local_whirlpool_event_file,
+  model::{
+    event::{
+      definition::{TradeDirection, TradedEventPayload},
+      WhirlpoolEvent, WhirlpoolEventBlock, WhirlpoolEventEntry, WHIRLPOOL_EVENT_FORMAT_VERSION,
+    },
+    header::WhirlpoolFileKind,
+  },
+};
+use anyhow::{bail, Result};
+use bigdecimal::{BigDecimal, One};
+use serde_derive::Serialize;
+use std::collections::BTreeMap;
+
+/*
+
+A sandwich is detected in a pool within a slot as:
+
+  front:  attacker trades in a direction
+  victim: another wallet trades in the same direction (one or more transactions)
+  back:   attacker trades in the reverse direction
+
+The attacker of the front and back trades is matched by token authority or payer,
+and a victim must differ from the attacker in both. One row is reported per sandwich,
+with the victim trades joined by ';' in the order of execution.
+A trade is used as the front or the back of at most one sandwich.
+
+*/
+
+#[derive(Serialize, Debug)]
+struct SandwichDetection {
+  slot: u64,
+  block_time: i64,
+  whirlpool: String,
+  attacker: String,
+  victim_count: usize,
+  victims: String,
+  front_signature: String,
+  victim_signatures: String,
+  back_signature: String,
+  // <instruction index>.<sub index> of the trades in their transactions
+  front_location: String,
+  victim_locations: String,
+  back_location: String,
+  // direction of the front and victim trades
+  direction: &'static str,
+  mint_a: String,
+  mint_b: String,
+  // ratio of the price change by the trade itself, (new price / old price) - 1.
+  // for victims it is their own move, the front's effect on their execution price is not estimated
+  // (it would need their trades replayed without the front).
+  front_price_impact: String,
+  victim_price_changes: String,
+  // token amounts the attacker gained (negative if lost) through the front and back trades
+  attacker_profit_a: String,
+  attacker_profit_b: String,
+}
+
+struct Trade<'a> {
+  transaction_index: usize,
+  signature: &'a str,
+  payer: &'a str,
+  entry: &'a WhirlpoolEventEntry,
+  traded: &'a TradedEventPayload,
+}
+
+impl Trade<'_> {
+  fn same_wallet(&self, other: &Trade) -> bool {
+    self.traded.token_authority == other.traded.token_authority || self.payer == other.payer
+  }
+}
+
+pub async fn process(
+  whirlpool_event_file_path: String,
+  sandwich_file_path: String,
+) -> Result<()> {
+  println!("open files...");
+  let (event_header, event_block_iter) = load_from_local_whirlpool_event_file(&whirlpool_event_file_path);
+
+  if let Some(event_header) = &event_header {
+    if !event_header.is_compatible_with(WhirlpoolFileKind::Event, WHIRLPOOL_EVENT_FORMAT_VERSION) {
+      bail!("unsupported event file (kind: {:?}, format version: {})", event_header.kind, event_header.format_version);
+    }
+  }
+
+  println!("detect sandwiches...");
+  let mut writer = csv::Writer::from_path(&sandwich_file_path)?;
+  let mut detections = 0;
+  for event_block in event_block_iter {
+    for detection in detect_sandwiches(&event_block) {
+      writer.serialize(detection)?;
+      detections += 1;
+    }
+  }
+  writer.flush()?;
+
+  println!("detected: {}", detections);
+
+  Ok(())
+}
+
+fn detect_sandwiches(event_block: &WhirlpoolEventBlock) -> Vec<SandwichDetection> {
+  // trades in a pool keep the intra-slot order
+  let mut trades_by_whirlpool: BTreeMap<&str, Vec<Trade>> = BTreeMap::new();
+  for (transaction_index, transaction) in event_block.transactions.iter().enumerate() {
+    for entry in transaction.events.iter() {
+      if let WhirlpoolEvent::Traded(traded) = &entry.event {
+        trades_by_whirlpool.entry(&traded.whirlpool).or_default().push(Trade {
+          transaction_index,
+          signature: &transaction.signature,
+          payer: &transaction.payer,
+          entry,
+          traded,
+        });
+      }
+    }
+  }
+
+  let mut detections = vec![];
+  for (whirlpool, trades) in trades_by_whirlpool {
+    let mut used = vec![false; trades.len()];
+    for (front_index, front) in trades.iter().enumerate() {
+      if used[front_index] {
+        continue;
+      }
+
+      // the back is the first reverse trade of the attacker with victims between it and the front
+      let victims_before = |back: &Trade| {
+        trades[front_index + 1..].iter().take_while(|trade| trade.transaction_index < back.transaction_index).filter(|trade| {
+          trade.transaction_index > front.transaction_index
+            && trade.traded.trade_direction == front.traded.trade_direction
+            && !trade.same_wallet(front)
+        }).collect::<Vec<_>>()
+      };
+      let Some((back_index, victims)) = (front_index + 1..trades.len())
+        .filter(|&index| {
+          let trade = &trades[index];
+          !used[index]
+            && trade.transaction_index > front.transaction_index
+            && trade.same_wallet(front)
+            && trade.traded.trade_direction != front.traded.trade_direction
+        })
+        .map(|index| (index, victims_before(&trades[index])))
+        .find(|(_, victims)| !victims.is_empty())
+      else {
+        continue;
+      };
+
+      detections.push(build_detection(event_block, whirlpool, front, &victims, &trades[back_index]));
+      used[front_index] = true;
+      used[back_index] = true;
+    }
+  }
+  detections
+}
+
+fn build_detection(
+  event_block: &WhirlpoolEventBlock,
+  whirlpool: &str,
+  front: &Trade,
+  victims: &[&Trade],
+  back: &Trade,
+) -> SandwichDetection {
+  // in: paid by the attacker at the front, out: received at the front
+  let front_in = front.traded.transfer_in.amount as i128;
+  let front_out = front.traded.transfer_out.amount as i128;
+  let back_in = back.traded.transfer_in.amount as i128;
+  let back_out = back.traded.transfer_out.amount as i128;
+  let profit_in_mint = back_out - front_in;
+  let profit_out_mint = front_out - back_in;
+
+  let (direction, mint_a, mint_b, attacker_profit_a, attacker_profit_b) = match front.traded.trade_direction {
+    TradeDirection::AtoB => (
+      "ab",
+      &front.traded.transfer_in.mint,
+      &front.traded.transfer_out.mint,
+      profit_in_mint,
+      profit_out_mint,
+    ),
+    TradeDirection::BtoA => (
+      "ba",
+      &front.traded.transfer_out.mint,
+      &front.traded.transfer_in.mint,
+      profit_out_mint,
+      profit_in_mint,
+    ),
+  };
+
+  SandwichDetection {
+    slot: event_block.slot,
+    block_time: event_block.block_time,
+    whirlpool: whirlpool.to_string(),
+    attacker: front.traded.token_authority.clone(),
+    victim_count: victims.len(),
+    victims: join(victims, |victim| victim.traded.token_authority.clone()),
+    front_signature: front.signature.to_string(),
+    victim_signatures: join(victims, |victim| victim.signature.to_string()),
+    back_signature: back.signature.to_string(),
+    front_location: front.entry.location(),
+    victim_locations: join(victims, |victim| victim.entry.location()),
+    back_location: back.entry.location(),
+    direction,
+    mint_a: mint_a.clone(),
+    mint_b: mint_b.clone(),
+    front_price_impact: price_impact(front.traded).to_string(),
+    victim_price_changes: join(victims, |victim| price_impact(victim.traded).to_string()),
+    attacker_profit_a: attacker_profit_a.to_string(),
+    attacker_profit_b: attacker_profit_b.to_string(),
+  }
+}
+
+fn join(victims: &[&Trade], f: impl Fn(&Trade) -> String) -> String {
+  victims.iter().map(|victim| f(victim)).collect::<Vec<_>>().join(";")
+}
+
+// decimals cancel out in the ratio, so it is computed from sqrt prices
+fn price_impact(traded: &TradedEventPayload) -> BigDecimal {
+  if traded.old_sqrt_price == 0 {
+    return BigDecimal::from(0);
+  }
+  let sqrt_price_ratio = BigDecimal::from(traded.new_sqrt_price) / BigDecimal::from(traded.old_sqrt_price);
+  (sqrt_price_ratio.square() - BigDecimal::one()).round(12)
+}
@@ -0,0 +1,305 @@
+use crate::{
+  commands::CheckOutcome,
+  io::event::load_from_local_whirlpool_event_file,
+  model::event::{WhirlpoolEventBlock, WhirlpoolEventEntry, WhirlpoolEventTransaction},
+};
+use anyhow::Result;
+use serde_json::Value;
+use std::{
+  cmp::Ordering,
+  collections::{BTreeMap, HashMap},
+};
+
+#[derive(Default)]
+struct EventTypeSummary {
+  added: u64,
+  removed: u64,
+  changed: u64,
+}
+
+#[derive(Default)]
+struct DiffReport {
+  blocks_added: u64,
+  blocks_removed: u64,
+  transactions_added: u64,
+  transactions_removed: u64,
+  // index, payer or raw instructions differ
+  transactions_changed: u64,
+  events: BTreeMap<String, EventTypeSummary>,
+}
+
+pub async fn process(
+  old_whirlpool_event_file_path: String,
+  new_whirlpool_event_file_path: String,
+) -> Result<CheckOutcome> {
+  // both files are decoded into the current structs, so files of different format versions can be compared
+  let (old_header, old_block_iter) = load_from_local_whirlpool_event_file(&old_whirlpool_event_file_path);
+  let (new_header, new_block_iter) = load_from_local_whirlpool_event_file(&new_whirlpool_event_file_path);
+
+  if old_header != new_header {
+    println!("header: {:?} -> {:?}", old_header, new_header);
+  }
+
+  let mut report = DiffReport::default();
+  let mut old_blocks = old_block_iter.peekable();
+  let mut new_blocks = new_block_iter.peekable();
+
+  // blocks are ordered by slot in both files
+  loop {
+    let ordering = match (old_blocks.peek(), new_blocks.peek()) {
+      (None, None) => break,
+      (Some(_), None) => Ordering::Less,
+      (None, Some(_)) => Ordering::Greater,
+      (Some(old_block), Some(new_block)) => old_block.slot.cmp(&new_block.slot),
+    };
+
+    match ordering {
+      Ordering::Less => {
+        let old_block = old_blocks.next().unwrap();
+        println!("- slot {}", old_block.slot);
+        report.blocks_removed += 1;
+        for (index, transaction) in old_block.transactions.iter().enumerate() {
+          report.transaction_removed(old_block.slot, index, transaction);
+        }
+      }
+      Ordering::Greater => {
+        let new_block = new_blocks.next().unwrap();
+        println!("+ slot {}", new_block.slot);
+        report.blocks_added += 1;
+        for (index, transaction) in new_block.transactions.iter().enumerate() {
+          report.transaction_added(new_block.slot, index, transaction);
+        }
+      }
+      Ordering::Equal => {
+        let old_block = old_blocks.next().unwrap();
+        let new_block = new_blocks.next().unwrap();
+        report.diff_block(&old_block, &new_block);
+      }
+    }
+  }
+
+  println!("summary:");
+  println!("  blocks: +{} -{}", report.blocks_added, report.blocks_removed);
+  println!(
+    "  transactions: +{} -{} ~{}",
+    report.transactions_added, report.transactions_removed, report.transactions_changed
+  );
+  for (name, summary) in report.events.iter() {
+    println!("  {}: +{} -{} ~{}", name, summary.added, summary.removed, summary.changed);
+  }
+
+  let has_difference = old_header != new_header
+    || report.blocks_added + report.blocks_removed + report.transactions_added + report.transactions_removed + report.transactions_changed > 0
+    || report.events.values().any(|summary| summary.added + summary.removed + summary.changed > 0);
+  if has_difference {
+    return Ok(CheckOutcome::Failed);
+  }
+
+  Ok(CheckOutcome::Passed)
+}
+
+impl DiffReport {
+  fn diff_block(&mut self, old_block: &WhirlpoolEventBlock, new_block: &WhirlpoolEventBlock) {
+    let slot = old_block.slot;
+    if old_block.block_height != new_block.block_height {
+      println!("~ slot {} h: {} -> {}", slot, old_block.block_height, new_block.block_height);
+    }
+    if old_block.block_time != new_block.block_time {
+      println!("~ slot {} t: {} -> {}", slot, old_block.block_time, new_block.block_time);
+    }
+
+    // transactions are aligned by signature
+    let new_transactions: HashMap<&str, (usize, &WhirlpoolEventTransaction)> = new_block
+      .transactions
+      .iter()
+      .enumerate()
+      .map(|(index, transaction)| (transaction.signature.as_str(), (index, transaction)))
+      .collect();
+    let old_signatures: HashMap<&str, usize> = old_block
+      .transactions
+      .iter()
+      .enumerate()
+      .map(|(index, transaction)| (transaction.signature.as_str(), index))
+      .collect();
+
+    for (old_index, old_transaction) in old_block.transactions.iter().enumerate() {
+      match new_transactions.get(old_transaction.signature.as_str()) {
+        Some((_, new_transaction)) => self.diff_transaction(slot, old_index, old_transaction, new_transaction),
+        None => self.transaction_removed(slot, old_index, old_transaction),
+      }
+    }
+    for (new_index, new_transaction) in new_block.transactions.iter().enumerate() {
+      if !old_signatures.contains_key(new_transaction.signature.as_str()) {
+        self.transaction_added(slot, new_index, new_transaction);
+      }
+    }
+  }
+
+  // paths are based on the old file (the new file for added transactions)
+  fn diff_transaction(
+    &mut self,
+    slot: u64,
+    index: usize,
+    old_transaction: &WhirlpoolEventTransaction,
+    new_transaction: &WhirlpoolEventTransaction,
+  ) {
+    let prefix = format!("slot {} {}", slot, old_transaction.signature);
+    let mut changed = false;
+    if old_transaction.index != new_transaction.index {
+      println!("~ {} x[{}].i: {} -> {}", prefix, index, old_transaction.index, new_transaction.index);
+      changed = true;
+    }
+    if old_transaction.payer != new_transaction.payer {
+      println!("~ {} x[{}].p: {} -> {}", prefix, index, old_transaction.payer, new_transaction.payer);
+      changed = true;
+    }
+
+    // raw instructions are absent (null) if either file was converted without them
+    let mut differences = vec![];
+    diff_values(
+      &format!("x[{}].ri", index),
+      &serde_json::to_value(&old_transaction.raw_instructions).unwrap(),
+      &serde_json::to_value(&new_transaction.raw_instructions).unwrap(),
+      &mut differences,
+    );
+    for (path, old_value, new_value) in differences.iter() {
+      println!("~ {} {}: {} -> {}", prefix, path, old_value, new_value);
+    }
+    if changed || !differences.is_empty() {
+      self.transactions_changed += 1;
+    }
+
+    let old_values = old_transaction.events.iter().map(event_to_value).collect::<Vec<_>>();
+    let new_values = new_transaction.events.iter().map(event_to_value).collect::<Vec<_>>();
+
+    // events are aligned by name, so an inserted event does not shift the others into changes
+    for alignment in align_events(&old_values, &new_values) {
+      match alignment {
+        Alignment::Both(old_event_index, new_event_index) => {
+          let path = format!("x[{}].e[{}]", index, old_event_index);
+          let mut differences = vec![];
+          diff_values(&path, &old_values[old_event_index], &new_values[new_event_index], &mut differences);
+          if !differences.is_empty() {
+            self.summary_of(&old_values[old_event_index]).changed += 1;
+            for (path, old_value, new_value) in differences {
+              println!("~ {} {}: {} -> {}", prefix, path, old_value, new_value);
+            }
+          }
+        }
+        Alignment::Old(old_event_index) => {
+          self.summary_of(&old_values[old_event_index]).removed += 1;
+          println!("- {} x[{}].e[{}] {}", prefix, index, old_event_index, old_values[old_event_index]);
+        }
+        Alignment::New(new_event_index) => {
+          self.summary_of(&new_values[new_event_index]).added += 1;
+          println!("+ {} x[{}].e[{}] {}", prefix, index, new_event_index, new_values[new_event_index]);
+        }
+      }
+    }
+  }
+
+  fn transaction_removed(&mut self, slot: u64, index: usize, transaction: &WhirlpoolEventTransaction) {
+    println!("- slot {} {} x[{}]", slot, transaction.signature, index);
+    self.transactions_removed += 1;
+    for event in transaction.events.iter() {
+      self.summary_of(&event_to_value(event)).removed += 1;
+    }
+  }
+
+  fn transaction_added(&mut self, slot: u64, index: usize, transaction: &WhirlpoolEventTransaction) {
+    println!("+ slot {} {} x[{}]", slot, transaction.signature, index);
+    self.transactions_added += 1;
+    for event in transaction.events.iter() {
+      self.summary_of(&event_to_value(event)).added += 1;
+    }
+  }
+
+  fn summary_of(&mut self, event: &Value) -> &mut EventTypeSummary {
+    self.events.entry(event_name(event).to_string()).or_default()
+  }
+}
+
+// indexes (ii, si) are included, so a reordered event is reported as a change
+fn event_to_value(entry: &WhirlpoolEventEntry) -> Value {
+  serde_json::to_value(entry).unwrap()
+}
+
+fn event_name(event: &Value) -> &str {
+  event.get("n").and_then(Value::as_str).unwrap_or("?")
+}
+
+enum Alignment {
+  Both(usize, usize),
+  Old(usize),
+  New(usize),
+}
+
+// longest common subsequence of event names (events in a transaction are few)
+fn align_events(old_events: &[Value], new_events: &[Value]) -> Vec<Alignment> {
+  let (n, m) = (old_events.len(), new_events.len());
+  let mut lcs = vec![vec![0usize; m + 1]; n + 1];
+  for i in (0..n).rev() {
+    for j in (0..m).rev() {
+      lcs[i][j] = if event_name(&old_events[i]) == event_name(&new_events[j]) {
+        lcs[i + 1][j + 1] + 1
+      } else {
+        lcs[i + 1][j].max(lcs[i][j + 1])
+      };
+    }
+  }
+
+  let mut alignments = vec![];
+  let (mut i, mut j) = (0, 0);
+  while i < n && j < m {
+    if event_name(&old_events[i]) == event_name(&new_events[j]) {
+      alignments.push(Alignment::Both(i, j));
+      i += 1;
+      j += 1;
+    } else if lcs[i + 1][j] >= lcs[i][j + 1] {
+      alignments.push(Alignment::Old(i));
+      i += 1;
+    } else {
+      alignments.push(Alignment::New(j));
+      j += 1;
+    }
+  }
+  alignments.extend((i..n).map(Alignment::Old));
+  alignments.extend((j..m).map(Alignment::New));
+  alignments
+}
+
+// collects (path, old value, new value) of the leaves that differ
+fn diff_values(path: &str, old_value: &Value, new_value: &Value, differences: &mut Vec<(String, String, String)>) {
+  match (old_value, new_value) {
+    (Value::Object(old_object), Value::Object(new_object)) => {
+      for (key, old_field) in old_object.iter() {
+        let field_path = format!("{}.{}", path, key);
+        match new_object.get(key) {
+          Some(new_field) => diff_values(&field_path, old_field, new_field, differences),
+          None => differences.push((field_path, old_field.to_string(), "(none)".to_string())),
+        }
+      }
+      for (key, new_field) in new_object.iter() {
+        if !old_object.contains_key(key) {
+          differences.push((format!("{}.{}", path, key), "(none)".to_string(), new_field.to_string()));
+        }
+      }
+    }
+    (Value::Array(old_array), Value::Array(new_array)) => {
+      for index in 0..old_array.len().max(new_array.len()) {
+        let item_path = format!("{}[{}]", path, index);
+        match (old_array.get(index), new_array.get(index)) {
+          (Some(old_item), Some(new_item)) => diff_values(&item_path, old_item, new_item, differences),
+          (Some(old_item), None) => differences.push((item_path, old_item.to_string(), "(none)".to_string())),
+          (None, Some(new_item)) => differences.push((item_path, "(none)".to_string(), new_item.to_string())),
+          (None, None) => unreachable!(),
+        }
+      }
+    }
+    _ => {
+      if old_value != new_value {
+        differences.push((path.to_string(), old_value.to_string(), new_value.to_string()));
+      }
+    }
+  }
+}
@@ -0,0 +1,251 @@
+use crate::{
+  commands::EventFormatArgs,
+  io::{compression::Compression, event::EventEncoding},
+  model::event::{migration::LEGACY_EVENT_FORMAT_VERSION, WHIRLPOOL_EVENT_FORMAT_VERSION},
+};
+use anyhow::{bail, Result};
+use serde_derive::{Deserialize, Serialize};
+use std::{
+  fs::{self, File, OpenOptions},
+  io::{Seek, SeekFrom, Write},
+  path::Path,
+};
+
+/*
+
+Event Conversion Checkpoint
+
+Written next to the output event file:
+
+  <event file>.checkpoint.json                  (this struct)
+  <event file>.checkpoint.<blocks>.state.json.gz (account store after <blocks> transaction blocks)
+
+Checkpoints are taken only at chunk boundaries of the output, so the output is byte-identical
+whether or not the conversion was interrupted and resumed.
+Compressed output must be seekable (independently compressed chunks) to be truncated and continued at a checkpoint.
+
+*/
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct EventCheckpoint {
+  // the last processed transaction block
+  pub slot: u64,
+  pub block_height: u64,
+  pub block_time: i64,
+
+  // position of the transaction iterator
+  pub processed_transaction_blocks: u64,
+  // the output file is truncated to this offset on resume
+  pub output_offset: u64,
+  pub state_file_name: String,
+
+  // resume must produce the same output format
+  #[serde(default = "legacy_event_format_version")]
+  pub format_version: u32,
+  pub with_header: bool,
+  pub encoding: EventEncoding,
+  #[serde(default)]
+  pub with_raw_instructions: bool,
+  #[serde(default)]
+  pub long_keys: bool,
+  pub compression: Compression,
+  pub compression_level: Option<i32>,
+  pub seekable: bool,
+}
+
+impl EventCheckpoint {
+  pub fn load(whirlpool_event_file_path: &str) -> Result<Option<Self>> {
+    let checkpoint_file_path = checkpoint_file_path(whirlpool_event_file_path);
+    if !Path::new(&checkpoint_file_path).exists() {
+      return Ok(None);
+    }
+    let json = fs::read_to_string(checkpoint_file_path)?;
+    Ok(Some(serde_json::from_str(&json)?))
+  }
+
+  // rename is atomic, so a crash while saving leaves the previous checkpoint intact.
+  // the content is synced before the rename, and the rename itself by syncing the directory.
+  pub fn save(&self, whirlpool_event_file_path: &str) -> Result<()> {
+    let checkpoint_file_path = checkpoint_file_path(whirlpool_event_file_path);
+    let tmp_file_path = format!("{}.tmp", checkpoint_file_path);
+    let mut f = File::create(&tmp_file_path)?;
+    f.write_all(serde_json::to_string(self)?.as_bytes())?;
+    f.sync_all()?;
+    fs::rename(tmp_file_path, &checkpoint_file_path)?;
+    crate::io::sync_parent_directory(&checkpoint_file_path)?;
+    Ok(())
+  }
+
+  pub fn remove(whirlpool_event_file_path: &str) -> Result<()> {
+    if let Some(checkpoint) = Self::load(whirlpool_event_file_path)? {
+      fs::remove_file(checkpoint.state_file_path(whirlpool_event_file_path))?;
+      fs::remove_file(checkpoint_file_path(whirlpool_event_file_path))?;
+    }
+    Ok(())
+  }
+
+  pub fn state_file_path(&self, whirlpool_event_file_path: &str) -> String {
+    sibling_file_path(whirlpool_event_file_path, &self.state_file_name)
+  }
+
+  // drops the output written after the checkpoint, writing continues at the end of the kept part
+  pub fn open_output(&self, whirlpool_event_file_path: &str) -> Result<File> {
+    let mut f = OpenOptions::new().write(true).open(whirlpool_event_file_path)?;
+    f.set_len(self.output_offset)?;
+    f.seek(SeekFrom::End(0))?;
+    Ok(f)
+  }
+
+  pub fn ensure_same_output(&self, format: &EventFormatArgs) -> Result<()> {
+    if self.format_version != WHIRLPOOL_EVENT_FORMAT_VERSION {
+      bail!(
+        "checkpoint was taken with event format version {}, but the current version is {}",
+        self.format_version, WHIRLPOOL_EVENT_FORMAT_VERSION
+      );
+    }
+    if self.with_header != format.with_header
+      || self.encoding != format.encoding
+      || self.with_raw_instructions != format.with_raw_instructions
+      || self.long_keys != format.long_keys
+      || self.compression != format.compression
+      || self.compression_level != format.compression_level
+      || self.seekable != format.seekable
+    {
+      bail!(
+        "output options differ from the checkpoint (with_header: {}, encoding: {:?}, with_raw_instructions: {}, long_keys: {}, compression: {:?}, compression_level: {:?}, seekable: {})",
+        self.with_header, self.encoding, self.with_raw_instructions, self.long_keys, self.compression, self.compression_level, self.seekable
+      );
+    }
+    Ok(())
+  }
+}
+
+// checkpoints taken before the version was recorded
+fn legacy_event_format_version() -> u32 {
+  LEGACY_EVENT_FORMAT_VERSION
+}
+
+pub fn state_file_name(whirlpool_event_file_path: &str, processed_transaction_blocks: u64) -> String {
+  let event_file_name = Path::new(whirlpool_event_file_path)
+    .file_name()
+    .map(|name| name.to_string_lossy().to_string())
+    .unwrap_or_default();
+  format!("{}.checkpoint.{}.state.json.gz", event_file_name, processed_transaction_blocks)
+}
+
+pub(super) fn checkpoint_file_path(whirlpool_event_file_path: &str) -> String {
+  format!("{}.checkpoint.json", whirlpool_event_file_path)
+}
+
+fn sibling_file_path(whirlpool_event_file_path: &str, file_name: &str) -> String {
+  Path::new(whirlpool_event_file_path)
+    .with_file_name(file_name)
+    .to_string_lossy()
+    .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{
+    io::event::{EventFileWriter, BLOCKS_PER_CHUNK},
+    model::event::fixtures,
+    test_support::{temp_file_path, write_event_file},
+  };
+
+  fn format(encoding: EventEncoding, compression: Compression) -> EventFormatArgs {
+    EventFormatArgs {
+      with_header: true,
+      encoding,
+      compression,
+      compression_level: None,
+      seekable: true,
+      with_raw_instructions: false,
+      long_keys: false,
+    }
+  }
+
+  fn checkpoint(format: &EventFormatArgs, processed_transaction_blocks: u64, output_offset: u64) -> EventCheckpoint {
+    EventCheckpoint {
+      slot: 0,
+      block_height: 0,
+      block_time: 0,
+      processed_transaction_blocks,
+      output_offset,
+      state_file_name: String::new(),
+      format_version: WHIRLPOOL_EVENT_FORMAT_VERSION,
+      with_header: format.with_header,
+      encoding: format.encoding,
+      with_raw_instructions: format.with_raw_instructions,
+      long_keys: format.long_keys,
+      compression: format.compression,
+      compression_level: format.compression_level,
+      seekable: format.seekable,
+    }
+  }
+
+  #[tokio::test]
+  async fn resumed_output_is_byte_identical() {
+    // the checkpoint is taken in the middle of the file, and a partial chunk follows it
+    let blocks = BLOCKS_PER_CHUNK * 3 + 17;
+    let checkpoint_blocks = BLOCKS_PER_CHUNK * 2;
+
+    for encoding in [EventEncoding::Jsonl, EventEncoding::Binary] {
+      for compression in [Compression::Gzip, Compression::Zstd, Compression::None] {
+        let format = format(encoding, compression);
+        let expected_path = temp_file_path(&format!("expected-{:?}-{:?}", encoding, compression));
+        let resumed_path = temp_file_path(&format!("resumed-{:?}-{:?}", encoding, compression));
+        write_event_file(&expected_path, Some(fixtures::current_header()), fixtures::generate_blocks(blocks), encoding, compression, format.seekable).await;
+
+        // the interrupted run writes past the checkpoint before it stops
+        let f = File::create(&resumed_path).unwrap();
+        let mut writer = EventFileWriter::new(f, Some(fixtures::current_header()), format.encoding, format.key_style(), format.compression, format.compression_level, format.seekable).unwrap();
+        let mut saved_checkpoint = None;
+        for (i, block) in fixtures::generate_blocks(checkpoint_blocks + 100).into_iter().enumerate() {
+          writer.write(block).await.unwrap();
+          if i + 1 == checkpoint_blocks {
+            assert!(writer.is_at_chunk_boundary());
+            let output_offset = writer.sync().await.unwrap();
+            saved_checkpoint = Some(checkpoint(&format, checkpoint_blocks as u64, output_offset));
+          }
+        }
+        writer.finish().await.unwrap();
+        saved_checkpoint.unwrap().save(&resumed_path).unwrap();
+
+        // resume without a header from the blocks after the checkpoint
+        let resume_checkpoint = EventCheckpoint::load(&resumed_path).unwrap().unwrap();
+        resume_checkpoint.ensure_same_output(&format).unwrap();
+        let f = resume_checkpoint.open_output(&resumed_path).unwrap();
+        let mut writer = EventFileWriter::new(f, None, format.encoding, format.key_style(), format.compression, format.compression_level, format.seekable).unwrap();
+        for block in fixtures::generate_blocks(blocks).into_iter().skip(resume_checkpoint.processed_transaction_blocks as usize) {
+          writer.write(block).await.unwrap();
+        }
+        writer.finish().await.unwrap();
+
+        let expected = fs::read(&expected_path).unwrap();
+        let resumed = fs::read(&resumed_path).unwrap();
+        assert!(expected == resumed, "{:?} {:?}", encoding, compression);
+
+        fs::remove_file(expected_path).unwrap();
+        fs::remove_file(checkpoint_file_path(&resumed_path)).unwrap();
+        fs::remove_file(resumed_path).unwrap();
+      }
+    }
+  }
+
+  #[test]
+  fn resume_with_different_output_options_is_rejected() {
+    let saved = format(EventEncoding::Jsonl, Compression::Gzip);
+    let resumed = checkpoint(&saved, 1, 0);
+    assert!(resumed.ensure_same_output(&saved).is_ok());
+    assert!(resumed.ensure_same_output(&format(EventEncoding::Binary, Compression::Gzip)).is_err());
+    assert!(resumed.ensure_same_output(&format(EventEncoding::Jsonl, Compression::Zstd)).is_err());
+
+    let older = EventCheckpoint {
+      format_version: WHIRLPOOL_EVENT_FORMAT_VERSION - 1,
+      ..resumed
+    };
+    assert!(older.ensure_same_output(&saved).is_err());
+  }
+}
@@ -2,6 +2,8 @@ use replay_engine::replay_engine::ReplayEngine;
 use std::collections::HashMap;
 use whirlpool_replayer::{schema::WhirlpoolTransaction, serde::AccountDataStoreConfig, Slot};
 
+pub type WhirlpoolTransactionIter = Box<dyn Iterator<Item = WhirlpoolTransaction> + Send>;
+
 pub fn build_with_local_file_storage(
     whirlpool_state_file_path: String,
     whirlpool_token_file_path: String,
@@ -9,8 +11,10 @@ pub fn build_with_local_file_storage(
     account_data_store_config: &AccountDataStoreConfig,
 ) -> (
     ReplayEngine,
-    Box<dyn Iterator<Item = WhirlpoolTransaction> + Send>,
+    Vec<u8>,
+    WhirlpoolTransactionIter,
     HashMap<String, u8>,
+    Slot,
 ) {
     let state = whirlpool_replayer::io::load_from_local_whirlpool_state_file(
         &whirlpool_state_file_path,
@@ -22,6 +26,11 @@ pub fn build_with_local_file_storage(
         &whirlpool_transaction_file_path,
     );
 
+    let state_slot = Slot::new(state.slot, state.block_height, state.block_time);
+
+    // the engine does not expose program data, so a copy is returned for checkpoints
+    let program_data = state.program_data.clone();
+
     let replay_engine = ReplayEngine::new(
         Slot::new(state.slot, state.block_height, state.block_time),
         state.program_data,
@@ -34,5 +43,5 @@ pub fn build_with_local_file_storage(
         .map(|t| (t.mint.clone(), t.decimals))
         .collect();
 
-    (replay_engine, Box::new(transaction_iter), decimals)
+    (replay_engine, program_data, Box::new(transaction_iter), decimals, state_slot)
 }
@@ -1,104 +1,343 @@
-use crate::model::event::{
-  convert::build_whirlpool_events, definition::ProgramDeployedEventPayload, WhirlpoolEvent,
-  WhirlpoolEventBlock, WhirlpoolEventTransaction,
+use crate::{
+  commands::{EventFormatArgs, ReplayStateArgs},
+  io::{compression::Compression, event::EventFileWriter},
+  model::{
+    event::WHIRLPOOL_EVENT_FORMAT_VERSION,
+    header::{WhirlpoolFileHeader, WhirlpoolFileKind},
+  },
 };
-use anyhow::Result;
-use flate2::write::GzEncoder;
-use replay_engine::decoded_instructions;
-use std::{fs::File, io::LineWriter, io::Write};
-use whirlpool_replayer::{serde::AccountDataStoreConfig, Slot};
+use anyhow::{bail, Result};
+use checkpoint::EventCheckpoint;
+use replay::EventBlockReplayer;
+use std::fs::File;
+use whirlpool_replayer::serde::AccountDataStoreConfig;
 
-mod io;
+mod checkpoint;
+pub mod io;
+pub mod replay;
 
 pub async fn process(
   whirlpool_state_file_path: String,
   whirlpool_token_file_path: String,
   whirlpool_transaction_file_path: String,
   whirlpool_event_file_path: String,
+  format: EventFormatArgs,
+  replay_state: ReplayStateArgs,
 ) -> Result<()> {
-  let f = File::create(whirlpool_event_file_path).unwrap();
-  let encoder = GzEncoder::new(f, flate2::Compression::default());
-  let mut writer = LineWriter::new(encoder);
+  // a compressed stream cannot be truncated and continued at a checkpoint, independent chunks can
+  if replay_state.checkpoint_interval.is_some() && format.compression != Compression::None && !format.seekable {
+    bail!("checkpoints of compressed output require --seekable");
+  }
+
+  let ConversionStart {
+    resume_checkpoint,
+    replay_state_file_path,
+    header,
+  } = start_conversion(
+    whirlpool_state_file_path,
+    &whirlpool_token_file_path,
+    &whirlpool_transaction_file_path,
+    &whirlpool_event_file_path,
+    &format,
+    replay_state.resume,
+  )?;
 
   // build replayer
-  let (mut replay_engine, mut transaction_iter, decimals) = io::build_with_local_file_storage(
-      whirlpool_state_file_path,
+  let (replay_engine, program_data, mut transaction_iter, decimals, state_slot) = io::build_with_local_file_storage(
+      replay_state_file_path,
       whirlpool_token_file_path,
       whirlpool_transaction_file_path,
       &AccountDataStoreConfig::OnDisk(None),
   );
 
+  let mut replayer = EventBlockReplayer::new(replay_engine, program_data, decimals, format.with_raw_instructions);
+
+  let header = header.map(|header| WhirlpoolFileHeader {
+      source_state_slot: state_slot.slot,
+      ..header
+  });
+
+  let (f, mut processed_transaction_blocks) = open_output(
+    &mut transaction_iter,
+    |whirlpool_transaction| whirlpool_transaction.slot,
+    resume_checkpoint.as_ref(),
+    &whirlpool_event_file_path,
+  )?;
+  let mut last_checkpoint_transaction_blocks = processed_transaction_blocks;
+  let mut last_slot = state_slot;
+
+  // replay runs on this thread, serialization and compression run on worker threads
+  let mut writer = EventFileWriter::new(f, header, format.encoding, format.key_style(), format.compression, format.compression_level, format.seekable)?;
+
   let mut next_whirlpool_transaction = transaction_iter.next();
   while next_whirlpool_transaction.is_some() {
-      let whirlpool_transaction = next_whirlpool_transaction.unwrap();
-
-      let slot = Slot {
-          slot: whirlpool_transaction.slot,
-          block_height: whirlpool_transaction.block_height,
-          block_time: whirlpool_transaction.block_time,
-      };
-
-      replay_engine.update_slot(slot.slot, slot.block_height, slot.block_time);
-
-      let mut event_block_transactions: Vec<WhirlpoolEventTransaction> = Vec::new();
-
-      for transaction in whirlpool_transaction.transactions {
-          let mut events: Vec<WhirlpoolEvent> = vec![];
-
-          for instruction in transaction.clone().instructions {
-              let name = instruction.name;
-              let payload = instruction.payload.to_string();
-              let decoded = decoded_instructions::from_json(&name, &payload).unwrap();
-
-              match decoded {
-                  decoded_instructions::DecodedInstruction::ProgramDeployInstruction(
-                      deploy_instruction,
-                  ) => {
-                      replay_engine.update_program_data(deploy_instruction.program_data);
-
-                      events.push(WhirlpoolEvent::ProgramDeployed(
-                          ProgramDeployedEventPayload {},
-                      ));
-                  }
-                  decoded_instructions::DecodedInstruction::WhirlpoolInstruction(
-                      whirlpool_instruction,
-                  ) => {
-                      let result = replay_engine
-                          .replay_instruction(&whirlpool_instruction)
-                          .unwrap();
-
-                      events.extend(build_whirlpool_events(
-                          &whirlpool_instruction,
-                          &decimals,
-                          replay_engine.get_accounts(),
-                          &result.snapshot,
-                      ));
-                  }
-              }
+      let (slot, event_block) = replayer.replay(next_whirlpool_transaction.unwrap());
+
+      writer.write(event_block).await?;
+      processed_transaction_blocks += 1;
+
+      // checkpoints wait for a chunk boundary to keep the output identical
+      if let Some(checkpoint_interval) = replay_state.checkpoint_interval {
+        if processed_transaction_blocks - last_checkpoint_transaction_blocks >= checkpoint_interval && writer.is_at_chunk_boundary() {
+          let previous_checkpoint = EventCheckpoint::load(&whirlpool_event_file_path)?;
+
+          let output_offset = writer.sync().await?;
+          let state_file_name = checkpoint::state_file_name(&whirlpool_event_file_path, processed_transaction_blocks);
+          let new_checkpoint = EventCheckpoint {
+            slot: slot.slot,
+            block_height: slot.block_height,
+            block_time: slot.block_time,
+            processed_transaction_blocks,
+            output_offset,
+            state_file_name,
+            format_version: WHIRLPOOL_EVENT_FORMAT_VERSION,
+            with_header: format.with_header,
+            encoding: format.encoding,
+            with_raw_instructions: format.with_raw_instructions,
+            long_keys: format.long_keys,
+            compression: format.compression,
+            compression_level: format.compression_level,
+            seekable: format.seekable,
+          };
+
+          crate::io::state::save_to_local_whirlpool_state_file(
+            &new_checkpoint.state_file_path(&whirlpool_event_file_path),
+            slot.slot,
+            slot.block_height,
+            slot.block_time,
+            replayer.replay_engine.get_accounts(),
+            &replayer.program_data,
+          )?;
+          new_checkpoint.save(&whirlpool_event_file_path)?;
+
+          // the previous state is no longer referenced
+          if let Some(previous_checkpoint) = previous_checkpoint {
+            let previous_state_file_path = previous_checkpoint.state_file_path(&whirlpool_event_file_path);
+            if previous_state_file_path != new_checkpoint.state_file_path(&whirlpool_event_file_path) {
+              std::fs::remove_file(previous_state_file_path)?;
+            }
           }
 
-          event_block_transactions.push(WhirlpoolEventTransaction {
-              signature: transaction.signature,
-              payer: transaction.payer,
-              events,
-          });
+          last_checkpoint_transaction_blocks = processed_transaction_blocks;
+        }
       }
 
-      let event_block = WhirlpoolEventBlock {
-          slot: whirlpool_transaction.slot,
-          block_height: whirlpool_transaction.block_height,
-          block_time: whirlpool_transaction.block_time,
-          transactions: event_block_transactions,
-      };
+      last_slot = slot;
+      next_whirlpool_transaction = transaction_iter.next();
+  }
 
-      let jsonl = serde_json::to_string(&event_block).unwrap();
-      writer.write_all(jsonl.as_bytes()).unwrap();
-      writer.write_all(b"\n").unwrap();
+  writer.finish().await?;
 
-      next_whirlpool_transaction = transaction_iter.next();
+  // the state after the last transaction block is the input state of the next day
+  if let Some(output_whirlpool_state_file_path) = replay_state.output_whirlpool_state_file_path {
+    crate::io::state::save_to_local_whirlpool_state_file(
+      &output_whirlpool_state_file_path,
+      last_slot.slot,
+      last_slot.block_height,
+      last_slot.block_time,
+      replayer.replay_engine.get_accounts(),
+      &replayer.program_data,
+    )?;
   }
 
-  writer.flush().unwrap();
+  // the output is complete, checkpoints are no longer needed
+  EventCheckpoint::remove(&whirlpool_event_file_path)?;
 
   Ok(())
 }
+
+struct ConversionStart {
+  resume_checkpoint: Option<EventCheckpoint>,
+  // resume replays from the account store saved at the checkpoint
+  replay_state_file_path: String,
+  // the header has already been written when resuming
+  header: Option<WhirlpoolFileHeader>,
+}
+
+fn start_conversion(
+  whirlpool_state_file_path: String,
+  whirlpool_token_file_path: &str,
+  whirlpool_transaction_file_path: &str,
+  whirlpool_event_file_path: &str,
+  format: &EventFormatArgs,
+  resume: bool,
+) -> Result<ConversionStart> {
+  let resume_checkpoint = if resume {
+    let resume_checkpoint = match EventCheckpoint::load(whirlpool_event_file_path)? {
+      Some(resume_checkpoint) => resume_checkpoint,
+      None => bail!("no checkpoint found for {}", whirlpool_event_file_path),
+    };
+    resume_checkpoint.ensure_same_output(format)?;
+    Some(resume_checkpoint)
+  } else {
+    None
+  };
+
+  let header = if format.with_header && resume_checkpoint.is_none() {
+    Some(crate::io::build_file_header(
+      WhirlpoolFileKind::Event,
+      WHIRLPOOL_EVENT_FORMAT_VERSION,
+      0, // filled after loading state
+      &[&whirlpool_state_file_path, whirlpool_token_file_path, whirlpool_transaction_file_path],
+    )?)
+  } else {
+    None
+  };
+
+  let replay_state_file_path = match &resume_checkpoint {
+    Some(resume_checkpoint) => resume_checkpoint.state_file_path(whirlpool_event_file_path),
+    None => whirlpool_state_file_path,
+  };
+
+  Ok(ConversionStart {
+    resume_checkpoint,
+    replay_state_file_path,
+    header,
+  })
+}
+
+// skips the transaction blocks converted before the checkpoint, and opens the output to continue after them.
+// returns the output and the number of processed transaction blocks.
+fn open_output<T>(
+  transaction_iter: &mut impl Iterator<Item = T>,
+  slot_of: impl Fn(&T) -> u64,
+  resume_checkpoint: Option<&EventCheckpoint>,
+  whirlpool_event_file_path: &str,
+) -> Result<(File, u64)> {
+  match resume_checkpoint {
+    Some(resume_checkpoint) => {
+      let last_processed = transaction_iter
+        .by_ref()
+        .take(resume_checkpoint.processed_transaction_blocks as usize)
+        .last();
+      if last_processed.map(|whirlpool_transaction| slot_of(&whirlpool_transaction)) != Some(resume_checkpoint.slot) {
+        bail!("transaction file does not match the checkpoint at slot {}", resume_checkpoint.slot);
+      }
+
+      Ok((
+        resume_checkpoint.open_output(whirlpool_event_file_path)?,
+        resume_checkpoint.processed_transaction_blocks,
+      ))
+    }
+    None => Ok((File::create(whirlpool_event_file_path)?, 0)),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{io::event::EventEncoding, test_support::temp_file_path};
+  use std::{fs, path::Path};
+
+  fn format(with_header: bool) -> EventFormatArgs {
+    EventFormatArgs {
+      with_header,
+      encoding: EventEncoding::Jsonl,
+      compression: Compression::None,
+      compression_level: None,
+      seekable: false,
+      with_raw_instructions: false,
+      long_keys: false,
+    }
+  }
+
+  fn checkpoint(format: &EventFormatArgs, slot: u64, processed_transaction_blocks: u64, output_offset: u64, state_file_name: String) -> EventCheckpoint {
+    EventCheckpoint {
+      slot,
+      block_height: slot,
+      block_time: 0,
+      processed_transaction_blocks,
+      output_offset,
+      state_file_name,
+      format_version: WHIRLPOOL_EVENT_FORMAT_VERSION,
+      with_header: format.with_header,
+      encoding: format.encoding,
+      with_raw_instructions: format.with_raw_instructions,
+      long_keys: format.long_keys,
+      compression: format.compression,
+      compression_level: format.compression_level,
+      seekable: format.seekable,
+    }
+  }
+
+  #[test]
+  fn conversion_starts_from_the_input_state_with_a_header() {
+    let input_file_paths = ["state", "token", "transaction"].map(|name| temp_file_path(&format!("start-{}", name)));
+    for path in input_file_paths.iter() {
+      fs::write(path, path.as_bytes()).unwrap();
+    }
+    let [state, token, transaction] = &input_file_paths;
+    let event = temp_file_path("start-event");
+
+    let start = start_conversion(state.clone(), token, transaction, &event, &format(true), false).unwrap();
+    assert!(start.resume_checkpoint.is_none());
+    assert_eq!(&start.replay_state_file_path, state);
+    assert_eq!(start.header.unwrap().input_files.len(), 3);
+
+    let start = start_conversion(state.clone(), token, transaction, &event, &format(false), false).unwrap();
+    assert!(start.header.is_none());
+
+    for path in input_file_paths.iter() {
+      fs::remove_file(path).unwrap();
+    }
+  }
+
+  #[test]
+  fn resume_starts_from_the_checkpoint_state_without_a_header() {
+    let format = format(true);
+    let event = temp_file_path("resume-event");
+    let saved = checkpoint(&format, 12, 3, 0, checkpoint::state_file_name(&event, 3));
+    saved.save(&event).unwrap();
+
+    // the input files are not read, the header has already been written
+    let start = start_conversion(temp_file_path("missing-state"), "missing-token", "missing-transaction", &event, &format, true).unwrap();
+    assert_eq!(start.resume_checkpoint, Some(saved.clone()));
+    assert_eq!(
+      start.replay_state_file_path,
+      Path::new(&event).with_file_name(&saved.state_file_name).to_string_lossy()
+    );
+    assert!(start.header.is_none());
+
+    // the checkpoint must exist and match the output options
+    let other_event = temp_file_path("resume-other-event");
+    assert!(start_conversion(String::new(), "", "", &other_event, &format, true).is_err());
+    let gzip = EventFormatArgs {
+      compression: Compression::Gzip,
+      seekable: true,
+      ..format
+    };
+    assert!(start_conversion(String::new(), "", "", &event, &gzip, true).is_err());
+
+    fs::remove_file(checkpoint::checkpoint_file_path(&event)).unwrap();
+  }
+
+  #[test]
+  fn resume_skips_processed_transaction_blocks() {
+    let format = format(false);
+    let event = temp_file_path("skip-event");
+    fs::write(&event, [b'x'; 100]).unwrap();
+
+    // transaction blocks at slot 10, 11, ..., and the checkpoint after the third one
+    let mut slots = 10u64..20;
+    let resume_checkpoint = checkpoint(&format, 12, 3, 40, String::new());
+    let (_f, processed_transaction_blocks) = open_output(&mut slots, |slot| *slot, Some(&resume_checkpoint), &event).unwrap();
+    assert_eq!(processed_transaction_blocks, 3);
+    assert_eq!(slots.next(), Some(13));
+    assert_eq!(fs::metadata(&event).unwrap().len(), 40);
+
+    // the transaction file must reach the slot of the checkpoint
+    let mismatched = checkpoint(&format, 11, 3, 40, String::new());
+    assert!(open_output(&mut (10u64..20), |slot| *slot, Some(&mismatched), &event).is_err());
+    let beyond_end = checkpoint(&format, 12, 30, 40, String::new());
+    assert!(open_output(&mut (10u64..20), |slot| *slot, Some(&beyond_end), &event).is_err());
+
+    // a new conversion starts from the first transaction block with an empty output
+    let mut slots = 10u64..20;
+    let (_f, processed_transaction_blocks) = open_output(&mut slots, |slot| *slot, None, &event).unwrap();
+    assert_eq!(processed_transaction_blocks, 0);
+    assert_eq!(slots.next(), Some(10));
+    assert_eq!(fs::metadata(&event).unwrap().len(), 0);
+
+    fs::remove_file(event).unwrap();
+  }
+}
@@ -0,0 +1,108 @@
+use crate::model::event::{
+  convert::build_whirlpool_events, definition::ProgramDeployedEventPayload, RawInstruction, WhirlpoolEvent,
+  WhirlpoolEventBlock, WhirlpoolEventEntry, WhirlpoolEventTransaction,
+};
+use replay_engine::{decoded_instructions, replay_engine::ReplayEngine};
+use std::collections::HashMap;
+use whirlpool_replayer::{schema::WhirlpoolTransaction, Slot};
+
+// replays transaction blocks and builds their event blocks (used by the event and verify commands)
+pub struct EventBlockReplayer {
+  pub replay_engine: ReplayEngine,
+  // the engine does not expose program data, so the latest deployed one is kept for saving the state
+  pub program_data: Vec<u8>,
+  decimals: HashMap<String, u8>,
+  with_raw_instructions: bool,
+}
+
+impl EventBlockReplayer {
+  pub fn new(
+    replay_engine: ReplayEngine,
+    program_data: Vec<u8>,
+    decimals: HashMap<String, u8>,
+    with_raw_instructions: bool,
+  ) -> Self {
+    Self {
+      replay_engine,
+      program_data,
+      decimals,
+      with_raw_instructions,
+    }
+  }
+
+  pub fn replay(&mut self, whirlpool_transaction: WhirlpoolTransaction) -> (Slot, WhirlpoolEventBlock) {
+    let slot = Slot {
+      slot: whirlpool_transaction.slot,
+      block_height: whirlpool_transaction.block_height,
+      block_time: whirlpool_transaction.block_time,
+    };
+
+    self.replay_engine.update_slot(slot.slot, slot.block_height, slot.block_time);
+
+    let mut event_block_transactions: Vec<WhirlpoolEventTransaction> = Vec::new();
+
+    for transaction in whirlpool_transaction.transactions {
+      let mut events: Vec<WhirlpoolEventEntry> = vec![];
+      let mut raw_instructions: Vec<RawInstruction> = vec![];
+
+      for (instruction_index, instruction) in transaction.instructions.into_iter().enumerate() {
+        let name = instruction.name;
+        let payload = instruction.payload.to_string();
+        let decoded = decoded_instructions::from_json(&name, &payload).unwrap();
+        if self.with_raw_instructions {
+          raw_instructions.push(RawInstruction {
+            name,
+            payload: instruction.payload,
+          });
+        }
+
+        match decoded {
+          decoded_instructions::DecodedInstruction::ProgramDeployInstruction(deploy_instruction) => {
+            self.program_data = deploy_instruction.program_data;
+            self.replay_engine.update_program_data(self.program_data.clone());
+
+            events.push(WhirlpoolEventEntry {
+              event: WhirlpoolEvent::ProgramDeployed(ProgramDeployedEventPayload {}),
+              instruction_index: Some(instruction_index as u32),
+              sub_index: Some(0),
+            });
+          }
+          decoded_instructions::DecodedInstruction::WhirlpoolInstruction(whirlpool_instruction) => {
+            let result = self.replay_engine.replay_instruction(&whirlpool_instruction).unwrap();
+
+            let instruction_events = build_whirlpool_events(
+              &whirlpool_instruction,
+              &self.decimals,
+              self.replay_engine.get_accounts(),
+              &result.snapshot,
+            );
+            events.extend(instruction_events.into_iter().enumerate().map(|(sub_index, event)| {
+              WhirlpoolEventEntry {
+                event,
+                instruction_index: Some(instruction_index as u32),
+                sub_index: Some(sub_index as u32),
+              }
+            }));
+          }
+        }
+      }
+
+      event_block_transactions.push(WhirlpoolEventTransaction {
+        index: event_block_transactions.len() as u32,
+        signature: transaction.signature,
+        payer: transaction.payer,
+        events,
+        raw_instructions: self.with_raw_instructions.then_some(raw_instructions),
+      });
+    }
+
+    let event_block = WhirlpoolEventBlock {
+      slot: slot.slot,
+      block_height: slot.block_height,
+      block_time: slot.block_time,
+      transactions: event_block_transactions,
+    };
+
+    (slot, event_block)
+  }
+}
@@ -0,0 +1,14 @@
+use crate::io::index::{build_whirlpool_event_index, save_whirlpool_event_index};
+use anyhow::Result;
+
+pub async fn process(
+  whirlpool_event_file_path: String,
+  whirlpool_event_index_file_path: String,
+) -> Result<()> {
+  // one sequential pass over the event file, decoding chunk by chunk to record their offsets
+  let index = build_whirlpool_event_index(&whirlpool_event_file_path)?;
+
+  save_whirlpool_event_index(&index, &whirlpool_event_index_file_path)?;
+
+  Ok(())
+}
@@ -1,7 +1,22 @@
-use clap::Subcommand;
+use clap::{Args, Subcommand};
+use crate::{
+    io::{compression::Compression, event::EventEncoding},
+    model::{header::WhirlpoolFileKind, schema::KeyStyle},
+};
 
+pub mod detect_arbitrage;
+pub mod detect_sandwich;
+pub mod diff;
 pub mod event;
+pub mod index;
 pub mod ohlcv;
+pub mod price_at;
+pub mod rekey;
+pub mod schema;
+pub mod transcode;
+pub mod validate;
+pub mod verify;
+pub mod wallets;
 
 #[derive(Subcommand, Debug)]
 pub enum Commands {
@@ -14,6 +29,10 @@ pub enum Commands {
         whirlpool_transaction_file_path: String,
         #[arg(long, short = 'e', id = "whirlpool-event-file-path")]
         whirlpool_event_file_path: String,
+        #[command(flatten)]
+        format: EventFormatArgs,
+        #[command(flatten)]
+        replay_state: ReplayStateArgs,
     },
     Ohlcv {
         #[arg(long, short = 's', id = "whirlpool-state-file-path")]
@@ -26,5 +45,182 @@ pub enum Commands {
         whirlpool_ohlcv_daily_file_path: String,
         #[arg(long, short = 'm', id = "whirlpool-ohlcv-minutely-file-path")]
         whirlpool_ohlcv_minutely_file_path: String,
+        #[command(flatten)]
+        output: OutputArgs,
+        // trades in cyclic arbitrage are not counted in volume
+        #[arg(long, id = "exclude-arbitrage")]
+        exclude_arbitrage: bool,
     },
+    PriceAt {
+        #[arg(long, short = 's', id = "whirlpool-state-file-path")]
+        whirlpool_state_file_path: String,
+        #[arg(long, short = 't', id = "whirlpool-token-file-path")]
+        whirlpool_token_file_path: String,
+        #[arg(long, short = 'e', id = "whirlpool-event-file-path")]
+        whirlpool_event_file_path: String,
+        #[arg(long, short = 'q', id = "price-query-file-path")]
+        price_query_file_path: String,
+        #[arg(long, short = 'o', id = "price-answer-file-path")]
+        price_answer_file_path: String,
+    },
+    Transcode {
+        #[arg(long, short = 'i', id = "input-whirlpool-event-file-path")]
+        input_whirlpool_event_file_path: String,
+        #[arg(long, short = 'o', id = "output-whirlpool-event-file-path")]
+        output_whirlpool_event_file_path: String,
+        #[arg(long, value_enum, id = "encoding")]
+        encoding: EventEncoding,
+        #[arg(long, value_enum, default_value_t = Compression::Gzip, id = "compression")]
+        compression: Compression,
+        #[arg(long, id = "compression-level")]
+        compression_level: Option<i32>,
+        // see EventFormatArgs
+        #[arg(long, id = "seekable")]
+        seekable: bool,
+    },
+    Index {
+        #[arg(long, short = 'e', id = "whirlpool-event-file-path")]
+        whirlpool_event_file_path: String,
+        #[arg(long, short = 'i', id = "whirlpool-event-index-file-path")]
+        whirlpool_event_index_file_path: String,
+    },
+    Diff {
+        #[arg(long, short = 'o', id = "old-whirlpool-event-file-path")]
+        old_whirlpool_event_file_path: String,
+        #[arg(long, short = 'n', id = "new-whirlpool-event-file-path")]
+        new_whirlpool_event_file_path: String,
+    },
+    Validate {
+        #[arg(long, short = 's', id = "whirlpool-state-file-path")]
+        whirlpool_state_file_path: String,
+        #[arg(long, short = 't', id = "whirlpool-token-file-path")]
+        whirlpool_token_file_path: String,
+        #[arg(long, short = 'e', id = "whirlpool-event-file-path")]
+        whirlpool_event_file_path: String,
+    },
+    Verify {
+        #[arg(long, short = 's', id = "whirlpool-state-file-path")]
+        whirlpool_state_file_path: String,
+        #[arg(long, short = 't', id = "whirlpool-token-file-path")]
+        whirlpool_token_file_path: String,
+        #[arg(long, short = 'x', id = "whirlpool-transaction-file-path")]
+        whirlpool_transaction_file_path: String,
+        #[arg(long, short = 'n', id = "next-whirlpool-state-file-path")]
+        next_whirlpool_state_file_path: String,
+    },
+    DetectArbitrage {
+        #[arg(long, short = 'e', id = "whirlpool-event-file-path")]
+        whirlpool_event_file_path: String,
+        #[arg(long, short = 'o', id = "arbitrage-file-path")]
+        arbitrage_file_path: String,
+    },
+    DetectSandwich {
+        #[arg(long, short = 'e', id = "whirlpool-event-file-path")]
+        whirlpool_event_file_path: String,
+        #[arg(long, short = 'o', id = "sandwich-file-path")]
+        sandwich_file_path: String,
+    },
+    Wallets {
+        #[arg(long, short = 'e', id = "whirlpool-event-file-path")]
+        whirlpool_event_file_path: String,
+        #[arg(long, short = 'w', id = "whirlpool-wallet-file-path")]
+        whirlpool_wallet_file_path: String,
+        #[command(flatten)]
+        output: OutputArgs,
+    },
+    Schema {
+        #[arg(long, short = 'k', value_enum, id = "kind")]
+        kind: WhirlpoolFileKind,
+        #[arg(long, short = 'l', value_enum, id = "language")]
+        language: schema::SchemaLanguage,
+        #[arg(long, short = 'o', id = "schema-file-path")]
+        schema_file_path: String,
+        #[arg(long, value_enum, default_value_t = KeyStyle::Short, id = "key-style")]
+        key_style: KeyStyle,
+    },
+    Rekey {
+        #[arg(long, short = 'i', id = "input-file-path")]
+        input_file_path: String,
+        #[arg(long, short = 'o', id = "output-file-path")]
+        output_file_path: String,
+        // detected from the header if omitted
+        #[arg(long, short = 'k', value_enum, id = "kind")]
+        kind: Option<WhirlpoolFileKind>,
+        #[arg(long, value_enum, id = "to")]
+        to: KeyStyle,
+        #[arg(long, value_enum, default_value_t = Compression::Gzip, id = "compression")]
+        compression: Compression,
+        #[arg(long, id = "compression-level")]
+        compression_level: Option<i32>,
+    },
+}
+
+#[derive(Args, Debug, Clone, Copy)]
+pub struct OutputArgs {
+    #[arg(long, id = "with-header")]
+    pub with_header: bool,
+    #[arg(long, value_enum, default_value_t = Compression::Gzip, id = "compression")]
+    pub compression: Compression,
+    #[arg(long, id = "compression-level")]
+    pub compression_level: Option<i32>,
+}
+
+#[derive(Args, Debug, Clone, Copy)]
+pub struct EventFormatArgs {
+    #[arg(long, id = "with-header")]
+    pub with_header: bool,
+    #[arg(long, value_enum, default_value_t = EventEncoding::Jsonl, id = "encoding")]
+    pub encoding: EventEncoding,
+    #[arg(long, value_enum, default_value_t = Compression::Gzip, id = "compression")]
+    pub compression: Compression,
+    #[arg(long, id = "compression-level")]
+    pub compression_level: Option<i32>,
+    // compresses each chunk of blocks independently, so that the index can seek to it (required for checkpoints of compressed output).
+    // readers must decode all gzip members or zstd frames in sequence (e.g. gzip -d, zstd -d).
+    #[arg(long, id = "seekable")]
+    pub seekable: bool,
+    // embeds the source instructions in each transaction
+    #[arg(long, id = "with-raw-instructions")]
+    pub with_raw_instructions: bool,
+    // descriptive keys for exploration (jsonl only), recorded in the header (so required) for other commands to read them
+    #[arg(long, id = "long-keys", requires = "with-header")]
+    pub long_keys: bool,
+}
+
+impl EventFormatArgs {
+    pub fn key_style(&self) -> KeyStyle {
+        if self.long_keys {
+            KeyStyle::Long
+        } else {
+            KeyStyle::Short
+        }
+    }
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct ReplayStateArgs {
+    // in transaction blocks, rounded up to the chunk size of the output
+    #[arg(long, id = "checkpoint-interval")]
+    pub checkpoint_interval: Option<u64>,
+    #[arg(long, id = "resume")]
+    pub resume: bool,
+    // the account state at the end of replay, usable as the input state of the next day
+    #[arg(long, id = "output-whirlpool-state-file-path")]
+    pub output_whirlpool_state_file_path: Option<String>,
+}
+
+// the result of the commands that check files (verify, validate and diff), mapped to the exit code in main
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CheckOutcome {
+    Passed,
+    Failed,
+}
+
+impl CheckOutcome {
+    pub fn exit_code(self) -> std::process::ExitCode {
+        match self {
+            CheckOutcome::Passed => std::process::ExitCode::SUCCESS,
+            CheckOutcome::Failed => std::process::ExitCode::FAILURE,
+        }
+    }
 }
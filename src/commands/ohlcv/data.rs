@@ -1,5 +1,5 @@
-use crate::model::{event::definition::{PoolInitializedEventPayload, TradeDirection, TradedEventPayload}, ohlcv};
-use bigdecimal::BigDecimal;
+use crate::model::{event::definition::{PoolInitializedEventPayload, TradeDirection, TradedEventPayload}, ohlcv, price::sqrt_price_to_decimal_price, transfer_fee};
+use anyhow::{anyhow, bail, Result};
 use std::collections::HashMap;
 
 #[derive(Debug)]
@@ -26,6 +26,7 @@ impl OhlcvDataManager {
         close: previous_close_sqrt_price,
         volume_a_to_b: VolumeData::default(),
         volume_b_to_a: VolumeData::default(),
+        tick_crossings: Some(0),
       },
       minutely: HashMap::new(),
     });
@@ -55,44 +56,49 @@ impl OhlcvDataManager {
         close: initial_sqrt_price,
         volume_a_to_b: VolumeData::default(),
         volume_b_to_a: VolumeData::default(),
+        tick_crossings: Some(0),
       },
       minutely: HashMap::new(),
     });
   }
 
-  pub fn process_traded_event(&mut self, block_time: i64, traded: &TradedEventPayload) {
+  // prices and fees are always updated, volume only if count_volume is true
+  pub fn process_traded_event(&mut self, block_time: i64, traded: &TradedEventPayload, count_volume: bool) {
     let whirlpool = self.data.get_mut(&traded.whirlpool).unwrap();
 
     // updating estimated_fees
-    let post_transfer_fee = calculate_post_transfer_fee(traded.transfer_in.amount, traded.transfer_in.transfer_fee_bps, traded.transfer_in.transfer_fee_max);
-    let trade_fee = calculate_trade_fee(post_transfer_fee, traded.fee_rate);
-    let (liquidity_provider_fee, protocol_fee) = split_fee(trade_fee, traded.protocol_fee_rate);
-    match traded.trade_direction {
-      TradeDirection::AtoB => {
-        whirlpool.estimated_fees.liquidity_provider_fee_a += liquidity_provider_fee;
-        whirlpool.estimated_fees.protocol_fee_a += protocol_fee;
-      }
-      TradeDirection::BtoA => {
-        whirlpool.estimated_fees.liquidity_provider_fee_b += liquidity_provider_fee;
-        whirlpool.estimated_fees.protocol_fee_b += protocol_fee;
+    // the fee of a trade with an invalid transfer fee config is not estimated (the converter leaves its post-fee amount unknown)
+    match calculate_post_transfer_fee(traded.transfer_in.amount, traded.transfer_in.transfer_fee_bps, traded.transfer_in.transfer_fee_max) {
+      Ok(post_transfer_fee) => {
+        let trade_fee = calculate_trade_fee(post_transfer_fee, traded.fee_rate);
+        let (liquidity_provider_fee, protocol_fee) = split_fee(trade_fee, traded.protocol_fee_rate);
+        match traded.trade_direction {
+          TradeDirection::AtoB => {
+            whirlpool.estimated_fees.liquidity_provider_fee_a += liquidity_provider_fee;
+            whirlpool.estimated_fees.protocol_fee_a += protocol_fee;
+          }
+          TradeDirection::BtoA => {
+            whirlpool.estimated_fees.liquidity_provider_fee_b += liquidity_provider_fee;
+            whirlpool.estimated_fees.protocol_fee_b += protocol_fee;
+          }
+        }
       }
+      Err(err) => eprintln!("warning: trade fee of {} is not estimated: {}", traded.whirlpool, err),
     }
 
     // updating daily
     whirlpool.daily.high = whirlpool.daily.high.max(traded.new_sqrt_price);
     whirlpool.daily.low = whirlpool.daily.low.min(traded.new_sqrt_price);
     whirlpool.daily.close = traded.new_sqrt_price;
-    match traded.trade_direction {
-      TradeDirection::AtoB => {
-        whirlpool.daily.volume_a_to_b.total_in += traded.transfer_in.amount as u128;
-        whirlpool.daily.volume_a_to_b.total_out += traded.transfer_out.amount as u128;
-        whirlpool.daily.volume_a_to_b.count += 1;
-      }
-      TradeDirection::BtoA => {
-        whirlpool.daily.volume_b_to_a.total_in += traded.transfer_in.amount as u128;
-        whirlpool.daily.volume_b_to_a.total_out += traded.transfer_out.amount as u128;
-        whirlpool.daily.volume_b_to_a.count += 1;
-      }
+    whirlpool.daily.tick_crossings = add_tick_crossings(whirlpool.daily.tick_crossings, traded);
+    if count_volume {
+      let volume = match traded.trade_direction {
+        TradeDirection::AtoB => &mut whirlpool.daily.volume_a_to_b,
+        TradeDirection::BtoA => &mut whirlpool.daily.volume_b_to_a,
+      };
+      volume.total_in += traded.transfer_in.amount as u128;
+      volume.total_out += traded.transfer_out.amount as u128;
+      volume.count += 1;
     }
 
     // updating minutely
@@ -105,22 +111,21 @@ impl OhlcvDataManager {
       close: traded.old_sqrt_price,
       volume_a_to_b: VolumeData::default(),
       volume_b_to_a: VolumeData::default(),
+      tick_crossings: Some(0),
     });
 
     minutely_data.high = minutely_data.high.max(traded.new_sqrt_price);
     minutely_data.low = minutely_data.low.min(traded.new_sqrt_price);
     minutely_data.close = traded.new_sqrt_price;
-    match traded.trade_direction {
-      TradeDirection::AtoB => {
-        minutely_data.volume_a_to_b.total_in += traded.transfer_in.amount as u128;
-        minutely_data.volume_a_to_b.total_out += traded.transfer_out.amount as u128;
-        minutely_data.volume_a_to_b.count += 1;
-      }
-      TradeDirection::BtoA => {
-        minutely_data.volume_b_to_a.total_in += traded.transfer_in.amount as u128;
-        minutely_data.volume_b_to_a.total_out += traded.transfer_out.amount as u128;
-        minutely_data.volume_b_to_a.count += 1;
-      }
+    minutely_data.tick_crossings = add_tick_crossings(minutely_data.tick_crossings, traded);
+    if count_volume {
+      let volume = match traded.trade_direction {
+        TradeDirection::AtoB => &mut minutely_data.volume_a_to_b,
+        TradeDirection::BtoA => &mut minutely_data.volume_b_to_a,
+      };
+      volume.total_in += traded.transfer_in.amount as u128;
+      volume.total_out += traded.transfer_out.amount as u128;
+      volume.count += 1;
     }
   }
 }
@@ -245,6 +250,8 @@ struct SqrtPriceOhlcvDataUnit {
   close: u128,
   volume_a_to_b: VolumeData,
   volume_b_to_a: VolumeData,
+  // None if a trade in the unit has no crossed ticks recorded
+  tick_crossings: Option<u64>,
 }
 
 #[derive(Default, Debug)]
@@ -283,14 +290,22 @@ fn convert_to_ohlcv_data_unit(data: &SqrtPriceOhlcvDataUnit, decimals_a: u8, dec
         count: data.volume_b_to_a.count,
       },
     },
+    tick_crossings: data.tick_crossings,
   }
 }
 
-fn calculate_post_transfer_fee(amount: u64, transfer_fee_bps: Option<u16>, transfer_fee_max: Option<u64>) -> u64 {
+fn add_tick_crossings(tick_crossings: Option<u64>, traded: &TradedEventPayload) -> Option<u64> {
+  let crossed_ticks = traded.crossed_ticks.as_ref()?;
+  tick_crossings.map(|tick_crossings| tick_crossings + crossed_ticks.len() as u64)
+}
+
+fn calculate_post_transfer_fee(amount: u64, transfer_fee_bps: Option<u16>, transfer_fee_max: Option<u64>) -> Result<u64> {
   match (transfer_fee_bps, transfer_fee_max) {
-    (Some(bps), Some(max)) => transfer_fee::calculate_post_fee_amount(amount, bps, max).unwrap(),
-    (None, None) => amount,
-    _ => unreachable!(),
+    (Some(bps), Some(max)) => transfer_fee::calculate_post_fee_amount(amount, bps, max).ok_or_else(|| {
+      anyhow!("transfer fee exceeds the amount (amount: {}, bps: {}, max: {})", amount, bps, max)
+    }),
+    (None, None) => Ok(amount),
+    _ => bail!("incomplete transfer fee config (bps: {:?}, max: {:?})", transfer_fee_bps, transfer_fee_max),
   }
 }
 
@@ -305,49 +320,3 @@ fn split_fee(amount: u64, protocol_fee_rate: u16) -> (u64, u64) {
   let liquidity_provider_fee = amount - protocol_fee;
   (liquidity_provider_fee, protocol_fee)
 }
-
-mod transfer_fee {
-  // cloned from: https://github.com/solana-labs/solana-program-library/blob/master/token/program-2022/src/extension/transfer_fee/mod.rs
-
-  const MAX_FEE_BASIS_POINTS: u16 = 10_000;
-  const ONE_IN_BASIS_POINTS: u128 = MAX_FEE_BASIS_POINTS as u128;
-
-  fn ceil_div(numerator: u128, denominator: u128) -> Option<u128> {
-    numerator
-        .checked_add(denominator)?
-        .checked_sub(1)?
-        .checked_div(denominator)
-  }
-
-  fn calculate_fee(pre_fee_amount: u64, transfer_fee_bps: u16, transfer_fee_max: u64) -> Option<u64> {
-      let transfer_fee_basis_points = transfer_fee_bps as u128;
-      if transfer_fee_basis_points == 0 || pre_fee_amount == 0 {
-          Some(0)
-      } else {
-          let numerator = (pre_fee_amount as u128).checked_mul(transfer_fee_basis_points)?;
-          let raw_fee: u64 = ceil_div(numerator, ONE_IN_BASIS_POINTS)?
-              .try_into() // guaranteed to be okay
-              .ok()?;
-
-          Some(raw_fee.min(transfer_fee_max))
-      }
-  }
-
-  pub fn calculate_post_fee_amount(pre_fee_amount: u64, transfer_fee_bps: u16, transfer_fee_max: u64) -> Option<u64> {
-      pre_fee_amount.checked_sub(calculate_fee(pre_fee_amount, transfer_fee_bps, transfer_fee_max)?)
-  }
-}
-
-
-// TODO: refactor (dedup event/convert.rs)
-static X64: std::sync::OnceLock<BigDecimal> = std::sync::OnceLock::new();
-fn sqrt_price_to_decimal_price(
-    sqrt_price: u128,
-    decimals_a: u8,
-    decimals_b: u8,
-) -> BigDecimal {
-    let x64 = X64.get_or_init(|| BigDecimal::from(1u128 << 64));
-    let price = (BigDecimal::from(sqrt_price) / x64).square();
-    let (i, scale) = price.as_bigint_and_exponent();
-    BigDecimal::new(i, scale - (decimals_a as i64 - decimals_b as i64))
-}
@@ -1,11 +1,5 @@
-use anyhow::Result;
-use flate2::read::GzDecoder;
-use std::{
-  collections::HashMap,
-  fs::File,
-  io::{BufRead, BufReader},
-};
-use crate::model::event::WhirlpoolEventBlock;
+use std::collections::HashMap;
+use crate::{io::event::WhirlpoolEventBlockIter, model::header::WhirlpoolFileHeader};
 use whirlpool_replayer::{schema::WhirlpoolState, serde::AccountDataStoreConfig};
 
 pub fn build_with_local_file_storage(
@@ -15,7 +9,8 @@ pub fn build_with_local_file_storage(
   account_data_store_config: &AccountDataStoreConfig,
 ) -> (
   WhirlpoolState,
-  Box<dyn Iterator<Item = WhirlpoolEventBlock> + Send>,
+  Option<WhirlpoolFileHeader>,
+  WhirlpoolEventBlockIter,
   HashMap<String, u8>,
 ) {
   let state = whirlpool_replayer::io::load_from_local_whirlpool_state_file(
@@ -24,7 +19,7 @@ pub fn build_with_local_file_storage(
   );
   let token =
       whirlpool_replayer::io::load_from_local_whirlpool_token_file(&whirlpool_token_file_path);
-  let event_iter = load_from_local_whirlpool_event_file(
+  let (event_header, event_iter) = crate::io::event::load_from_local_whirlpool_event_file(
       &whirlpool_event_file_path,
   );
 
@@ -34,22 +29,5 @@ pub fn build_with_local_file_storage(
       .map(|t| (t.mint.clone(), t.decimals))
       .collect();
 
-  (state, Box::new(event_iter), decimals)
-}
-
-fn load_from_local_whirlpool_event_file(
-  whirlpool_event_file_path: &str,
-) -> impl Iterator<Item = WhirlpoolEventBlock> {
-  let file = File::open(whirlpool_event_file_path).unwrap();
-
-  let decoder = GzDecoder::new(file);
-  let buf = BufReader::new(decoder);
-
-  let iter = buf.lines().map(|jsonl| jsonl.unwrap()).map(|jsonl| {
-      let t: Result<WhirlpoolEventBlock, serde_json::Error> =
-          serde_json::from_str(jsonl.as_str());
-      t.unwrap()
-  });
-
-  iter
+  (state, event_header, event_iter, decimals)
 }
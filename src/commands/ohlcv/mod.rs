@@ -1,15 +1,23 @@
-use crate::model::{event::WhirlpoolEvent, ohlcv};
+use crate::{
+  commands::OutputArgs,
+  model::{
+    arbitrage::detect_arbitrage_cycles,
+    event::{WhirlpoolEvent, WHIRLPOOL_EVENT_FORMAT_VERSION},
+    header::{WhirlpoolFileHeader, WhirlpoolFileKind},
+    ohlcv::WHIRLPOOL_OHLCV_FORMAT_VERSION,
+  },
+};
 use anchor_lang::Discriminator;
-use anyhow::Result;
-use flate2::write::GzEncoder;
+use anyhow::{bail, Result};
 use std::{
-  fs::File, io::LineWriter, io::Write,
+  collections::HashSet, fs::File, io::LineWriter, io::Write,
 };
 use whirlpool_replayer::serde::AccountDataStoreConfig;
 use anchor_lang::AccountDeserialize;
 
-mod io;
+pub mod io;
 mod data;
+mod shard;
 
 pub async fn process(
   whirlpool_state_file_path: String,
@@ -17,23 +25,49 @@ pub async fn process(
   whirlpool_event_file_path: String,
   whirlpool_ohlcv_daily_file_path: String,
   whirlpool_ohlcv_minutely_file_path: String,
+  output: OutputArgs,
+  exclude_arbitrage: bool,
 ) -> Result<()> {
   println!("open files...");
-  let (state, event_block_iter, decimals) = io::build_with_local_file_storage(
+  let input_file_paths = [
+    whirlpool_state_file_path.clone(),
+    whirlpool_token_file_path.clone(),
+    whirlpool_event_file_path.clone(),
+  ];
+  let (state, event_header, event_block_iter, decimals) = io::build_with_local_file_storage(
     whirlpool_state_file_path,
     whirlpool_token_file_path,
     whirlpool_event_file_path,
     &AccountDataStoreConfig::OnDisk(None),
   );
 
+  if let Some(event_header) = &event_header {
+    println!("event file header: {:?}", event_header);
+    if !event_header.is_compatible_with(WhirlpoolFileKind::Event, WHIRLPOOL_EVENT_FORMAT_VERSION) {
+      bail!("unsupported event file (kind: {:?}, format version: {})", event_header.kind, event_header.format_version);
+    }
+  }
+
+  let build_header = |kind: WhirlpoolFileKind| -> Result<Option<WhirlpoolFileHeader>> {
+    if !output.with_header {
+      return Ok(None);
+    }
+    let input_file_paths = input_file_paths.iter().map(String::as_str).collect::<Vec<_>>();
+    Ok(Some(crate::io::build_file_header(kind, WHIRLPOOL_OHLCV_FORMAT_VERSION, state.slot, &input_file_paths)?))
+  };
+  let daily_header = build_header(WhirlpoolFileKind::OhlcvDaily)?;
+  let minutely_header = build_header(WhirlpoolFileKind::OhlcvMinutely)?;
+
   // state is at the end of yesterday
   let seconds_per_day = 60 * 60 * 24;
   let yesterday_timestamp = state.block_time / seconds_per_day * seconds_per_day;
   let daily_timestamp = yesterday_timestamp + seconds_per_day;
 
-  let mut ohlcv_data_manager = data::OhlcvDataManager::new(daily_timestamp);
+  let shards = std::thread::available_parallelism().map(|n| n.get()).unwrap_or(4);
+  let mut ohlcv_data_manager = shard::ShardedOhlcvDataManager::new(daily_timestamp, shards);
 
   println!("traverse accounts...");
+  let mut initial_messages = vec![];
   state.accounts.traverse(|pubkey, data| {
     if data.starts_with(&whirlpool_base::state::Whirlpool::DISCRIMINATOR) {
       let whirlpool = whirlpool_base::state::Whirlpool::try_deserialize(&mut data.as_slice()).unwrap();
@@ -41,7 +75,7 @@ pub async fn process(
       let mint_b = whirlpool.token_mint_b.to_string();
       let decimals_a = *decimals.get(&mint_a).unwrap();
       let decimals_b = *decimals.get(&mint_b).unwrap();
-      ohlcv_data_manager.initialize_with_previous_close(data::Metadata {
+      initial_messages.push(shard::ShardMessage::InitializeWithPreviousClose(data::Metadata {
         whirlpool: pubkey.to_string(),
         whirlpools_config: whirlpool.whirlpools_config.to_string(),
         mint_a,
@@ -49,51 +83,77 @@ pub async fn process(
         tick_spacing: whirlpool.tick_spacing,
         decimals_a,
         decimals_b,
-      }, whirlpool.sqrt_price);
+      }, whirlpool.sqrt_price));
     }
     Ok(())
   })?;
+  for message in initial_messages {
+    ohlcv_data_manager.dispatch(message).await?;
+  }
 
+  // events are decoded once here and dispatched to the shard owning the pool
   println!("process events...");
   for event_block in event_block_iter {
-    event_block.transactions.iter().for_each(|transaction| {
-      transaction.events.iter().for_each(|event| {
-        match event {
+    for transaction in event_block.transactions {
+      let arbitrage_event_indexes = if exclude_arbitrage {
+        detect_arbitrage_cycles(&transaction)
+          .into_iter()
+          .flat_map(|cycle| cycle.event_indexes)
+          .collect::<HashSet<_>>()
+      } else {
+        HashSet::new()
+      };
+
+      for (event_index, entry) in transaction.events.into_iter().enumerate() {
+        match entry.event {
           WhirlpoolEvent::Traded(traded) => {
-            ohlcv_data_manager.process_traded_event(event_block.block_time, traded);
+            let count_volume = !arbitrage_event_indexes.contains(&event_index);
+            ohlcv_data_manager.dispatch(shard::ShardMessage::Traded(event_block.block_time, Box::new(traded), count_volume)).await?;
           }
           WhirlpoolEvent::PoolInitialized(pool_initialized) => {
-            ohlcv_data_manager.process_pool_initialized_event(event_block.slot, event_block.block_time, pool_initialized);
+            ohlcv_data_manager.dispatch(shard::ShardMessage::PoolInitialized(event_block.slot, event_block.block_time, Box::new(pool_initialized))).await?;
           }
           _ => { /* ignore */ }
         }
-      });
-    });
+      }
+    }
   }
 
+  // shards serialize their pools in parallel
+  println!("aggregate shards...");
+  let shard_outputs = ohlcv_data_manager.finish().await?;
+
   // write daily file
   println!("write daily file...");
   let f = File::create(whirlpool_ohlcv_daily_file_path).unwrap();
-  let encoder = GzEncoder::new(f, flate2::Compression::default());
+  let encoder = output.compression.create_writer(f, output.compression_level)?;
   let mut writer = LineWriter::new(encoder);
-  ohlcv_data_manager.data.values().map(ohlcv::WhirlpoolOhlcvDailyData::from).for_each(|data| {
-    let jsonl = serde_json::to_string(&data).unwrap();
+  if let Some(header) = daily_header {
+    let jsonl = serde_json::to_string(&header).unwrap();
+    writer.write_all(jsonl.as_bytes()).unwrap();
+    writer.write_all(b"\n").unwrap();
+  }
+  shard_outputs.iter().flat_map(|shard_output| shard_output.daily.iter()).for_each(|jsonl| {
     writer.write_all(jsonl.as_bytes()).unwrap();
     writer.write_all(b"\n").unwrap();
   });
-  writer.flush().unwrap();
+  writer.into_inner().map_err(|err| err.into_error())?.finish()?;
 
   // write minutely file
   println!("write minutely file...");
   let f = File::create(whirlpool_ohlcv_minutely_file_path).unwrap();
-  let encoder = GzEncoder::new(f, flate2::Compression::default());
+  let encoder = output.compression.create_writer(f, output.compression_level)?;
   let mut writer = LineWriter::new(encoder);
-  ohlcv_data_manager.data.values().map(ohlcv::WhirlpoolOhlcvMinutelyData::from).for_each(|data| {
-    let jsonl = serde_json::to_string(&data).unwrap();
+  if let Some(header) = minutely_header {
+    let jsonl = serde_json::to_string(&header).unwrap();
+    writer.write_all(jsonl.as_bytes()).unwrap();
+    writer.write_all(b"\n").unwrap();
+  }
+  shard_outputs.iter().flat_map(|shard_output| shard_output.minutely.iter()).for_each(|jsonl| {
     writer.write_all(jsonl.as_bytes()).unwrap();
     writer.write_all(b"\n").unwrap();
   });
-  writer.flush().unwrap();
+  writer.into_inner().map_err(|err| err.into_error())?.finish()?;
 
   Ok(())
 }
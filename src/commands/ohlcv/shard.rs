@@ -0,0 +1,133 @@
+use super::data::{Metadata, OhlcvDataManager};
+use crate::model::{
+  event::definition::{PoolInitializedEventPayload, TradedEventPayload},
+  ohlcv,
+};
+use anyhow::Result;
+use std::{
+  collections::hash_map::DefaultHasher,
+  hash::{Hash, Hasher},
+};
+use tokio::{sync::mpsc, task::JoinHandle};
+
+// messages are sent to shards in batches to reduce channel overhead
+const MESSAGES_PER_BATCH: usize = 1024;
+const BATCH_CHANNEL_CAPACITY: usize = 16;
+
+pub enum ShardMessage {
+  InitializeWithPreviousClose(Metadata, u128),
+  PoolInitialized(u64, i64, Box<PoolInitializedEventPayload>),
+  // the flag is false if the trade is excluded from volume (e.g. arbitrage)
+  Traded(i64, Box<TradedEventPayload>, bool),
+}
+
+impl ShardMessage {
+  fn whirlpool(&self) -> &str {
+    match self {
+      ShardMessage::InitializeWithPreviousClose(metadata, _) => &metadata.whirlpool,
+      ShardMessage::PoolInitialized(_, _, pool_initialized) => &pool_initialized.whirlpool,
+      ShardMessage::Traded(_, traded, _) => &traded.whirlpool,
+    }
+  }
+}
+
+// serialized JSON lines of the pools in a shard
+pub struct ShardOutput {
+  pub daily: Vec<String>,
+  pub minutely: Vec<String>,
+}
+
+// each pool is owned by exactly one shard, so shards aggregate and serialize in parallel without locks
+pub struct ShardedOhlcvDataManager {
+  senders: Vec<mpsc::Sender<Vec<ShardMessage>>>,
+  buffers: Vec<Vec<ShardMessage>>,
+  handles: Vec<JoinHandle<ShardOutput>>,
+}
+
+impl ShardedOhlcvDataManager {
+  pub fn new(timestamp: i64, shards: usize) -> Self {
+    let mut senders = Vec::with_capacity(shards);
+    let mut handles = Vec::with_capacity(shards);
+
+    for _ in 0..shards {
+      let (sender, mut receiver) = mpsc::channel::<Vec<ShardMessage>>(BATCH_CHANNEL_CAPACITY);
+      let handle = tokio::task::spawn_blocking(move || {
+        let mut ohlcv_data_manager = OhlcvDataManager::new(timestamp);
+
+        while let Some(messages) = receiver.blocking_recv() {
+          messages.into_iter().for_each(|message| match message {
+            ShardMessage::InitializeWithPreviousClose(metadata, previous_close_sqrt_price) => {
+              ohlcv_data_manager.initialize_with_previous_close(metadata, previous_close_sqrt_price);
+            }
+            ShardMessage::PoolInitialized(slot, block_time, pool_initialized) => {
+              ohlcv_data_manager.process_pool_initialized_event(slot, block_time, &pool_initialized);
+            }
+            ShardMessage::Traded(block_time, traded, count_volume) => {
+              ohlcv_data_manager.process_traded_event(block_time, &traded, count_volume);
+            }
+          });
+        }
+
+        let daily = ohlcv_data_manager.data.values().map(ohlcv::WhirlpoolOhlcvDailyData::from).map(|data| {
+          serde_json::to_string(&data).unwrap()
+        }).collect();
+        let minutely = ohlcv_data_manager.data.values().map(ohlcv::WhirlpoolOhlcvMinutelyData::from).map(|data| {
+          serde_json::to_string(&data).unwrap()
+        }).collect();
+
+        ShardOutput { daily, minutely }
+      });
+
+      senders.push(sender);
+      handles.push(handle);
+    }
+
+    Self {
+      senders,
+      buffers: (0..shards).map(|_| Vec::with_capacity(MESSAGES_PER_BATCH)).collect(),
+      handles,
+    }
+  }
+
+  pub async fn dispatch(&mut self, message: ShardMessage) -> Result<()> {
+    let shard = self.shard_of(message.whirlpool());
+    self.buffers[shard].push(message);
+    if self.buffers[shard].len() >= MESSAGES_PER_BATCH {
+      self.send_batch(shard).await?;
+    }
+    Ok(())
+  }
+
+  pub async fn finish(mut self) -> Result<Vec<ShardOutput>> {
+    let shards = self.senders.len();
+    for shard in 0..shards {
+      self.send_batch(shard).await?;
+    }
+
+    // closing the channels lets the shards serialize their pools and exit
+    drop(self.senders);
+
+    let mut outputs = Vec::with_capacity(self.handles.len());
+    for handle in self.handles {
+      outputs.push(handle.await?);
+    }
+    Ok(outputs)
+  }
+
+  async fn send_batch(&mut self, shard: usize) -> Result<()> {
+    if self.buffers[shard].is_empty() {
+      return Ok(());
+    }
+    let batch = std::mem::replace(&mut self.buffers[shard], Vec::with_capacity(MESSAGES_PER_BATCH));
+    if self.senders[shard].send(batch).await.is_err() {
+      anyhow::bail!("ohlcv shard {} has stopped", shard);
+    }
+    Ok(())
+  }
+
+  fn shard_of(&self, whirlpool: &str) -> usize {
+    let mut hasher = DefaultHasher::new();
+    whirlpool.hash(&mut hasher);
+    (hasher.finish() % self.senders.len() as u64) as usize
+  }
+}
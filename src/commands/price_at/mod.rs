@@ -0,0 +1,286 @@
+use crate::{
+  commands::ohlcv::io::build_with_local_file_storage,
+  model::{
+    event::{WhirlpoolEvent, WHIRLPOOL_EVENT_FORMAT_VERSION},
+    header::WhirlpoolFileKind,
+    pool::{PoolStateSnapshot, PoolStateTracker},
+    price::sqrt_price_to_decimal_price,
+    serde::string_decimal_price,
+  },
+};
+use anyhow::{bail, Result};
+use bigdecimal::Zero;
+use serde_derive::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use whirlpool_replayer::serde::AccountDataStoreConfig;
+
+/*
+
+Query CSV:
+  target,timestamp
+  <whirlpool>,<unix timestamp>
+  <base mint>/<quote mint>,<unix timestamp>
+
+For a pair, the pool of the pair with the most liquidity at the timestamp is used
+(ties are broken by the latest update, pools with unknown liquidity are used only if no other pool exists),
+and the price is oriented as the price of the base mint in the quote mint.
+The deepest pool is the one whose price is hardest to move, so it is the most representative one.
+
+*/
+
+#[derive(Deserialize, Debug)]
+struct PriceQuery {
+  target: String,
+  timestamp: i64,
+}
+
+#[derive(Serialize, Debug)]
+struct PriceAnswer {
+  target: String,
+  timestamp: i64,
+  whirlpool: Option<String>,
+  slot: Option<u64>,
+  block_time: Option<i64>,
+  // "event" if the price was set by an event (initialization or trade) at or before the timestamp,
+  // "state" if the price is still the one in the state file
+  source: Option<&'static str>,
+  sqrt_price: Option<String>,
+  tick_current_index: Option<i32>,
+  decimal_price: Option<String>,
+  inverted: Option<bool>,
+}
+
+enum QueryTarget {
+  Pool(String),
+  Pair(String, String),
+}
+
+pub async fn process(
+  whirlpool_state_file_path: String,
+  whirlpool_token_file_path: String,
+  whirlpool_event_file_path: String,
+  price_query_file_path: String,
+  price_answer_file_path: String,
+) -> Result<()> {
+  let queries = csv::Reader::from_path(&price_query_file_path)?
+    .deserialize::<PriceQuery>()
+    .collect::<Result<Vec<_>, _>>()?;
+  let targets = queries
+    .iter()
+    .map(|query| parse_target(&query.target))
+    .collect::<Result<Vec<_>>>()?;
+
+  println!("open files...");
+  let (state, event_header, event_block_iter, decimals) = build_with_local_file_storage(
+    whirlpool_state_file_path,
+    whirlpool_token_file_path,
+    whirlpool_event_file_path,
+    &AccountDataStoreConfig::OnDisk(None),
+  );
+
+  if let Some(event_header) = &event_header {
+    if !event_header.is_compatible_with(WhirlpoolFileKind::Event, WHIRLPOOL_EVENT_FORMAT_VERSION) {
+      bail!("unsupported event file (kind: {:?}, format version: {})", event_header.kind, event_header.format_version);
+    }
+  }
+
+  println!("traverse accounts...");
+  let mut tracker = PoolStateTracker::from_accounts(&state.accounts, state.slot, state.block_time, true)?;
+
+  // only the queried pools and the pools of queried pairs keep history
+  let pairs: HashSet<(String, String)> = targets
+    .iter()
+    .filter_map(|target| match target {
+      QueryTarget::Pair(base, quote) => Some(pair_key(base, quote)),
+      QueryTarget::Pool(_) => None,
+    })
+    .collect();
+  let mut pools_of_pair: HashMap<(String, String), Vec<String>> = HashMap::new();
+  for (whirlpool, pool) in tracker.pools() {
+    let key = pair_key(&pool.token_mint_a.to_string(), &pool.token_mint_b.to_string());
+    if pairs.contains(&key) {
+      pools_of_pair.entry(key).or_default().push(whirlpool.clone());
+    }
+  }
+  let mut relevant_pools: HashSet<String> = targets
+    .iter()
+    .filter_map(|target| match target {
+      QueryTarget::Pool(whirlpool) => Some(whirlpool.clone()),
+      QueryTarget::Pair(_, _) => None,
+    })
+    .chain(pools_of_pair.values().flatten().cloned())
+    .collect();
+
+  println!("process events...");
+  for event_block in event_block_iter {
+    for transaction in event_block.transactions.iter() {
+      for entry in transaction.events.iter() {
+        let event = &entry.event;
+        if let WhirlpoolEvent::PoolInitialized(payload) = event {
+          let key = pair_key(&payload.token_mint_a, &payload.token_mint_b);
+          if pairs.contains(&key) {
+            pools_of_pair.entry(key).or_default().push(payload.whirlpool.clone());
+            relevant_pools.insert(payload.whirlpool.clone());
+          }
+        }
+
+        if event.whirlpool().map(|whirlpool| relevant_pools.contains(whirlpool)).unwrap_or(false) {
+          tracker.apply_event(event_block.slot, event_block.block_time, event);
+        }
+      }
+    }
+  }
+
+  // times before the state fall back to the state file's price
+  let state_at_time = |whirlpool: &String, timestamp: i64| {
+    tracker
+      .state_at_time(whirlpool, timestamp)
+      .or_else(|| tracker.history(whirlpool).first().filter(|snapshot| snapshot.slot == state.slot))
+      .map(|snapshot| (whirlpool.clone(), snapshot))
+  };
+
+  println!("answer queries...");
+  let mut writer = csv::Writer::from_path(&price_answer_file_path)?;
+  for (query, target) in queries.into_iter().zip(targets) {
+    let found = match &target {
+      QueryTarget::Pool(whirlpool) => state_at_time(whirlpool, query.timestamp),
+      QueryTarget::Pair(base, quote) => pools_of_pair
+        .get(&pair_key(base, quote))
+        .into_iter()
+        .flatten()
+        .filter_map(|whirlpool| state_at_time(whirlpool, query.timestamp))
+        .max_by_key(|(_, snapshot)| (snapshot.state.liquidity, snapshot.slot)),
+    };
+
+    let answer = match found {
+      Some((whirlpool, snapshot)) => {
+        let inverted = match &target {
+          QueryTarget::Pair(base, _) => snapshot.state.token_mint_a.to_string() != *base,
+          QueryTarget::Pool(_) => false,
+        };
+        answer_of(query, whirlpool, snapshot, inverted, &decimals)
+      }
+      None => PriceAnswer {
+        target: query.target,
+        timestamp: query.timestamp,
+        whirlpool: None,
+        slot: None,
+        block_time: None,
+        source: None,
+        sqrt_price: None,
+        tick_current_index: None,
+        decimal_price: None,
+        inverted: None,
+      },
+    };
+    writer.serialize(answer)?;
+  }
+  writer.flush()?;
+
+  Ok(())
+}
+
+fn parse_target(target: &str) -> Result<QueryTarget> {
+  match target.split_once('/') {
+    Some((base, quote)) if !base.is_empty() && !quote.is_empty() => Ok(QueryTarget::Pair(base.to_string(), quote.to_string())),
+    Some(_) => bail!("invalid pair: {}", target),
+    None => Ok(QueryTarget::Pool(target.to_string())),
+  }
+}
+
+// pairs are matched regardless of the order of mints
+fn pair_key(mint_x: &str, mint_y: &str) -> (String, String) {
+  if mint_x <= mint_y {
+    (mint_x.to_string(), mint_y.to_string())
+  } else {
+    (mint_y.to_string(), mint_x.to_string())
+  }
+}
+
+fn answer_of(
+  query: PriceQuery,
+  whirlpool: String,
+  snapshot: &PoolStateSnapshot,
+  inverted: bool,
+  decimals: &HashMap<String, u8>,
+) -> PriceAnswer {
+  let decimals_a = decimals.get(&snapshot.state.token_mint_a.to_string());
+  let decimals_b = decimals.get(&snapshot.state.token_mint_b.to_string());
+  let decimal_price = match (decimals_a, decimals_b) {
+    (Some(decimals_a), Some(decimals_b)) => {
+      let price = sqrt_price_to_decimal_price(snapshot.state.sqrt_price, *decimals_a, *decimals_b);
+      if !inverted {
+        Some(price)
+      } else if price.is_zero() {
+        None
+      } else {
+        Some(price.inverse())
+      }
+    }
+    _ => None,
+  };
+
+  PriceAnswer {
+    target: query.target,
+    timestamp: query.timestamp,
+    whirlpool: Some(whirlpool),
+    slot: Some(snapshot.slot),
+    block_time: Some(snapshot.block_time),
+    source: Some(if snapshot.price_from_event { "event" } else { "state" }),
+    sqrt_price: Some(snapshot.state.sqrt_price.to_string()),
+    tick_current_index: Some(snapshot.state.tick_current_index),
+    decimal_price: decimal_price.map(|price| string_decimal_price::to_string(&price)),
+    inverted: Some(inverted),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::model::pool::{PoolRewardInfo, PoolState};
+  use anchor_lang::prelude::Pubkey;
+
+  fn answer_at(sqrt_price: u128, inverted: bool) -> PriceAnswer {
+    let (token_mint_a, token_mint_b) = (Pubkey::new_unique(), Pubkey::new_unique());
+    let snapshot = PoolStateSnapshot {
+      slot: 1,
+      block_time: 2,
+      state: PoolState {
+        token_mint_a,
+        token_mint_b,
+        tick_spacing: 64,
+        sqrt_price,
+        tick_current_index: 0,
+        liquidity: Some(0),
+        fee_rate: 3000,
+        protocol_fee_rate: 1300,
+        reward_infos: [PoolRewardInfo::default(); 3],
+      },
+      price_from_event: true,
+    };
+    let decimals = HashMap::from([(token_mint_a.to_string(), 9), (token_mint_b.to_string(), 6)]);
+    let query = PriceQuery {
+      target: format!("{}/{}", token_mint_b, token_mint_a),
+      timestamp: 0,
+    };
+    answer_of(query, "whirlpool".to_string(), &snapshot, inverted, &decimals)
+  }
+
+  #[test]
+  fn decimal_price_is_formatted_as_in_events() {
+    // price 9 in raw amounts, 9000 with decimals
+    let sqrt_price = 3u128 << 64;
+    let answer = answer_at(sqrt_price, false);
+    assert_eq!(answer.decimal_price.as_deref(), Some("9.000000000e3"));
+    assert_eq!(answer.inverted, Some(false));
+
+    // the inverted price is rounded to the precision of event prices
+    let answer = answer_at(sqrt_price, true);
+    assert_eq!(answer.decimal_price.as_deref(), Some("1.111111111e-4"));
+    assert_eq!(answer.inverted, Some(true));
+    assert_eq!(answer.sqrt_price, Some(sqrt_price.to_string()));
+
+    // a zero price has no inverse
+    assert_eq!(answer_at(0, true).decimal_price, None);
+  }
+}
@@ -0,0 +1,186 @@
+use crate::{
+  io::{
+    compression::{self, Compression},
+    event::{EventRecord, EventRecordReader},
+  },
+  model::{
+    header::{WhirlpoolFileHeader, WhirlpoolFileKind},
+    schema::{file_schema, rekey::rekey_jsonl, Definitions, FileSchema, KeyStyle, TypeSchema},
+  },
+};
+use anyhow::{bail, Result};
+use serde_json::Value;
+use std::{
+  fs::File,
+  io::{BufReader, LineWriter, Write},
+};
+
+pub async fn process(
+  input_file_path: String,
+  output_file_path: String,
+  kind: Option<WhirlpoolFileKind>,
+  to: KeyStyle,
+  compression: Compression,
+  compression_level: Option<i32>,
+) -> Result<()> {
+  println!("open files...");
+  let decoder = compression::open_local_file_with_auto_decompression(&input_file_path)?;
+  let mut records = EventRecordReader::new(BufReader::new(decoder)).peekable();
+
+  // the first line may be a header, which is always in short keys
+  let header = match records.peek() {
+    Some(EventRecord::Line(jsonl)) => WhirlpoolFileHeader::from_jsonl(jsonl),
+    _ => None,
+  };
+  if header.is_some() {
+    records.next();
+  }
+
+  let kind = match (kind, &header) {
+    (Some(kind), _) => kind,
+    (None, Some(header)) => header.kind,
+    (None, None) => bail!("no header found, the kind of the file must be specified"),
+  };
+  let file_schema = file_schema(kind);
+
+  // the key style is recorded in the header, files without a header are detected from the first line
+  let from = match (&header, records.peek()) {
+    (Some(header), _) => header.key_style(),
+    (None, Some(EventRecord::Line(jsonl))) => detect_key_style(jsonl, &file_schema)?,
+    (None, _) => to.opposite(),
+  };
+  if from == to {
+    bail!("the input file is already in {:?} keys", to);
+  }
+
+  let f = File::create(output_file_path)?;
+  let encoder = compression.create_writer(f, compression_level)?;
+  let mut writer = LineWriter::new(encoder);
+  if let Some(header) = header {
+    let header = WhirlpoolFileHeader {
+      key_style: (to != KeyStyle::Short).then_some(to),
+      ..header
+    };
+    writer.write_all(serde_json::to_string(&header)?.as_bytes())?;
+    writer.write_all(b"\n")?;
+  }
+
+  println!("rekey lines...");
+  let mut lines = 0u64;
+  for record in records {
+    let EventRecord::Line(jsonl) = record else {
+      bail!("binary records have no keys, transcode the file to jsonl first");
+    };
+    let jsonl = rekey_jsonl(&jsonl, &file_schema.root, &file_schema.definitions, from, to)?;
+    writer.write_all(jsonl.as_bytes())?;
+    writer.write_all(b"\n")?;
+    lines += 1;
+  }
+  writer.into_inner().map_err(|err| err.into_error())?.finish()?;
+
+  println!("lines: {}", lines);
+
+  Ok(())
+}
+
+// the style whose top-level keys contain all keys of the line
+fn detect_key_style(jsonl: &str, file_schema: &FileSchema) -> Result<KeyStyle> {
+  let line: Value = serde_json::from_str(jsonl)?;
+  let Some(object) = line.as_object() else {
+    bail!("the first line is not a JSON object");
+  };
+
+  let styles = [KeyStyle::Short, KeyStyle::Long]
+    .into_iter()
+    .filter(|style| {
+      let keys = top_level_keys(&file_schema.root, &file_schema.definitions, *style);
+      object.keys().all(|key| keys.contains(key))
+    })
+    .collect::<Vec<_>>();
+  match styles.as_slice() {
+    [style] => Ok(*style),
+    _ => bail!("the key style of the first line cannot be detected, the file may not be of the given kind"),
+  }
+}
+
+fn top_level_keys(schema: &TypeSchema, definitions: &Definitions, style: KeyStyle) -> Vec<String> {
+  let TypeSchema::Object(object) = definitions.resolve(schema) else {
+    return vec![];
+  };
+  object
+    .fields
+    .iter()
+    .flat_map(|field| match field.key_in(style) {
+      Some(key) => vec![key],
+      // flattened fields share the object
+      None => top_level_keys(&field.schema, definitions, style),
+    })
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{
+    io::event::EventEncoding,
+    model::event::fixtures,
+    test_support::{temp_file_path, write_event_file},
+  };
+
+  async fn write_short_key_file(path: &str, header: Option<WhirlpoolFileHeader>) {
+    write_event_file(path, header, fixtures::generate_blocks(3), EventEncoding::Jsonl, Compression::None, false).await;
+  }
+
+  async fn rekey(input: &str, output: &str, kind: Option<WhirlpoolFileKind>, to: KeyStyle) -> Result<()> {
+    process(input.to_string(), output.to_string(), kind, to, Compression::None, None).await
+  }
+
+  #[tokio::test]
+  async fn short_long_short_round_trip_is_byte_identical() {
+    let header = fixtures::current_header();
+    for (name, header, kind) in [("with-header", Some(header), None), ("without-header", None, Some(WhirlpoolFileKind::Event))] {
+      let short_path = temp_file_path(&format!("{}.short", name));
+      let long_path = temp_file_path(&format!("{}.long", name));
+      let round_trip_path = temp_file_path(&format!("{}.short.long.short", name));
+      write_short_key_file(&short_path, header.clone()).await;
+
+      rekey(&short_path, &long_path, kind, KeyStyle::Long).await.unwrap();
+      let long = std::fs::read_to_string(&long_path).unwrap();
+      let mut lines = long.lines();
+      if header.is_some() {
+        // the header stays in short keys
+        let long_header = WhirlpoolFileHeader::from_jsonl(lines.next().unwrap()).unwrap();
+        assert_eq!(long_header.key_style, Some(KeyStyle::Long), "{}", name);
+      }
+      assert!(lines.next().unwrap().starts_with(r#"{"slot":"#), "{}", name);
+
+      rekey(&long_path, &round_trip_path, kind, KeyStyle::Short).await.unwrap();
+      assert_eq!(std::fs::read(&round_trip_path).unwrap(), std::fs::read(&short_path).unwrap(), "{}", name);
+
+      for path in [short_path, long_path, round_trip_path] {
+        std::fs::remove_file(path).unwrap();
+      }
+    }
+  }
+
+  #[tokio::test]
+  async fn rekey_to_the_current_key_style_is_rejected() {
+    let header = fixtures::current_header();
+    for (name, header, kind) in [("with-header", Some(header), None), ("without-header", None, Some(WhirlpoolFileKind::Event))] {
+      let short_path = temp_file_path(&format!("{}.current.short", name));
+      let long_path = temp_file_path(&format!("{}.current.long", name));
+      let output_path = temp_file_path(&format!("{}.current.output", name));
+      write_short_key_file(&short_path, header).await;
+      rekey(&short_path, &long_path, kind, KeyStyle::Long).await.unwrap();
+
+      let err = rekey(&short_path, &output_path, kind, KeyStyle::Short).await.unwrap_err();
+      assert_eq!(err.to_string(), "the input file is already in Short keys", "{}", name);
+      let err = rekey(&long_path, &output_path, kind, KeyStyle::Long).await.unwrap_err();
+      assert_eq!(err.to_string(), "the input file is already in Long keys", "{}", name);
+
+      for path in [short_path, long_path] {
+        std::fs::remove_file(path).unwrap();
+      }
+    }
+  }
+}
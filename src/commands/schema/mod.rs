@@ -0,0 +1,31 @@
+use crate::model::{
+  header::WhirlpoolFileKind,
+  schema::{file_schema, json_schema::build_json_schema, typescript::build_typescript, KeyStyle},
+};
+use anyhow::Result;
+
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SchemaLanguage {
+  JsonSchema,
+  Typescript,
+}
+
+pub async fn process(
+  kind: WhirlpoolFileKind,
+  language: SchemaLanguage,
+  schema_file_path: String,
+  key_style: KeyStyle,
+) -> Result<()> {
+  println!("build schema...");
+  let file_schema = file_schema(kind);
+
+  let schema = match language {
+    SchemaLanguage::JsonSchema => serde_json::to_string_pretty(&build_json_schema(&file_schema, key_style))? + "\n",
+    SchemaLanguage::Typescript => build_typescript(&file_schema, key_style),
+  };
+  std::fs::write(&schema_file_path, schema)?;
+
+  println!("definitions: {}", file_schema.definitions.iter().count());
+
+  Ok(())
+}
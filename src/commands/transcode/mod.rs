@@ -0,0 +1,42 @@
+use crate::{
+  io::{
+    compression::Compression,
+    event::{load_from_local_whirlpool_event_file, EventEncoding, EventFileWriter},
+  },
+  model::{
+    event::WHIRLPOOL_EVENT_FORMAT_VERSION,
+    header::WhirlpoolFileHeader,
+    schema::KeyStyle,
+  },
+};
+use anyhow::Result;
+use std::fs::File;
+
+pub async fn process(
+  input_whirlpool_event_file_path: String,
+  output_whirlpool_event_file_path: String,
+  encoding: EventEncoding,
+  compression: Compression,
+  compression_level: Option<i32>,
+  seekable: bool,
+) -> Result<()> {
+  // both encodings are decoded into the same structs, so the conversion is lossless
+  let (header, event_block_iter) = load_from_local_whirlpool_event_file(&input_whirlpool_event_file_path);
+
+  // blocks are upgraded to the current format while decoding, so is the header (if any)
+  let header = header.map(|header| WhirlpoolFileHeader {
+    format_version: WHIRLPOOL_EVENT_FORMAT_VERSION,
+    ..header
+  });
+
+  let f = File::create(output_whirlpool_event_file_path).unwrap();
+  let mut writer = EventFileWriter::new(f, header, encoding, KeyStyle::Short, compression, compression_level, seekable)?;
+
+  for event_block in event_block_iter {
+    writer.write(event_block).await?;
+  }
+
+  writer.finish().await?;
+
+  Ok(())
+}
@@ -0,0 +1,225 @@
+use crate::{
+  commands::{ohlcv::io::build_with_local_file_storage, CheckOutcome},
+  model::{
+    event::{definition::TransferInfo, WhirlpoolEvent, WhirlpoolEventTransaction, WHIRLPOOL_EVENT_FORMAT_VERSION},
+    header::WhirlpoolFileKind,
+    pool::PoolStateTracker,
+  },
+};
+use anyhow::{bail, Result};
+use std::collections::{BTreeMap, HashMap};
+use whirlpool_replayer::serde::AccountDataStoreConfig;
+
+struct Validator {
+  // events are checked against the pool state before they are applied
+  pools: PoolStateTracker,
+  decimals: HashMap<String, u8>,
+  last_slot: u64,
+  last_block_time: i64,
+  violations: BTreeMap<&'static str, u64>,
+}
+
+pub async fn process(
+  whirlpool_state_file_path: String,
+  whirlpool_token_file_path: String,
+  whirlpool_event_file_path: String,
+) -> Result<CheckOutcome> {
+  println!("open files...");
+  let (state, event_header, event_block_iter, decimals) = build_with_local_file_storage(
+    whirlpool_state_file_path,
+    whirlpool_token_file_path,
+    whirlpool_event_file_path,
+    &AccountDataStoreConfig::OnDisk(None),
+  );
+
+  if let Some(event_header) = &event_header {
+    if !event_header.is_compatible_with(WhirlpoolFileKind::Event, WHIRLPOOL_EVENT_FORMAT_VERSION) {
+      bail!("unsupported event file (kind: {:?}, format version: {})", event_header.kind, event_header.format_version);
+    }
+  }
+
+  println!("traverse accounts...");
+  let pools = PoolStateTracker::from_accounts(&state.accounts, state.slot, state.block_time, false)?;
+
+  let mut validator = Validator {
+    pools,
+    decimals,
+    last_slot: state.slot,
+    last_block_time: state.block_time,
+    violations: BTreeMap::new(),
+  };
+
+  println!("validate events...");
+  for event_block in event_block_iter {
+    validator.validate_block(event_block.slot, event_block.block_time);
+    for (transaction_index, transaction) in event_block.transactions.into_iter().enumerate() {
+      let location = format!("slot {} tx {}", event_block.slot, transaction.signature);
+      validator.validate_order(&location, transaction_index, &transaction);
+      for entry in transaction.events {
+        validator.validate_event(&location, &entry.event);
+        validator.pools.apply_event(event_block.slot, event_block.block_time, &entry.event);
+      }
+    }
+  }
+
+  println!("report:");
+  let mut total = 0;
+  for (kind, count) in validator.violations.iter() {
+    println!("  {}: {}", kind, count);
+    total += count;
+  }
+  println!("  total: {}", total);
+
+  if total > 0 {
+    return Ok(CheckOutcome::Failed);
+  }
+
+  Ok(CheckOutcome::Passed)
+}
+
+impl Validator {
+  fn report(&mut self, kind: &'static str, location: &str, message: String) {
+    println!("[{}] {}: {}", kind, location, message);
+    *self.violations.entry(kind).or_default() += 1;
+  }
+
+  fn validate_block(&mut self, slot: u64, block_time: i64) {
+    let location = format!("slot {}", slot);
+    if slot <= self.last_slot {
+      self.report("slot", &location, format!("slot is not increasing (previous: {})", self.last_slot));
+    }
+    if block_time < self.last_block_time {
+      self.report("block time", &location, format!("block time {} is before the previous {}", block_time, self.last_block_time));
+    }
+    self.last_slot = slot;
+    self.last_block_time = block_time;
+  }
+
+  // transaction indexes follow the position in the block, and events follow the instruction order
+  fn validate_order(&mut self, location: &str, transaction_index: usize, transaction: &WhirlpoolEventTransaction) {
+    if transaction.index as usize != transaction_index {
+      self.report("order", location, format!("transaction index {} does not match the position {}", transaction.index, transaction_index));
+    }
+
+    let positions = transaction
+      .events
+      .iter()
+      .filter_map(|entry| entry.instruction_index.zip(entry.sub_index))
+      .collect::<Vec<_>>();
+    if positions.windows(2).any(|pair| pair[0] >= pair[1]) {
+      self.report("order", location, format!("event indexes are not increasing: {:?}", positions));
+    }
+  }
+
+  fn validate_event(&mut self, location: &str, event: &WhirlpoolEvent) {
+    match event {
+      WhirlpoolEvent::PoolInitialized(payload) => {
+        self.validate_decimals(location, &payload.token_mint_a, payload.token_decimals_a);
+        self.validate_decimals(location, &payload.token_mint_b, payload.token_decimals_b);
+      }
+      WhirlpoolEvent::Traded(payload) => {
+        self.validate_transfer(location, &payload.transfer_in);
+        self.validate_transfer(location, &payload.transfer_out);
+        let Some(pool) = self.pools.current(&payload.whirlpool) else {
+          return self.report("unknown whirlpool", location, format!("traded on {}", payload.whirlpool));
+        };
+
+        let previous_sqrt_price = pool.sqrt_price;
+        if payload.old_sqrt_price != previous_sqrt_price {
+          self.report("sqrt price chain", location, format!(
+            "{}: old sqrt price {} does not match the previous {}",
+            payload.whirlpool, payload.old_sqrt_price, previous_sqrt_price
+          ));
+        }
+      }
+      WhirlpoolEvent::LiquidityDeposited(payload) => {
+        self.validate_transfer(location, &payload.transfer_a);
+        self.validate_transfer(location, &payload.transfer_b);
+        let in_range = payload.lower_tick_index <= payload.whirlpool_current_tick_index
+          && payload.whirlpool_current_tick_index < payload.upper_tick_index;
+        let expected_new_liquidity = if in_range {
+          payload.old_whirlpool_liquidity.checked_add(payload.liquidity_delta)
+        } else {
+          Some(payload.old_whirlpool_liquidity)
+        };
+        self.validate_liquidity(location, &payload.whirlpool, payload.old_whirlpool_liquidity, payload.new_whirlpool_liquidity, expected_new_liquidity);
+      }
+      WhirlpoolEvent::LiquidityWithdrawn(payload) => {
+        self.validate_transfer(location, &payload.transfer_a);
+        self.validate_transfer(location, &payload.transfer_b);
+        let in_range = payload.lower_tick_index <= payload.whirlpool_current_tick_index
+          && payload.whirlpool_current_tick_index < payload.upper_tick_index;
+        let expected_new_liquidity = if in_range {
+          payload.old_whirlpool_liquidity.checked_sub(payload.liquidity_delta)
+        } else {
+          Some(payload.old_whirlpool_liquidity)
+        };
+        self.validate_liquidity(location, &payload.whirlpool, payload.old_whirlpool_liquidity, payload.new_whirlpool_liquidity, expected_new_liquidity);
+      }
+      WhirlpoolEvent::LiquidityPatched(payload) => {
+        let expected_new_liquidity = payload.old_whirlpool_liquidity.checked_add(payload.liquidity_delta);
+        self.validate_liquidity(location, &payload.whirlpool, payload.old_whirlpool_liquidity, payload.new_whirlpool_liquidity, expected_new_liquidity);
+      }
+      WhirlpoolEvent::PositionFeesHarvested(payload) => {
+        self.validate_transfer(location, &payload.transfer_a);
+        self.validate_transfer(location, &payload.transfer_b);
+      }
+      WhirlpoolEvent::PositionRewardHarvested(payload) => {
+        self.validate_transfer(location, &payload.transfer_reward);
+      }
+      WhirlpoolEvent::ProtocolFeesCollected(payload) => {
+        self.validate_transfer(location, &payload.transfer_a);
+        self.validate_transfer(location, &payload.transfer_b);
+      }
+      _ => { /* ignore */ }
+    }
+  }
+
+  fn validate_liquidity(
+    &mut self,
+    location: &str,
+    whirlpool: &str,
+    old_liquidity: u128,
+    new_liquidity: u128,
+    expected_new_liquidity: Option<u128>,
+  ) {
+    let Some(pool) = self.pools.current(whirlpool) else {
+      return self.report("unknown whirlpool", location, format!("liquidity changed on {}", whirlpool));
+    };
+
+    let previous_liquidity = pool.liquidity;
+
+    if let Some(previous_liquidity) = previous_liquidity {
+      if old_liquidity != previous_liquidity {
+        self.report("liquidity chain", location, format!(
+          "{}: old liquidity {} does not match the previous {}",
+          whirlpool, old_liquidity, previous_liquidity
+        ));
+      }
+    }
+    if expected_new_liquidity != Some(new_liquidity) {
+      self.report("liquidity chain", location, format!(
+        "{}: new liquidity {} does not match the delta applied ({:?})",
+        whirlpool, new_liquidity, expected_new_liquidity
+      ));
+    }
+  }
+
+  fn validate_transfer(&mut self, location: &str, transfer: &TransferInfo) {
+    self.validate_decimals(location, &transfer.mint, transfer.decimals);
+  }
+
+  // mints missing in the token file are checked against their first appearance
+  fn validate_decimals(&mut self, location: &str, mint: &str, decimals: u8) {
+    match self.decimals.get(mint) {
+      Some(known_decimals) if *known_decimals != decimals => {
+        let message = format!("{}: decimals {} does not match {}", mint, decimals, known_decimals);
+        self.report("decimals", location, message);
+      }
+      Some(_) => {}
+      None => {
+        self.decimals.insert(mint.to_string(), decimals);
+      }
+    }
+  }
+}
@@ -0,0 +1,186 @@
+use crate::model::account::DecodedTickArray;
+use anchor_lang::{AccountDeserialize, Discriminator};
+use whirlpool_base::state::{FeeTier, Position, Whirlpool, WhirlpoolsConfig};
+
+// (field path, value) pairs of a decoded account
+pub type Fields = Vec<(String, String)>;
+
+// decodes known account types into comparable fields
+pub fn decode_account_fields(data: &[u8]) -> Option<(&'static str, Fields)> {
+  if data.starts_with(&Whirlpool::DISCRIMINATOR) {
+    let whirlpool = Whirlpool::try_deserialize(&mut &data[..]).ok()?;
+    return Some(("Whirlpool", whirlpool_fields(&whirlpool)));
+  }
+  if data.starts_with(&Position::DISCRIMINATOR) {
+    let position = Position::try_deserialize(&mut &data[..]).ok()?;
+    return Some(("Position", position_fields(&position)));
+  }
+  if DecodedTickArray::is_tick_array(data) {
+    let tick_array = DecodedTickArray::decode(data)?;
+    return Some(("TickArray", tick_array_fields(&tick_array)));
+  }
+  if data.starts_with(&WhirlpoolsConfig::DISCRIMINATOR) {
+    let config = WhirlpoolsConfig::try_deserialize(&mut &data[..]).ok()?;
+    return Some(("WhirlpoolsConfig", whirlpools_config_fields(&config)));
+  }
+  if data.starts_with(&FeeTier::DISCRIMINATOR) {
+    let fee_tier = FeeTier::try_deserialize(&mut &data[..]).ok()?;
+    return Some(("FeeTier", fee_tier_fields(&fee_tier)));
+  }
+  None
+}
+
+// pairs of (field path, replayed value, expected value) that differ
+pub fn diff_fields(replayed: &Fields, expected: &Fields) -> Vec<(String, String, String)> {
+  replayed
+    .iter()
+    .zip(expected.iter())
+    .filter(|((_, replayed_value), (_, expected_value))| replayed_value != expected_value)
+    .map(|((path, replayed_value), (_, expected_value))| (path.clone(), replayed_value.clone(), expected_value.clone()))
+    .collect()
+}
+
+// ranges [start, end) of differing bytes, bytes beyond the shorter data differ as well.
+// used when the decoded fields do not explain a mismatch (padding, fields not listed below)
+pub fn diff_bytes(replayed: &[u8], expected: &[u8]) -> Vec<(usize, usize)> {
+  let mut ranges: Vec<(usize, usize)> = Vec::new();
+  let len = replayed.len().max(expected.len());
+  for offset in 0..len {
+    if replayed.get(offset) == expected.get(offset) {
+      continue;
+    }
+    match ranges.last_mut() {
+      Some((_, end)) if *end == offset => *end = offset + 1,
+      _ => ranges.push((offset, offset + 1)),
+    }
+  }
+  ranges
+}
+
+macro_rules! push_fields {
+  ($fields:ident, $prefix:expr, $value:expr, [$($field:ident),* $(,)?]) => {
+    $( $fields.push((format!("{}{}", $prefix, stringify!($field)), $value.$field.to_string())); )*
+  };
+}
+
+fn whirlpool_fields(whirlpool: &Whirlpool) -> Fields {
+  let mut fields = Fields::new();
+  push_fields!(fields, "", whirlpool, [
+    whirlpools_config,
+    tick_spacing,
+    fee_rate,
+    protocol_fee_rate,
+    liquidity,
+    sqrt_price,
+    tick_current_index,
+    protocol_fee_owed_a,
+    protocol_fee_owed_b,
+    token_mint_a,
+    token_vault_a,
+    fee_growth_global_a,
+    token_mint_b,
+    token_vault_b,
+    fee_growth_global_b,
+    reward_last_updated_timestamp,
+  ]);
+  fields.push(("whirlpool_bump".to_string(), format!("{:?}", whirlpool.whirlpool_bump)));
+  fields.push(("tick_spacing_seed".to_string(), format!("{:?}", whirlpool.tick_spacing_seed)));
+  for (i, reward_info) in whirlpool.reward_infos.iter().enumerate() {
+    push_fields!(fields, format!("reward_infos[{}].", i), reward_info, [
+      mint,
+      vault,
+      authority,
+      emissions_per_second_x64,
+      growth_global_x64,
+    ]);
+  }
+  fields
+}
+
+fn position_fields(position: &Position) -> Fields {
+  let mut fields = Fields::new();
+  push_fields!(fields, "", position, [
+    whirlpool,
+    position_mint,
+    liquidity,
+    tick_lower_index,
+    tick_upper_index,
+    fee_growth_checkpoint_a,
+    fee_owed_a,
+    fee_growth_checkpoint_b,
+    fee_owed_b,
+  ]);
+  for (i, reward_info) in position.reward_infos.iter().enumerate() {
+    push_fields!(fields, format!("reward_infos[{}].", i), reward_info, [
+      growth_inside_checkpoint,
+      amount_owed,
+    ]);
+  }
+  fields
+}
+
+fn tick_array_fields(tick_array: &DecodedTickArray) -> Fields {
+  let mut fields = Fields::new();
+  push_fields!(fields, "", tick_array, [start_tick_index, whirlpool]);
+  for (i, tick) in tick_array.ticks.iter().enumerate() {
+    push_fields!(fields, format!("ticks[{}].", i), tick, [
+      initialized,
+      liquidity_net,
+      liquidity_gross,
+      fee_growth_outside_a,
+      fee_growth_outside_b,
+    ]);
+    fields.push((format!("ticks[{}].reward_growths_outside", i), format!("{:?}", tick.reward_growths_outside)));
+  }
+  fields
+}
+
+fn whirlpools_config_fields(config: &WhirlpoolsConfig) -> Fields {
+  let mut fields = Fields::new();
+  push_fields!(fields, "", config, [
+    fee_authority,
+    collect_protocol_fees_authority,
+    reward_emissions_super_authority,
+    default_protocol_fee_rate,
+  ]);
+  fields
+}
+
+fn fee_tier_fields(fee_tier: &FeeTier) -> Fields {
+  let mut fields = Fields::new();
+  push_fields!(fields, "", fee_tier, [whirlpools_config, tick_spacing, default_fee_rate]);
+  fields
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use anchor_lang::AccountSerialize;
+
+  #[test]
+  fn diff_bytes_merges_adjacent_offsets() {
+    assert!(diff_bytes(&[1, 2, 3], &[1, 2, 3]).is_empty());
+    assert_eq!(diff_bytes(&[1, 2, 3, 4, 5], &[1, 0, 0, 4, 0]), vec![(1, 3), (4, 5)]);
+    // trailing bytes of the longer data
+    assert_eq!(diff_bytes(&[1, 2], &[1, 2, 3, 4]), vec![(2, 4)]);
+    assert_eq!(diff_bytes(&[1, 2, 9], &[1, 2]), vec![(2, 3)]);
+  }
+
+  #[test]
+  fn unlisted_config_bytes_are_reported() {
+    let config = WhirlpoolsConfig {
+      default_protocol_fee_rate: 300,
+      ..Default::default()
+    };
+    let mut replayed = Vec::new();
+    config.try_serialize(&mut replayed).unwrap();
+    // a byte after the listed fields (e.g. a field added to the account later)
+    let mut expected = replayed.clone();
+    expected.push(1);
+
+    let (_, replayed_fields) = decode_account_fields(&replayed).unwrap();
+    let (_, expected_fields) = decode_account_fields(&expected).unwrap();
+    assert!(diff_fields(&replayed_fields, &expected_fields).is_empty());
+    assert_eq!(diff_bytes(&replayed, &expected), vec![(replayed.len(), replayed.len() + 1)]);
+  }
+}
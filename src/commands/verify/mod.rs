@@ -0,0 +1,126 @@
+use crate::commands::{
+  event::{io::build_with_local_file_storage, replay::EventBlockReplayer},
+  CheckOutcome,
+};
+use anyhow::Result;
+use whirlpool_replayer::serde::AccountDataStoreConfig;
+
+mod fields;
+
+pub async fn process(
+  whirlpool_state_file_path: String,
+  whirlpool_token_file_path: String,
+  whirlpool_transaction_file_path: String,
+  next_whirlpool_state_file_path: String,
+) -> Result<CheckOutcome> {
+  // build replayer
+  let (replay_engine, program_data, transaction_iter, decimals, _state_slot) = build_with_local_file_storage(
+    whirlpool_state_file_path,
+    whirlpool_token_file_path,
+    whirlpool_transaction_file_path,
+    &AccountDataStoreConfig::OnDisk(None),
+  );
+  // replayed the same way as the event command, so the replayed state is the one its output is built from
+  let mut replayer = EventBlockReplayer::new(replay_engine, program_data, decimals, false);
+
+  println!("replay transactions...");
+  let mut last_slot = None;
+  for whirlpool_transaction in transaction_iter {
+    let (slot, _event_block) = replayer.replay(whirlpool_transaction);
+    last_slot = Some(slot.slot);
+  }
+
+  println!("load next state...");
+  let next_state = whirlpool_replayer::io::load_from_local_whirlpool_state_file(
+    &next_whirlpool_state_file_path,
+    &AccountDataStoreConfig::OnDisk(None),
+  );
+  // the next state may be taken at a later slot without whirlpool transactions
+  println!("replayed up to slot {:?}, next state is at slot {}", last_slot, next_state.slot);
+
+  println!("compare accounts...");
+  let replayed_accounts = replayer.replay_engine.get_accounts();
+  let expected_accounts = &next_state.accounts;
+
+  let mut compared = 0u64;
+  let mut mismatched = 0u64;
+  let mut only_in_replayed = 0u64;
+  let mut only_in_expected = 0u64;
+
+  replayed_accounts.traverse(|pubkey, replayed_data| {
+    compared += 1;
+    match expected_accounts.get(pubkey)? {
+      None => {
+        only_in_replayed += 1;
+        println!("only in replayed state: {}", pubkey);
+      }
+      Some(expected_data) if expected_data != *replayed_data => {
+        mismatched += 1;
+        report_mismatch(pubkey, replayed_data, &expected_data);
+      }
+      Some(_) => {}
+    }
+    Ok(())
+  })?;
+
+  expected_accounts.traverse(|pubkey, _| {
+    if replayed_accounts.get(pubkey)?.is_none() {
+      only_in_expected += 1;
+      println!("only in next state: {}", pubkey);
+    }
+    Ok(())
+  })?;
+
+  println!(
+    "compared: {}, mismatched: {}, only in replayed state: {}, only in next state: {}",
+    compared, mismatched, only_in_replayed, only_in_expected
+  );
+
+  if mismatched + only_in_replayed + only_in_expected > 0 {
+    return Ok(CheckOutcome::Failed);
+  }
+
+  Ok(CheckOutcome::Passed)
+}
+
+fn report_mismatch(pubkey: &str, replayed_data: &[u8], expected_data: &[u8]) {
+  match (fields::decode_account_fields(replayed_data), fields::decode_account_fields(expected_data)) {
+    (Some((replayed_type, replayed_fields)), Some((expected_type, expected_fields))) if replayed_type == expected_type => {
+      println!("mismatch: {} ({})", pubkey, replayed_type);
+      let diffs = fields::diff_fields(&replayed_fields, &expected_fields);
+      for (path, replayed_value, expected_value) in diffs.iter() {
+        println!("  {}: replayed={} expected={}", path, replayed_value, expected_value);
+      }
+      // the difference is in bytes not covered by the listed fields
+      if diffs.is_empty() {
+        report_byte_diffs(replayed_data, expected_data);
+      }
+    }
+    _ => {
+      // unknown or different account types are compared as raw bytes
+      println!(
+        "mismatch: {} (unknown, replayed {} bytes, expected {} bytes)",
+        pubkey,
+        replayed_data.len(),
+        expected_data.len()
+      );
+      report_byte_diffs(replayed_data, expected_data);
+    }
+  }
+}
+
+fn report_byte_diffs(replayed_data: &[u8], expected_data: &[u8]) {
+  for (start, end) in fields::diff_bytes(replayed_data, expected_data) {
+    println!(
+      "  bytes[{}..{}]: replayed={} expected={}",
+      start,
+      end,
+      to_hex(replayed_data.get(start..end.min(replayed_data.len())).unwrap_or_default()),
+      to_hex(expected_data.get(start..end.min(expected_data.len())).unwrap_or_default()),
+    );
+  }
+}
+
+fn to_hex(data: &[u8]) -> String {
+  data.iter().map(|b| format!("{:02x}", b)).collect()
+}
@@ -0,0 +1,64 @@
+use crate::{
+  commands::OutputArgs,
+  io::event::load_from_local_whirlpool_event_file,
+  model::{
+    event::WHIRLPOOL_EVENT_FORMAT_VERSION,
+    header::WhirlpoolFileKind,
+    wallet::{WalletAggregator, WHIRLPOOL_WALLET_FORMAT_VERSION},
+  },
+};
+use anyhow::{bail, Result};
+use std::{
+  fs::File,
+  io::{LineWriter, Write},
+};
+
+pub async fn process(
+  whirlpool_event_file_path: String,
+  whirlpool_wallet_file_path: String,
+  output: OutputArgs,
+) -> Result<()> {
+  println!("open files...");
+  let (event_header, event_block_iter) = load_from_local_whirlpool_event_file(&whirlpool_event_file_path);
+
+  if let Some(event_header) = &event_header {
+    println!("event file header: {:?}", event_header);
+    if !event_header.is_compatible_with(WhirlpoolFileKind::Event, WHIRLPOOL_EVENT_FORMAT_VERSION) {
+      bail!("unsupported event file (kind: {:?}, format version: {})", event_header.kind, event_header.format_version);
+    }
+  }
+
+  let wallet_header = if output.with_header {
+    let source_state_slot = event_header.as_ref().map(|header| header.source_state_slot).unwrap_or_default();
+    Some(crate::io::build_file_header(
+      WhirlpoolFileKind::Wallet,
+      WHIRLPOOL_WALLET_FORMAT_VERSION,
+      source_state_slot,
+      &[whirlpool_event_file_path.as_str()],
+    )?)
+  } else {
+    None
+  };
+
+  println!("process events...");
+  let mut aggregator = WalletAggregator::default();
+  for event_block in event_block_iter {
+    aggregator.apply_block(&event_block);
+  }
+
+  println!("write wallet file...");
+  let f = File::create(whirlpool_wallet_file_path)?;
+  let encoder = output.compression.create_writer(f, output.compression_level)?;
+  let mut writer = LineWriter::new(encoder);
+  if let Some(header) = wallet_header {
+    writer.write_all(serde_json::to_string(&header)?.as_bytes())?;
+    writer.write_all(b"\n")?;
+  }
+  for data in aggregator.into_wallet_data() {
+    writer.write_all(serde_json::to_string(&data)?.as_bytes())?;
+    writer.write_all(b"\n")?;
+  }
+  writer.into_inner().map_err(|err| err.into_error())?.finish()?;
+
+  Ok(())
+}
@@ -0,0 +1,183 @@
+use anyhow::Result;
+use flate2::{read::MultiGzDecoder, write::GzEncoder};
+use serde_derive::{Deserialize, Serialize};
+use std::{
+    fs::File,
+    io::{BufRead, BufReader, Read, Write},
+};
+
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+
+#[derive(clap::ValueEnum, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    Gzip,
+    Zstd,
+    None,
+}
+
+impl Compression {
+    pub fn create_writer(&self, file: File, level: Option<i32>) -> Result<CompressedWriter> {
+        match self {
+            Compression::Gzip => Ok(CompressedWriter::Gzip(GzEncoder::new(file, gzip_level(level)))),
+            Compression::Zstd => Ok(CompressedWriter::Zstd(zstd::Encoder::new(file, zstd_level(level))?)),
+            Compression::None => Ok(CompressedWriter::None(file)),
+        }
+    }
+
+    // compressed chunks can be concatenated: gzip members and zstd frames are decoded in sequence
+    pub fn compress_chunk(&self, data: &[u8], level: Option<i32>) -> Result<Vec<u8>> {
+        match self {
+            Compression::Gzip => {
+                let mut encoder = GzEncoder::new(Vec::new(), gzip_level(level));
+                encoder.write_all(data)?;
+                Ok(encoder.finish()?)
+            }
+            Compression::Zstd => Ok(zstd::encode_all(data, zstd_level(level))?),
+            Compression::None => Ok(data.to_vec()),
+        }
+    }
+}
+
+// the stream is complete only after finish(), which also returns the errors of writing its end
+// (dropping the writer would leave a truncated stream without reporting anything)
+pub enum CompressedWriter {
+    Gzip(GzEncoder<File>),
+    Zstd(zstd::Encoder<'static, File>),
+    None(File),
+}
+
+impl CompressedWriter {
+    pub fn get_ref(&self) -> &File {
+        match self {
+            CompressedWriter::Gzip(encoder) => encoder.get_ref(),
+            CompressedWriter::Zstd(encoder) => encoder.get_ref(),
+            CompressedWriter::None(file) => file,
+        }
+    }
+
+    pub fn finish(self) -> Result<File> {
+        match self {
+            CompressedWriter::Gzip(encoder) => Ok(encoder.finish()?),
+            CompressedWriter::Zstd(encoder) => Ok(encoder.finish()?),
+            CompressedWriter::None(mut file) => {
+                file.flush()?;
+                Ok(file)
+            }
+        }
+    }
+}
+
+impl Write for CompressedWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        match self {
+            CompressedWriter::Gzip(encoder) => encoder.write(buf),
+            CompressedWriter::Zstd(encoder) => encoder.write(buf),
+            CompressedWriter::None(file) => file.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match self {
+            CompressedWriter::Gzip(encoder) => encoder.flush(),
+            CompressedWriter::Zstd(encoder) => encoder.flush(),
+            CompressedWriter::None(file) => file.flush(),
+        }
+    }
+}
+
+pub fn detect_compression(magic: &[u8]) -> Compression {
+    if magic.starts_with(&GZIP_MAGIC) {
+        Compression::Gzip
+    } else if magic.starts_with(&ZSTD_MAGIC) {
+        Compression::Zstd
+    } else {
+        Compression::None
+    }
+}
+
+pub fn open_local_file_with_auto_decompression(file_path: &str) -> Result<Box<dyn Read + Send>> {
+    let mut buf = BufReader::new(File::open(file_path)?);
+    let compression = detect_compression(buf.fill_buf()?);
+
+    match compression {
+        Compression::Gzip => Ok(Box::new(MultiGzDecoder::new(buf))),
+        Compression::Zstd => Ok(Box::new(zstd::Decoder::with_buffer(buf)?)),
+        Compression::None => Ok(Box::new(buf)),
+    }
+}
+
+fn gzip_level(level: Option<i32>) -> flate2::Compression {
+    match level {
+        Some(level) => flate2::Compression::new(level.clamp(0, 9) as u32),
+        None => flate2::Compression::default(),
+    }
+}
+
+fn zstd_level(level: Option<i32>) -> i32 {
+    // 0 means the default level of zstd
+    level.unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{model::event::binary::BINARY_RECORD_MAGIC, test_support::write_temp_file};
+
+    const COMPRESSIONS: [Compression; 3] = [Compression::Gzip, Compression::Zstd, Compression::None];
+
+    #[test]
+    fn detect_compression_from_magic_bytes() {
+        for compression in COMPRESSIONS {
+            let compressed = compression.compress_chunk(b"{\"s\":1}\n", None).unwrap();
+            assert_eq!(detect_compression(&compressed), compression);
+        }
+
+        // uncompressed event files start with a JSON line or a binary record
+        assert_eq!(detect_compression(b"{\"fv\":6}"), Compression::None);
+        assert_eq!(detect_compression(&BINARY_RECORD_MAGIC), Compression::None);
+        assert_eq!(detect_compression(&[]), Compression::None);
+        // a partial magic is not enough
+        assert_eq!(detect_compression(&ZSTD_MAGIC[..2]), Compression::None);
+    }
+
+    #[test]
+    fn decompress_concatenated_chunks() {
+        let chunks: [&[u8]; 3] = [b"{\"fv\":6}\n", b"{\"s\":1}\n{\"s\":2}\n", b"{\"s\":3}\n"];
+        for compression in COMPRESSIONS {
+            let mut file = Vec::new();
+            for chunk in chunks {
+                file.extend(compression.compress_chunk(chunk, Some(3)).unwrap());
+            }
+            let path = write_temp_file(&format!("chunks-{:?}", compression), &file);
+
+            let mut data = Vec::new();
+            open_local_file_with_auto_decompression(&path)
+                .unwrap()
+                .read_to_end(&mut data)
+                .unwrap();
+            assert_eq!(data, chunks.concat(), "{:?}", compression);
+
+            std::fs::remove_file(path).unwrap();
+        }
+    }
+
+    #[test]
+    fn decompress_stream_written_by_create_writer() {
+        for compression in COMPRESSIONS {
+            let path = write_temp_file(&format!("writer-{:?}", compression), &[]);
+            let mut writer = compression.create_writer(File::create(&path).unwrap(), None).unwrap();
+            writer.write_all(b"{\"s\":1}\n").unwrap();
+            writer.finish().unwrap();
+
+            let mut data = Vec::new();
+            open_local_file_with_auto_decompression(&path)
+                .unwrap()
+                .read_to_end(&mut data)
+                .unwrap();
+            assert_eq!(data, b"{\"s\":1}\n", "{:?}", compression);
+
+            std::fs::remove_file(path).unwrap();
+        }
+    }
+}
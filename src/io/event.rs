@@ -0,0 +1,522 @@
+use super::compression::{self, Compression};
+use crate::model::{
+    event::{
+        binary::{decode_binary_record_payload, encode_binary_record, BINARY_RECORD_MAGIC},
+        migration::{decode_whirlpool_event_block, LEGACY_EVENT_FORMAT_VERSION},
+        WhirlpoolEventBlock,
+    },
+    header::{WhirlpoolFileHeader, WhirlpoolFileKind},
+    schema::{
+        file_schema,
+        rekey::{rekey_jsonl, Rekeyed},
+        FileSchema, KeyStyle,
+    },
+};
+use anyhow::{bail, Result};
+use futures::StreamExt;
+use serde_derive::{Deserialize, Serialize};
+use std::{
+    fs::File,
+    io::{BufRead, BufReader, Seek, Write},
+    sync::OnceLock,
+};
+use tokio::{
+    sync::{mpsc, oneshot},
+    task::JoinHandle,
+};
+use tokio_stream::wrappers::ReceiverStream;
+
+pub type WhirlpoolEventBlockIter = Box<dyn Iterator<Item = WhirlpoolEventBlock> + Send>;
+
+#[derive(clap::ValueEnum, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventEncoding {
+    Jsonl,
+    Binary,
+}
+
+pub fn load_from_local_whirlpool_event_file(
+    whirlpool_event_file_path: &str,
+) -> (Option<WhirlpoolFileHeader>, WhirlpoolEventBlockIter) {
+    // compression is detected from magic bytes
+    let decoder =
+        compression::open_local_file_with_auto_decompression(whirlpool_event_file_path).unwrap();
+    let mut records = EventRecordReader::new(BufReader::new(decoder)).peekable();
+
+    // the first line may be a header
+    let header = match records.peek() {
+        Some(EventRecord::Line(jsonl)) => WhirlpoolFileHeader::from_jsonl(jsonl),
+        _ => None,
+    };
+    if header.is_some() {
+        records.next();
+    }
+
+    // older blocks are upgraded into the current structs
+    let format_version = header
+        .as_ref()
+        .map(|header| header.format_version)
+        .unwrap_or(LEGACY_EVENT_FORMAT_VERSION);
+
+    // blocks in long keys are rekeyed to short keys before decoding (binary records have no keys)
+    let key_style = header.as_ref().map(|header| header.key_style()).unwrap_or(KeyStyle::Short);
+
+    let iter = records
+        .map(move |record| match (record, key_style) {
+            (EventRecord::Line(jsonl), KeyStyle::Long) => {
+                let schema = event_file_schema();
+                EventRecord::Line(rekey_jsonl(&jsonl, &schema.root, &schema.definitions, KeyStyle::Long, KeyStyle::Short).unwrap())
+            }
+            (record, _) => record,
+        })
+        .flat_map(move |record| decode_event_record(record, format_version).unwrap());
+
+    (header, Box::new(iter))
+}
+
+// a JSON line holds one block, a binary record holds a chunk of blocks
+pub fn decode_event_record(record: EventRecord, format_version: u32) -> Result<Vec<WhirlpoolEventBlock>> {
+    match record {
+        EventRecord::Line(jsonl) => Ok(vec![decode_whirlpool_event_block(&jsonl, format_version)?]),
+        // binary records are always of the current format version (see binary.rs)
+        EventRecord::Binary(payload) => decode_binary_record_payload(&payload),
+    }
+}
+
+// JSON lines and binary records can be mixed in a stream
+pub enum EventRecord {
+    Line(String),
+    Binary(Vec<u8>),
+}
+
+pub struct EventRecordReader<R: BufRead> {
+    reader: R,
+}
+
+impl<R: BufRead> EventRecordReader<R> {
+    pub fn new(reader: R) -> Self {
+        Self { reader }
+    }
+
+    fn read_record(&mut self) -> Result<Option<EventRecord>> {
+        let first_byte = match self.reader.fill_buf()?.first() {
+            Some(b) => *b,
+            None => return Ok(None),
+        };
+
+        if first_byte == BINARY_RECORD_MAGIC[0] {
+            let mut magic = [0u8; 4];
+            self.reader.read_exact(&mut magic)?;
+            if magic != BINARY_RECORD_MAGIC {
+                bail!("invalid binary record magic: {:?}", magic);
+            }
+            let mut length = [0u8; 4];
+            self.reader.read_exact(&mut length)?;
+            let mut payload = vec![0u8; u32::from_le_bytes(length) as usize];
+            self.reader.read_exact(&mut payload)?;
+            return Ok(Some(EventRecord::Binary(payload)));
+        }
+
+        let mut line = String::new();
+        self.reader.read_line(&mut line)?;
+        if line.ends_with('\n') {
+            line.pop();
+        }
+        Ok(Some(EventRecord::Line(line)))
+    }
+}
+
+impl<R: BufRead> Iterator for EventRecordReader<R> {
+    type Item = EventRecord;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read_record().unwrap()
+    }
+}
+
+// blocks are serialized in chunks on worker threads, and the write thread streams them through one encoder,
+// so a compressed file is a single gzip member or zstd frame.
+// seekable output compresses each chunk on the workers instead, as an independent gzip member or zstd frame,
+// so that the index can seek to a chunk and checkpoints can resume at a chunk boundary.
+// readers of seekable output must decode all members in sequence, and the header records the chunk size (see model/header.rs).
+pub const BLOCKS_PER_CHUNK: usize = 256;
+const CHUNK_CHANNEL_CAPACITY: usize = 16;
+
+enum WriterMessage {
+    Chunk(Vec<WhirlpoolEventBlock>),
+    // replies with the output offset after all preceding chunks are written
+    Sync(oneshot::Sender<u64>),
+}
+
+enum WriterOutput {
+    Chunk(Vec<u8>),
+    Sync(oneshot::Sender<u64>),
+}
+
+pub struct EventFileWriter {
+    sender: mpsc::Sender<WriterMessage>,
+    chunk: Vec<WhirlpoolEventBlock>,
+    // the offset of a chunk boundary is a valid end of the file only if no encoder spans the chunks
+    syncable: bool,
+    handle: JoinHandle<Result<()>>,
+}
+
+impl EventFileWriter {
+    // writing starts at the current position of the file (the end of the kept part when resuming)
+    pub fn new(
+        mut file: File,
+        header: Option<WhirlpoolFileHeader>,
+        encoding: EventEncoding,
+        key_style: KeyStyle,
+        compression: Compression,
+        compression_level: Option<i32>,
+        seekable: bool,
+    ) -> Result<Self> {
+        if encoding == EventEncoding::Binary && key_style == KeyStyle::Long {
+            bail!("long keys are only available with jsonl encoding");
+        }
+
+        // seekable output is compressed chunk by chunk on the workers, otherwise as one stream on the write thread
+        let (chunk_compression, stream_compression) = if seekable {
+            (compression, Compression::None)
+        } else {
+            (Compression::None, compression)
+        };
+
+        let header_output = match header {
+            Some(header) => {
+                // the header is always in short keys, and records the key style of the blocks
+                let header = WhirlpoolFileHeader {
+                    compression_chunk_blocks: (chunk_compression != Compression::None).then_some(BLOCKS_PER_CHUNK as u32),
+                    key_style: (key_style != KeyStyle::Short).then_some(key_style),
+                    ..header
+                };
+                let mut jsonl = serde_json::to_string(&header)?.into_bytes();
+                jsonl.push(b'\n');
+                Some(compress_chunk(chunk_compression, jsonl, compression_level)?)
+            }
+            None => None,
+        };
+
+        let workers = std::thread::available_parallelism()
+            .map(|n| n.get())
+            .unwrap_or(4);
+
+        let (sender, receiver) = mpsc::channel::<WriterMessage>(CHUNK_CHANNEL_CAPACITY);
+        let (output_sender, mut output_receiver) = mpsc::channel::<WriterOutput>(CHUNK_CHANNEL_CAPACITY);
+
+        // file I/O blocks, so it runs on a blocking thread rather than on a runtime worker
+        let mut offset = file.stream_position()?;
+        let write_handle = tokio::task::spawn_blocking(move || {
+            let mut writer = stream_compression.create_writer(file, compression_level)?;
+            if let Some(header_output) = header_output {
+                writer.write_all(&header_output)?;
+                offset += header_output.len() as u64;
+            }
+
+            while let Some(output) = output_receiver.blocking_recv() {
+                match output {
+                    WriterOutput::Chunk(chunk_output) => {
+                        writer.write_all(&chunk_output)?;
+                        offset += chunk_output.len() as u64;
+                    }
+                    WriterOutput::Sync(reply) => {
+                        writer.flush()?;
+                        writer.get_ref().sync_data()?;
+                        let _ = reply.send(offset);
+                    }
+                }
+            }
+
+            writer.finish()?;
+            Ok::<(), anyhow::Error>(())
+        });
+
+        let handle = tokio::spawn(async move {
+            // buffered keeps the output order of chunks
+            let mut outputs = ReceiverStream::new(receiver)
+                .map(move |message| async move {
+                    match message {
+                        WriterMessage::Chunk(blocks) => {
+                            let chunk_output = tokio::task::spawn_blocking(move || {
+                                let encoded = encode_chunk(&blocks, encoding, key_style)?;
+                                compress_chunk(chunk_compression, encoded, compression_level)
+                            })
+                            .await??;
+                            Ok::<WriterOutput, anyhow::Error>(WriterOutput::Chunk(chunk_output))
+                        }
+                        WriterMessage::Sync(reply) => Ok(WriterOutput::Sync(reply)),
+                    }
+                })
+                .buffered(workers);
+
+            while let Some(output) = outputs.next().await {
+                // the write thread has stopped on an error, which is returned below
+                if output_sender.send(output?).await.is_err() {
+                    break;
+                }
+            }
+
+            // closing the channel lets the write thread drain and exit
+            drop(output_sender);
+            write_handle.await?
+        });
+
+        Ok(Self {
+            sender,
+            chunk: Vec::with_capacity(BLOCKS_PER_CHUNK),
+            syncable: stream_compression == Compression::None,
+            handle,
+        })
+    }
+
+    pub async fn write(&mut self, event_block: WhirlpoolEventBlock) -> Result<()> {
+        self.chunk.push(event_block);
+        if self.chunk.len() >= BLOCKS_PER_CHUNK {
+            self.send_chunk().await?;
+        }
+        Ok(())
+    }
+
+    // true if no block is waiting for its chunk to be filled
+    pub fn is_at_chunk_boundary(&self) -> bool {
+        self.chunk.is_empty()
+    }
+
+    // waits until all written blocks are on disk and returns the output offset (seekable or uncompressed output only).
+    // calling this at a chunk boundary keeps the output identical to the one without sync.
+    pub async fn sync(&mut self) -> Result<u64> {
+        if !self.syncable {
+            bail!("compressed output can be synced only if it is seekable");
+        }
+
+        if !self.chunk.is_empty() {
+            self.send_chunk().await?;
+        }
+
+        let (reply, offset) = oneshot::channel();
+        if self.sender.send(WriterMessage::Sync(reply)).await.is_err() {
+            bail!("event file writer has stopped");
+        }
+        Ok(offset.await?)
+    }
+
+    pub async fn finish(mut self) -> Result<()> {
+        if !self.chunk.is_empty() {
+            self.send_chunk().await?;
+        }
+
+        // closing the channel lets the writer task drain and exit
+        drop(self.sender);
+        self.handle.await?
+    }
+
+    async fn send_chunk(&mut self) -> Result<()> {
+        let chunk = std::mem::replace(&mut self.chunk, Vec::with_capacity(BLOCKS_PER_CHUNK));
+        if self.sender.send(WriterMessage::Chunk(chunk)).await.is_err() {
+            bail!("event file writer has stopped");
+        }
+        Ok(())
+    }
+}
+
+static EVENT_FILE_SCHEMA: OnceLock<FileSchema> = OnceLock::new();
+
+fn event_file_schema() -> &'static FileSchema {
+    EVENT_FILE_SCHEMA.get_or_init(|| file_schema(WhirlpoolFileKind::Event))
+}
+
+fn compress_chunk(compression: Compression, data: Vec<u8>, level: Option<i32>) -> Result<Vec<u8>> {
+    match compression {
+        Compression::None => Ok(data),
+        _ => compression.compress_chunk(&data, level),
+    }
+}
+
+fn encode_chunk(blocks: &[WhirlpoolEventBlock], encoding: EventEncoding, key_style: KeyStyle) -> Result<Vec<u8>> {
+    match (encoding, key_style) {
+        (EventEncoding::Jsonl, KeyStyle::Short) => {
+            let mut jsonl = Vec::new();
+            for block in blocks {
+                serde_json::to_writer(&mut jsonl, block)?;
+                jsonl.push(b'\n');
+            }
+            Ok(jsonl)
+        }
+        (EventEncoding::Jsonl, KeyStyle::Long) => {
+            let schema = event_file_schema();
+            let mut jsonl = Vec::new();
+            for block in blocks {
+                let block = serde_json::to_value(block)?;
+                let rekeyed = Rekeyed::new(&block, &schema.root, &schema.definitions, KeyStyle::Short, KeyStyle::Long);
+                serde_json::to_writer(&mut jsonl, &rekeyed)?;
+                jsonl.push(b'\n');
+            }
+            Ok(jsonl)
+        }
+        (EventEncoding::Binary, _) => encode_binary_record(blocks),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        io::build_file_header,
+        model::event::{fixtures, WHIRLPOOL_EVENT_FORMAT_VERSION},
+        test_support::{temp_file_path, write_event_file},
+    };
+    use flate2::read::GzDecoder;
+    use std::io::Read;
+
+    // several chunks and a partial last chunk
+    fn test_blocks() -> Vec<WhirlpoolEventBlock> {
+        fixtures::generate_blocks(BLOCKS_PER_CHUNK * 3 + 17)
+    }
+
+    fn decompress(path: &str) -> Vec<u8> {
+        let mut data = Vec::new();
+        compression::open_local_file_with_auto_decompression(path)
+            .unwrap()
+            .read_to_end(&mut data)
+            .unwrap();
+        data
+    }
+
+    #[tokio::test]
+    async fn chunked_output_matches_sequential_writer() {
+        // the sequential writer serializes all lines on one thread
+        let mut sequential_lines = Vec::new();
+        for block in test_blocks() {
+            serde_json::to_writer(&mut sequential_lines, &block).unwrap();
+            sequential_lines.push(b'\n');
+        }
+
+        for compression in [Compression::Gzip, Compression::Zstd, Compression::None] {
+            for seekable in [false, true] {
+                let path = temp_file_path(&format!("chunked-{:?}-{}", compression, seekable));
+                write_event_file(&path, Some(fixtures::current_header()), test_blocks(), EventEncoding::Jsonl, compression, seekable).await;
+
+                let decompressed = decompress(&path);
+                let header_length = decompressed.iter().position(|b| *b == b'\n').unwrap() + 1;
+                assert_eq!(&decompressed[header_length..], sequential_lines.as_slice(), "{:?} {}", compression, seekable);
+
+                let (header, blocks) = load_from_local_whirlpool_event_file(&path);
+                let expected_chunk_blocks = (seekable && compression != Compression::None).then_some(BLOCKS_PER_CHUNK as u32);
+                assert_eq!(header.unwrap().compression_chunk_blocks, expected_chunk_blocks);
+                assert!(blocks.eq(test_blocks()), "{:?} {}", compression, seekable);
+
+                std::fs::remove_file(path).unwrap();
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn output_is_a_single_member_unless_seekable() {
+        for compression in [Compression::Gzip, Compression::Zstd] {
+            let path = temp_file_path(&format!("single-member-{:?}", compression));
+            write_event_file(&path, Some(fixtures::current_header()), test_blocks(), EventEncoding::Jsonl, compression, false).await;
+
+            // single-member and single-frame decoders read the whole file
+            let mut data = Vec::new();
+            match compression {
+                Compression::Gzip => GzDecoder::new(File::open(&path).unwrap()).read_to_end(&mut data).unwrap(),
+                _ => zstd::Decoder::new(File::open(&path).unwrap()).unwrap().single_frame().read_to_end(&mut data).unwrap(),
+            };
+            assert_eq!(data, decompress(&path), "{:?}", compression);
+
+            std::fs::remove_file(path).unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn binary_and_jsonl_round_trip_is_lossless() {
+        let jsonl_path = temp_file_path("round-trip.jsonl");
+        let binary_path = temp_file_path("round-trip.bin");
+        let round_trip_path = temp_file_path("round-trip.bin.jsonl");
+
+        // jsonl -> binary -> jsonl, as the transcode command does
+        write_event_file(&jsonl_path, Some(fixtures::current_header()), test_blocks(), EventEncoding::Jsonl, Compression::Gzip, false).await;
+        let (_, blocks) = load_from_local_whirlpool_event_file(&jsonl_path);
+        write_event_file(&binary_path, Some(fixtures::current_header()), blocks, EventEncoding::Binary, Compression::Zstd, true).await;
+        let (_, blocks) = load_from_local_whirlpool_event_file(&binary_path);
+        let blocks = blocks.collect::<Vec<_>>();
+        assert_eq!(blocks, test_blocks());
+        write_event_file(&round_trip_path, Some(fixtures::current_header()), blocks, EventEncoding::Jsonl, Compression::Gzip, false).await;
+
+        assert_eq!(std::fs::read(&round_trip_path).unwrap(), std::fs::read(&jsonl_path).unwrap());
+
+        for path in [jsonl_path, binary_path, round_trip_path] {
+            std::fs::remove_file(path).unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn long_key_blocks_are_read_in_short_keys() {
+        let path = temp_file_path("long-keys");
+        let mut writer = EventFileWriter::new(
+            File::create(&path).unwrap(),
+            Some(fixtures::current_header()),
+            EventEncoding::Jsonl,
+            KeyStyle::Long,
+            Compression::Gzip,
+            None,
+            false,
+        )
+        .unwrap();
+        for block in test_blocks() {
+            writer.write(block).await.unwrap();
+        }
+        writer.finish().await.unwrap();
+
+        // the header stays in short keys, and the blocks are in long keys
+        let decompressed = String::from_utf8(decompress(&path)).unwrap();
+        let mut lines = decompressed.lines();
+        let header = WhirlpoolFileHeader::from_jsonl(lines.next().unwrap()).unwrap();
+        assert_eq!(header.key_style, Some(KeyStyle::Long));
+        assert!(lines.next().unwrap().starts_with(r#"{"slot":"#));
+
+        let (header, blocks) = load_from_local_whirlpool_event_file(&path);
+        assert_eq!(header.unwrap().key_style(), KeyStyle::Long);
+        assert!(blocks.eq(test_blocks()));
+
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn sync_returns_the_offset_of_written_chunks() {
+        let path = temp_file_path("sync");
+        let header = build_file_header(WhirlpoolFileKind::Event, WHIRLPOOL_EVENT_FORMAT_VERSION, 0, &[]).unwrap();
+        let mut writer = EventFileWriter::new(
+            File::create(&path).unwrap(),
+            Some(header),
+            EventEncoding::Jsonl,
+            KeyStyle::Short,
+            Compression::Gzip,
+            None,
+            true,
+        )
+        .unwrap();
+        for block in test_blocks().into_iter().take(BLOCKS_PER_CHUNK) {
+            writer.write(block).await.unwrap();
+        }
+        let offset = writer.sync().await.unwrap();
+        assert_eq!(offset, std::fs::metadata(&path).unwrap().len());
+        writer.finish().await.unwrap();
+
+        // a compressed stream has no chunk boundaries to sync at
+        let mut writer = EventFileWriter::new(
+            File::create(&path).unwrap(),
+            None,
+            EventEncoding::Jsonl,
+            KeyStyle::Short,
+            Compression::Gzip,
+            None,
+            false,
+        )
+        .unwrap();
+        assert!(writer.sync().await.is_err());
+        writer.finish().await.unwrap();
+
+        std::fs::remove_file(path).unwrap();
+    }
+}
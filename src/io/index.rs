@@ -0,0 +1,454 @@
+use super::{
+    checksum_of_local_file,
+    compression::{self, Compression},
+    event::{decode_event_record, EventRecord, EventRecordReader},
+};
+use crate::model::{
+    event::{
+        migration::LEGACY_EVENT_FORMAT_VERSION, WhirlpoolEventBlock, WhirlpoolEventTransaction,
+    },
+    header::WhirlpoolFileHeader,
+    index::{
+        BlockLocation, TransactionLocation, WhirlpoolEventIndex,
+        WHIRLPOOL_EVENT_INDEX_FORMAT_VERSION,
+    },
+    schema::KeyStyle,
+};
+use anyhow::{bail, Result};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs::File,
+    io::{BufRead, BufReader, Seek, SeekFrom},
+};
+
+pub fn build_whirlpool_event_index(whirlpool_event_file_path: &str) -> Result<WhirlpoolEventIndex> {
+    let event_file = checksum_of_local_file(whirlpool_event_file_path)?;
+
+    let mut reader = BufReader::new(File::open(whirlpool_event_file_path)?);
+    let compression = compression::detect_compression(reader.fill_buf()?);
+
+    let mut event_format_version = LEGACY_EVENT_FORMAT_VERSION;
+    let mut is_first_record = true;
+
+    let mut blocks: Vec<BlockLocation> = Vec::new();
+    let mut signatures = HashMap::new();
+    let mut whirlpools: HashMap<String, Vec<u32>> = HashMap::new();
+
+    while !reader.fill_buf()?.is_empty() {
+        let offset = reader.stream_position()?;
+        let records = chunk_records(&mut reader, compression)?;
+
+        let mut skip = 0u32;
+        for record in records {
+            // the first line may be a header
+            if is_first_record {
+                is_first_record = false;
+                if let Some(header) = header_of(&record) {
+                    if header.key_style() != KeyStyle::Short {
+                        bail!("event files in {:?} keys cannot be indexed, rekey the file to short keys first", header.key_style());
+                    }
+                    event_format_version = header.format_version;
+                    continue;
+                }
+            }
+
+            for event_block in decode_event_record(record, event_format_version)? {
+                let block_index = blocks.len() as u32;
+                blocks.push(BlockLocation {
+                    slot: event_block.slot,
+                    block_height: event_block.block_height,
+                    block_time: event_block.block_time,
+                    offset,
+                    skip,
+                });
+                skip += 1;
+
+                for (transaction_index, transaction) in event_block.transactions.iter().enumerate() {
+                    signatures.insert(
+                        transaction.signature.clone(),
+                        TransactionLocation {
+                            block: block_index,
+                            transaction: transaction_index as u32,
+                        },
+                    );
+
+                    for whirlpool in transaction.events.iter().filter_map(|entry| entry.event.whirlpool()) {
+                        let posting = whirlpools.entry(whirlpool.to_string()).or_default();
+                        if posting.last() != Some(&block_index) {
+                            posting.push(block_index);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(WhirlpoolEventIndex {
+        format_version: WHIRLPOOL_EVENT_INDEX_FORMAT_VERSION,
+        event_file,
+        event_format_version,
+        blocks,
+        signatures,
+        whirlpools,
+    })
+}
+
+pub fn save_whirlpool_event_index(index: &WhirlpoolEventIndex, whirlpool_event_index_file_path: &str) -> Result<()> {
+    let f = File::create(whirlpool_event_index_file_path)?;
+    let mut writer = Compression::Gzip.create_writer(f, None)?;
+    bincode::serialize_into(&mut writer, index)?;
+    writer.finish()?;
+    Ok(())
+}
+
+pub fn load_whirlpool_event_index(whirlpool_event_index_file_path: &str) -> Result<WhirlpoolEventIndex> {
+    let decoder = compression::open_local_file_with_auto_decompression(whirlpool_event_index_file_path)?;
+    let index: WhirlpoolEventIndex = bincode::deserialize_from(BufReader::new(decoder))?;
+    if index.format_version != WHIRLPOOL_EVENT_INDEX_FORMAT_VERSION {
+        bail!("unsupported event index format version: {}", index.format_version);
+    }
+    Ok(index)
+}
+
+// answers lookups by decoding only the chunks that contain the requested blocks
+pub struct WhirlpoolEventIndexReader {
+    whirlpool_event_file_path: String,
+    compression: Compression,
+    index: WhirlpoolEventIndex,
+}
+
+impl WhirlpoolEventIndexReader {
+    pub fn open(whirlpool_event_file_path: &str, whirlpool_event_index_file_path: &str) -> Result<Self> {
+        let index = load_whirlpool_event_index(whirlpool_event_index_file_path)?;
+
+        let mut reader = BufReader::new(File::open(whirlpool_event_file_path)?);
+        let compression = compression::detect_compression(reader.fill_buf()?);
+
+        Ok(Self {
+            whirlpool_event_file_path: whirlpool_event_file_path.to_string(),
+            compression,
+            index,
+        })
+    }
+
+    pub fn index(&self) -> &WhirlpoolEventIndex {
+        &self.index
+    }
+
+    pub fn read_block(&self, block: u32) -> Result<WhirlpoolEventBlock> {
+        match self.read_blocks(&[block])?.pop() {
+            Some(event_block) => Ok(event_block),
+            None => bail!("block not found: {}", block),
+        }
+    }
+
+    // blocks are returned in the order of the file (duplicates are returned once)
+    pub fn read_blocks(&self, blocks: &[u32]) -> Result<Vec<WhirlpoolEventBlock>> {
+        // group by chunk so that each chunk is decoded at most once
+        let mut chunks: BTreeMap<u64, Vec<u32>> = BTreeMap::new();
+        for block in blocks {
+            let location = match self.index.blocks.get(*block as usize) {
+                Some(location) => location,
+                None => bail!("block index out of range: {}", block),
+            };
+            chunks.entry(location.offset).or_default().push(location.skip);
+        }
+
+        let mut reader = BufReader::new(File::open(&self.whirlpool_event_file_path)?);
+        let mut event_blocks = Vec::with_capacity(blocks.len());
+        for (offset, mut skips) in chunks {
+            skips.sort_unstable();
+            skips.dedup();
+            let last_skip = *skips.last().unwrap();
+
+            reader.seek(SeekFrom::Start(offset))?;
+            let mut chunk_blocks = ChunkBlockIter::new(&mut reader, self.compression, self.index.event_format_version)?;
+            for skip in 0..=last_skip {
+                let event_block = match chunk_blocks.next() {
+                    Some(event_block) => event_block?,
+                    None => bail!("block not found in chunk at offset {}: {}", offset, skip),
+                };
+                if skips.binary_search(&skip).is_ok() {
+                    event_blocks.push(event_block);
+                }
+            }
+        }
+
+        Ok(event_blocks)
+    }
+
+    pub fn find_block_by_slot(&self, slot: u64) -> Result<Option<WhirlpoolEventBlock>> {
+        match self.index.block_of_slot(slot) {
+            Some(block) => Ok(Some(self.read_block(block)?)),
+            None => Ok(None),
+        }
+    }
+
+    pub fn find_blocks_by_slot_range(&self, start_slot: u64, end_slot: u64) -> Result<Vec<WhirlpoolEventBlock>> {
+        self.read_blocks(&self.index.blocks_in_slot_range(start_slot, end_slot))
+    }
+
+    pub fn find_blocks_by_time_range(&self, start_block_time: i64, end_block_time: i64) -> Result<Vec<WhirlpoolEventBlock>> {
+        self.read_blocks(&self.index.blocks_in_time_range(start_block_time, end_block_time))
+    }
+
+    pub fn find_transaction_by_signature(&self, signature: &str) -> Result<Option<(BlockLocation, WhirlpoolEventTransaction)>> {
+        let location = match self.index.transaction_of_signature(signature) {
+            Some(location) => location,
+            None => return Ok(None),
+        };
+
+        let mut event_block = self.read_block(location.block)?;
+        let transaction = event_block.transactions.swap_remove(location.transaction as usize);
+        Ok(Some((self.index.blocks[location.block as usize], transaction)))
+    }
+
+    // only the transactions with events of the whirlpool are returned
+    pub fn find_transactions_by_whirlpool(&self, whirlpool: &str) -> Result<Vec<(BlockLocation, WhirlpoolEventTransaction)>> {
+        let blocks = self.index.blocks_of_whirlpool(whirlpool);
+
+        let mut transactions = Vec::new();
+        for (block, event_block) in blocks.iter().zip(self.read_blocks(blocks)?) {
+            let location = self.index.blocks[*block as usize];
+            transactions.extend(
+                event_block
+                    .transactions
+                    .into_iter()
+                    .filter(|transaction| {
+                        transaction
+                            .events
+                            .iter()
+                            .any(|entry| entry.event.whirlpool() == Some(whirlpool))
+                    })
+                    .map(|transaction| (location, transaction)),
+            );
+        }
+
+        Ok(transactions)
+    }
+}
+
+// the records of the chunk starting at the current position of the reader.
+// uncompressed files have no chunk boundaries, so only one record is read.
+// a compressed file written without --seekable is a single chunk, so lookups decode it from the start.
+fn chunk_records<'a>(reader: &'a mut BufReader<File>, compression: Compression) -> Result<Box<dyn Iterator<Item = EventRecord> + 'a>> {
+    match compression {
+        Compression::None => Ok(Box::new(EventRecordReader::new(reader).take(1))),
+        _ => Ok(Box::new(EventRecordReader::new(open_chunk_decoder(reader, compression)?))),
+    }
+}
+
+fn open_chunk_decoder<'a>(reader: &'a mut BufReader<File>, compression: Compression) -> Result<Box<dyn BufRead + 'a>> {
+    // single-member and single-frame decoders stop at the end of the chunk
+    match compression {
+        Compression::Gzip => Ok(Box::new(BufReader::new(flate2::bufread::GzDecoder::new(reader)))),
+        Compression::Zstd => Ok(Box::new(BufReader::new(
+            zstd::Decoder::with_buffer(reader)?.single_frame(),
+        ))),
+        Compression::None => Ok(Box::new(reader)),
+    }
+}
+
+fn header_of(record: &EventRecord) -> Option<WhirlpoolFileHeader> {
+    match record {
+        EventRecord::Line(jsonl) => WhirlpoolFileHeader::from_jsonl(jsonl),
+        EventRecord::Binary(_) => None,
+    }
+}
+
+// decodes blocks from a chunk (or, for uncompressed files, from a record onward)
+struct ChunkBlockIter<'a> {
+    records: EventRecordReader<Box<dyn BufRead + 'a>>,
+    event_format_version: u32,
+    pending: std::vec::IntoIter<WhirlpoolEventBlock>,
+}
+
+impl<'a> ChunkBlockIter<'a> {
+    fn new(reader: &'a mut BufReader<File>, compression: Compression, event_format_version: u32) -> Result<Self> {
+        Ok(Self {
+            records: EventRecordReader::new(open_chunk_decoder(reader, compression)?),
+            event_format_version,
+            pending: Vec::new().into_iter(),
+        })
+    }
+}
+
+impl Iterator for ChunkBlockIter<'_> {
+    type Item = Result<WhirlpoolEventBlock>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(event_block) = self.pending.next() {
+                return Some(Ok(event_block));
+            }
+
+            let record = self.records.next()?;
+            if header_of(&record).is_some() {
+                continue;
+            }
+            match decode_event_record(record, self.event_format_version) {
+                Ok(event_blocks) => self.pending = event_blocks.into_iter(),
+                Err(err) => return Some(Err(err)),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        io::event::{EventEncoding, BLOCKS_PER_CHUNK},
+        model::event::{fixtures, WHIRLPOOL_EVENT_FORMAT_VERSION},
+        test_support::{temp_file_path, write_event_file},
+    };
+
+    const BLOCKS: usize = BLOCKS_PER_CHUNK * 2 + 5;
+
+    // event files of all encodings and compressions, with their index saved and loaded again
+    async fn for_each_indexed_event_file(f: impl Fn(&WhirlpoolEventIndexReader, Encoding)) {
+        for encoding in [EventEncoding::Jsonl, EventEncoding::Binary] {
+            for compression in [Compression::Gzip, Compression::Zstd, Compression::None] {
+                let event_file_path = temp_file_path(&format!("{:?}-{:?}", encoding, compression));
+                let index_file_path = format!("{}.index", event_file_path);
+                write_event_file(&event_file_path, Some(fixtures::current_header()), fixtures::generate_blocks(BLOCKS), encoding, compression, true).await;
+
+                let index = build_whirlpool_event_index(&event_file_path).unwrap();
+                save_whirlpool_event_index(&index, &index_file_path).unwrap();
+                let reader = WhirlpoolEventIndexReader::open(&event_file_path, &index_file_path).unwrap();
+                assert_eq!(reader.index(), &index);
+
+                f(&reader, (encoding, compression));
+
+                std::fs::remove_file(event_file_path).unwrap();
+                std::fs::remove_file(index_file_path).unwrap();
+            }
+        }
+    }
+
+    type Encoding = (EventEncoding, Compression);
+
+    #[tokio::test]
+    async fn index_locates_every_block() {
+        for_each_indexed_event_file(|reader, encoding| {
+            let index = reader.index();
+            assert_eq!(index.event_format_version, WHIRLPOOL_EVENT_FORMAT_VERSION, "{:?}", encoding);
+            assert_eq!(index.blocks.len(), BLOCKS, "{:?}", encoding);
+
+            // compressed blocks share the offset of their chunk
+            if encoding.1 != Compression::None {
+                for (block, location) in index.blocks.iter().enumerate() {
+                    assert_eq!(location.skip as usize, block % BLOCKS_PER_CHUNK, "{:?}", encoding);
+                    assert_eq!(location.offset, index.blocks[block - block % BLOCKS_PER_CHUNK].offset, "{:?}", encoding);
+                }
+            }
+
+            // every block can be read back by seeking
+            let blocks = (0..BLOCKS as u32).collect::<Vec<_>>();
+            assert_eq!(reader.read_blocks(&blocks).unwrap(), fixtures::generate_blocks(BLOCKS), "{:?}", encoding);
+        })
+        .await;
+    }
+
+    #[tokio::test]
+    async fn index_locates_blocks_in_a_single_stream() {
+        for compression in [Compression::Gzip, Compression::Zstd] {
+            let event_file_path = temp_file_path(&format!("single-stream-{:?}", compression));
+            write_event_file(&event_file_path, Some(fixtures::current_header()), fixtures::generate_blocks(BLOCKS), EventEncoding::Jsonl, compression, false).await;
+
+            // all blocks are in the chunk at the start of the file
+            let index = build_whirlpool_event_index(&event_file_path).unwrap();
+            for (block, location) in index.blocks.iter().enumerate() {
+                assert_eq!((location.offset, location.skip as usize), (0, block), "{:?}", compression);
+            }
+
+            let index_file_path = format!("{}.index", event_file_path);
+            save_whirlpool_event_index(&index, &index_file_path).unwrap();
+            let reader = WhirlpoolEventIndexReader::open(&event_file_path, &index_file_path).unwrap();
+            let expected = fixtures::generate_blocks(BLOCKS);
+            let blocks = reader.read_blocks(&[1, BLOCKS as u32 - 1]).unwrap();
+            assert_eq!((&blocks[0], &blocks[1]), (&expected[1], &expected[BLOCKS - 1]), "{:?}", compression);
+
+            std::fs::remove_file(event_file_path).unwrap();
+            std::fs::remove_file(index_file_path).unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn find_blocks_by_slot() {
+        let expected = fixtures::generate_blocks(BLOCKS);
+        for_each_indexed_event_file(|reader, encoding| {
+            // first block, a block in the middle of a chunk, the first block of a chunk and the last block
+            for block in [0, 100, BLOCKS_PER_CHUNK, BLOCKS - 1] {
+                let slot = expected[block].slot;
+                assert_eq!(reader.find_block_by_slot(slot).unwrap().as_ref(), Some(&expected[block]), "{:?}", encoding);
+            }
+
+            // slots are not contiguous, and blocks outside of the file are not found
+            assert_eq!(reader.find_block_by_slot(expected[0].slot + 1).unwrap(), None, "{:?}", encoding);
+            assert_eq!(reader.find_block_by_slot(expected[0].slot - 1).unwrap(), None, "{:?}", encoding);
+            assert_eq!(reader.find_block_by_slot(expected[BLOCKS - 1].slot + 2).unwrap(), None, "{:?}", encoding);
+
+            // a range across a chunk boundary
+            let start = BLOCKS_PER_CHUNK - 2;
+            let blocks = reader
+                .find_blocks_by_slot_range(expected[start].slot - 1, expected[start + 3].slot)
+                .unwrap();
+            assert_eq!(blocks, expected[start..start + 4], "{:?}", encoding);
+
+            assert!(reader.find_blocks_by_slot_range(expected[1].slot, expected[0].slot).unwrap().is_empty());
+        })
+        .await;
+    }
+
+    #[tokio::test]
+    async fn find_blocks_by_time_range() {
+        let expected = fixtures::generate_blocks(BLOCKS);
+        for_each_indexed_event_file(|reader, encoding| {
+            let block_time = expected[BLOCKS_PER_CHUNK].block_time;
+            let blocks = reader.find_blocks_by_time_range(block_time, block_time).unwrap();
+            let matched = expected
+                .iter()
+                .filter(|block| block.block_time == block_time)
+                .collect::<Vec<_>>();
+            assert!(!matched.is_empty());
+            assert_eq!(blocks.iter().collect::<Vec<_>>(), matched, "{:?}", encoding);
+        })
+        .await;
+    }
+
+    #[tokio::test]
+    async fn find_transaction_by_signature() {
+        let expected = fixtures::generate_blocks(BLOCKS);
+        for_each_indexed_event_file(|reader, encoding| {
+            for block in [0, BLOCKS_PER_CHUNK + 1, BLOCKS - 1] {
+                let transaction = &expected[block].transactions[0];
+                let (location, found) = reader.find_transaction_by_signature(&transaction.signature).unwrap().unwrap();
+                assert_eq!(location.slot, expected[block].slot, "{:?}", encoding);
+                assert_eq!(&found, transaction, "{:?}", encoding);
+            }
+            assert!(reader.find_transaction_by_signature("unknown").unwrap().is_none());
+        })
+        .await;
+    }
+
+    #[tokio::test]
+    async fn find_transactions_by_whirlpool() {
+        let expected = fixtures::generate_blocks(BLOCKS);
+        for_each_indexed_event_file(|reader, encoding| {
+            for (i, whirlpool) in fixtures::WHIRLPOOLS.iter().enumerate() {
+                let transactions = reader.find_transactions_by_whirlpool(whirlpool).unwrap();
+                let expected_slots = expected
+                    .iter()
+                    .skip(i)
+                    .step_by(fixtures::WHIRLPOOLS.len())
+                    .map(|block| block.slot)
+                    .collect::<Vec<_>>();
+                let slots = transactions.iter().map(|(location, _)| location.slot).collect::<Vec<_>>();
+                assert_eq!(slots, expected_slots, "{:?}", encoding);
+            }
+            assert!(reader.find_transactions_by_whirlpool("unknown").unwrap().is_empty());
+        })
+        .await;
+    }
+}
@@ -0,0 +1,107 @@
+use crate::model::header::{InputFileChecksum, WhirlpoolFileHeader, WhirlpoolFileKind, CONVERTER_VERSION};
+use anyhow::Result;
+use serde::de::DeserializeOwned;
+use std::{
+    fs::File,
+    io::{BufRead, BufReader, Read},
+    path::Path,
+};
+
+pub mod compression;
+pub mod event;
+pub mod index;
+pub mod ohlcv;
+pub mod state;
+
+fn load_from_local_jsonl_file<T: DeserializeOwned + 'static>(
+    file_path: &str,
+) -> (Option<WhirlpoolFileHeader>, Box<dyn Iterator<Item = T> + Send>) {
+    let (header, lines) = load_lines_from_local_jsonl_file(file_path);
+
+    let iter = lines.map(|jsonl| {
+        let t: Result<T, serde_json::Error> = serde_json::from_str(jsonl.as_str());
+        t.unwrap()
+    });
+
+    (header, Box::new(iter))
+}
+
+fn load_lines_from_local_jsonl_file(
+    file_path: &str,
+) -> (Option<WhirlpoolFileHeader>, Box<dyn Iterator<Item = String> + Send>) {
+    // compression is detected from magic bytes
+    let decoder = compression::open_local_file_with_auto_decompression(file_path).unwrap();
+    let buf = BufReader::new(decoder);
+
+    let mut lines = buf.lines().map(|jsonl| jsonl.unwrap()).peekable();
+
+    // the first line may be a header
+    let header = lines
+        .peek()
+        .and_then(|jsonl| WhirlpoolFileHeader::from_jsonl(jsonl));
+    if header.is_some() {
+        lines.next();
+    }
+
+    (header, Box::new(lines))
+}
+
+pub fn build_file_header(
+    kind: WhirlpoolFileKind,
+    format_version: u32,
+    source_state_slot: u64,
+    input_file_paths: &[&str],
+) -> Result<WhirlpoolFileHeader> {
+    let input_files = input_file_paths
+        .iter()
+        .map(|path| checksum_of_local_file(path))
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(WhirlpoolFileHeader {
+        format_version,
+        kind,
+        converter_version: CONVERTER_VERSION.to_string(),
+        source_state_slot,
+        input_files,
+        compression_chunk_blocks: None,
+        key_style: None,
+    })
+}
+
+pub fn checksum_of_local_file(file_path: &str) -> Result<InputFileChecksum> {
+    Ok(InputFileChecksum {
+        name: file_name(file_path),
+        crc32: format!("{:08x}", crc32_of_local_file(file_path)?),
+    })
+}
+
+fn crc32_of_local_file(file_path: &str) -> Result<u32> {
+    let mut reader = BufReader::new(File::open(file_path)?);
+    let mut crc = flate2::Crc::new();
+    let mut buf = vec![0u8; 1 << 20];
+    loop {
+        let len = reader.read(&mut buf)?;
+        if len == 0 {
+            break;
+        }
+        crc.update(&buf[..len]);
+    }
+    Ok(crc.sum())
+}
+
+// makes the creation and renaming of the file durable (fsync of the file itself does not cover its directory entry)
+pub fn sync_parent_directory(file_path: &str) -> Result<()> {
+    let directory = match Path::new(file_path).parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    File::open(directory)?.sync_all()?;
+    Ok(())
+}
+
+fn file_name(file_path: &str) -> String {
+    Path::new(file_path)
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| file_path.to_string())
+}
@@ -0,0 +1,22 @@
+use crate::model::{
+    header::WhirlpoolFileHeader,
+    ohlcv::{WhirlpoolOhlcvDailyData, WhirlpoolOhlcvMinutelyData},
+};
+
+pub fn load_from_local_whirlpool_ohlcv_daily_file(
+    whirlpool_ohlcv_daily_file_path: &str,
+) -> (
+    Option<WhirlpoolFileHeader>,
+    Box<dyn Iterator<Item = WhirlpoolOhlcvDailyData> + Send>,
+) {
+    super::load_from_local_jsonl_file(whirlpool_ohlcv_daily_file_path)
+}
+
+pub fn load_from_local_whirlpool_ohlcv_minutely_file(
+    whirlpool_ohlcv_minutely_file_path: &str,
+) -> (
+    Option<WhirlpoolFileHeader>,
+    Box<dyn Iterator<Item = WhirlpoolOhlcvMinutelyData> + Send>,
+) {
+    super::load_from_local_jsonl_file(whirlpool_ohlcv_minutely_file_path)
+}
@@ -0,0 +1,66 @@
+use super::compression::Compression;
+use anyhow::Result;
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use replay_engine::account_data_store::AccountDataStore;
+use serde_json::json;
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+};
+
+/*
+
+Whirlpool State JSON Format (same as the input state file of the replayer)
+
+{
+  slot: u64,
+  blockHeight: u64,
+  blockTime: i64,
+  accounts: [
+    { pubkey: String(base58 encoding), data: String(base64 encoding) },
+    ...
+  ],
+  programData: String(base64 encoding),
+}
+
+*/
+
+// accounts are streamed from the store, so the whole state is never held in memory
+pub fn save_to_local_whirlpool_state_file(
+    whirlpool_state_file_path: &str,
+    slot: u64,
+    block_height: u64,
+    block_time: i64,
+    accounts: &AccountDataStore,
+    program_data: &[u8],
+) -> Result<()> {
+    let f = File::create(whirlpool_state_file_path)?;
+    let mut writer = BufWriter::new(Compression::Gzip.create_writer(f, None)?);
+
+    write!(
+        writer,
+        "{{\"slot\":{},\"blockHeight\":{},\"blockTime\":{},\"accounts\":[",
+        slot, block_height, block_time
+    )?;
+
+    let mut is_first = true;
+    accounts.traverse(|pubkey, data| {
+        if !is_first {
+            writer.write_all(b",")?;
+        }
+        is_first = false;
+        serde_json::to_writer(
+            &mut writer,
+            &json!({ "pubkey": pubkey, "data": BASE64.encode(data) }),
+        )?;
+        Ok(())
+    })?;
+
+    write!(writer, "],\"programData\":\"{}\"}}", BASE64.encode(program_data))?;
+    let f = writer.into_inner().map_err(|err| err.into_error())?.finish()?;
+
+    // checkpoints refer to the state file, so it must be on disk before a checkpoint is saved
+    f.sync_all()?;
+    super::sync_parent_directory(whirlpool_state_file_path)?;
+    Ok(())
+}
@@ -0,0 +1,4 @@
+pub mod io;
+pub mod model;
+#[cfg(any(test, feature = "test-support"))]
+pub mod test_support;
@@ -1,8 +1,11 @@
 use clap::Parser;
 use commands::Commands;
+use std::process::ExitCode;
+use whirlpool_archive_converter::{io, model};
+#[cfg(test)]
+use whirlpool_archive_converter::test_support;
 
 mod commands;
-mod model;
 
 #[derive(Parser, Debug)]
 struct Args {
@@ -11,7 +14,7 @@ struct Args {
 }
 
 #[tokio::main]
-async fn main() {
+async fn main() -> ExitCode {
     let args: Args = Args::parse();
     match args.command {
         Commands::Event {
@@ -19,11 +22,15 @@ async fn main() {
             whirlpool_token_file_path,
             whirlpool_transaction_file_path,
             whirlpool_event_file_path,
+            format,
+            replay_state,
         } => commands::event::process(
             whirlpool_state_file_path,
             whirlpool_token_file_path,
             whirlpool_transaction_file_path,
             whirlpool_event_file_path,
+            format,
+            replay_state,
         )
         .await
         .unwrap(),
@@ -33,14 +40,134 @@ async fn main() {
             whirlpool_event_file_path,
             whirlpool_ohlcv_daily_file_path,
             whirlpool_ohlcv_minutely_file_path,
+            output,
+            exclude_arbitrage,
         } => commands::ohlcv::process(
             whirlpool_state_file_path,
             whirlpool_token_file_path,
             whirlpool_event_file_path,
             whirlpool_ohlcv_daily_file_path,
             whirlpool_ohlcv_minutely_file_path,
+            output,
+            exclude_arbitrage,
         )
         .await
         .unwrap(),
+        Commands::PriceAt {
+            whirlpool_state_file_path,
+            whirlpool_token_file_path,
+            whirlpool_event_file_path,
+            price_query_file_path,
+            price_answer_file_path,
+        } => commands::price_at::process(
+            whirlpool_state_file_path,
+            whirlpool_token_file_path,
+            whirlpool_event_file_path,
+            price_query_file_path,
+            price_answer_file_path,
+        )
+        .await
+        .unwrap(),
+        Commands::Transcode {
+            input_whirlpool_event_file_path,
+            output_whirlpool_event_file_path,
+            encoding,
+            compression,
+            compression_level,
+            seekable,
+        } => commands::transcode::process(
+            input_whirlpool_event_file_path,
+            output_whirlpool_event_file_path,
+            encoding,
+            compression,
+            compression_level,
+            seekable,
+        )
+        .await
+        .unwrap(),
+        Commands::Index {
+            whirlpool_event_file_path,
+            whirlpool_event_index_file_path,
+        } => commands::index::process(whirlpool_event_file_path, whirlpool_event_index_file_path)
+            .await
+            .unwrap(),
+        Commands::Diff {
+            old_whirlpool_event_file_path,
+            new_whirlpool_event_file_path,
+        } => {
+            return commands::diff::process(old_whirlpool_event_file_path, new_whirlpool_event_file_path)
+                .await
+                .unwrap()
+                .exit_code()
+        }
+        Commands::Validate {
+            whirlpool_state_file_path,
+            whirlpool_token_file_path,
+            whirlpool_event_file_path,
+        } => {
+            return commands::validate::process(
+                whirlpool_state_file_path,
+                whirlpool_token_file_path,
+                whirlpool_event_file_path,
+            )
+            .await
+            .unwrap()
+            .exit_code()
+        }
+        Commands::Verify {
+            whirlpool_state_file_path,
+            whirlpool_token_file_path,
+            whirlpool_transaction_file_path,
+            next_whirlpool_state_file_path,
+        } => {
+            return commands::verify::process(
+                whirlpool_state_file_path,
+                whirlpool_token_file_path,
+                whirlpool_transaction_file_path,
+                next_whirlpool_state_file_path,
+            )
+            .await
+            .unwrap()
+            .exit_code()
+        }
+        Commands::DetectArbitrage {
+            whirlpool_event_file_path,
+            arbitrage_file_path,
+        } => commands::detect_arbitrage::process(whirlpool_event_file_path, arbitrage_file_path)
+            .await
+            .unwrap(),
+        Commands::DetectSandwich {
+            whirlpool_event_file_path,
+            sandwich_file_path,
+        } => commands::detect_sandwich::process(whirlpool_event_file_path, sandwich_file_path)
+            .await
+            .unwrap(),
+        Commands::Wallets {
+            whirlpool_event_file_path,
+            whirlpool_wallet_file_path,
+            output,
+        } => commands::wallets::process(whirlpool_event_file_path, whirlpool_wallet_file_path, output)
+            .await
+            .unwrap(),
+        Commands::Schema {
+            kind,
+            language,
+            schema_file_path,
+            key_style,
+        } => commands::schema::process(kind, language, schema_file_path, key_style)
+            .await
+            .unwrap(),
+        Commands::Rekey {
+            input_file_path,
+            output_file_path,
+            kind,
+            to,
+            compression,
+            compression_level,
+        } => commands::rekey::process(input_file_path, output_file_path, kind, to, compression, compression_level)
+            .await
+            .unwrap(),
     }
+
+    ExitCode::SUCCESS
 }
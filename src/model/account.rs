@@ -0,0 +1,77 @@
+use anchor_lang::{prelude::Pubkey, Discriminator};
+use whirlpool_base::state::TickArray;
+
+/*
+
+TickArray Account Layout
+
+TickArray is a zero-copy (packed, little endian) account, so it is decoded manually.
+
+  discriminator(8) | start_tick_index(i32) | ticks(Tick x 88) | whirlpool(Pubkey)
+
+Tick:
+  initialized(bool) | liquidity_net(i128) | liquidity_gross(u128)
+  | fee_growth_outside_a(u128) | fee_growth_outside_b(u128) | reward_growths_outside(u128 x 3)
+
+*/
+
+pub const TICK_ARRAY_SIZE: usize = 88;
+const TICK_LEN: usize = 1 + 16 * 7;
+pub const TICK_ARRAY_LEN: usize = 8 + 4 + TICK_LEN * TICK_ARRAY_SIZE + 32;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecodedTick {
+    pub initialized: bool,
+    pub liquidity_net: i128,
+    pub liquidity_gross: u128,
+    pub fee_growth_outside_a: u128,
+    pub fee_growth_outside_b: u128,
+    pub reward_growths_outside: [u128; 3],
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DecodedTickArray {
+    pub start_tick_index: i32,
+    pub ticks: Vec<DecodedTick>,
+    pub whirlpool: Pubkey,
+}
+
+impl DecodedTickArray {
+    pub fn is_tick_array(data: &[u8]) -> bool {
+        data.len() == TICK_ARRAY_LEN && data.starts_with(&TickArray::DISCRIMINATOR)
+    }
+
+    pub fn decode(data: &[u8]) -> Option<Self> {
+        if !Self::is_tick_array(data) {
+            return None;
+        }
+
+        let start_tick_index = i32::from_le_bytes(data[8..12].try_into().unwrap());
+        let ticks = data[12..12 + TICK_LEN * TICK_ARRAY_SIZE]
+            .chunks_exact(TICK_LEN)
+            .map(|tick| DecodedTick {
+                initialized: tick[0] != 0,
+                liquidity_net: i128::from_le_bytes(tick[1..17].try_into().unwrap()),
+                liquidity_gross: u128_at(tick, 17),
+                fee_growth_outside_a: u128_at(tick, 33),
+                fee_growth_outside_b: u128_at(tick, 49),
+                reward_growths_outside: [u128_at(tick, 65), u128_at(tick, 81), u128_at(tick, 97)],
+            })
+            .collect();
+        let whirlpool = Pubkey::new_from_array(data[TICK_ARRAY_LEN - 32..].try_into().unwrap());
+
+        Some(Self {
+            start_tick_index,
+            ticks,
+            whirlpool,
+        })
+    }
+
+    pub fn tick_index_of(&self, offset: usize, tick_spacing: u16) -> i32 {
+        self.start_tick_index + offset as i32 * tick_spacing as i32
+    }
+}
+
+fn u128_at(data: &[u8], offset: usize) -> u128 {
+    u128::from_le_bytes(data[offset..offset + 16].try_into().unwrap())
+}
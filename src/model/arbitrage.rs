@@ -0,0 +1,144 @@
+use super::event::{definition::TradedEventPayload, WhirlpoolEvent, WhirlpoolEventTransaction};
+
+/*
+
+Cyclic Arbitrage
+
+A cycle is a chain of Traded events in one transaction where each trade pays in the mint
+received by the previous trade, and the last trade receives the mint paid in by the first.
+Both two-hop swaps and separate swap instructions form chains.
+
+Chains are built greedily in event order, and each trade belongs to at most one cycle.
+A chain in a single pool (e.g. a user swapping and swapping back) is a round trip, not an arbitrage.
+
+The profit is the difference of the transfer amounts, Token-2022 transfer fees are not deducted.
+
+*/
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArbitrageCycle {
+    // indexes of the Traded events in the transaction, in route order
+    pub event_indexes: Vec<usize>,
+    pub mint: String,
+    pub input_amount: u64,
+    pub output_amount: u64,
+}
+
+impl ArbitrageCycle {
+    // in the cycle mint, before transfer fees
+    pub fn profit(&self) -> i128 {
+        self.output_amount as i128 - self.input_amount as i128
+    }
+}
+
+pub fn detect_arbitrage_cycles(transaction: &WhirlpoolEventTransaction) -> Vec<ArbitrageCycle> {
+    let trades = transaction
+        .events
+        .iter()
+        .enumerate()
+        .filter_map(|(index, entry)| match &entry.event {
+            WhirlpoolEvent::Traded(traded) => Some((index, traded)),
+            _ => None,
+        })
+        .collect::<Vec<(usize, &TradedEventPayload)>>();
+
+    let mut used = vec![false; trades.len()];
+    let mut cycles = vec![];
+    for start in 0..trades.len() {
+        if used[start] {
+            continue;
+        }
+
+        let (_, first) = trades[start];
+        let mut route = vec![start];
+        let mut mint = &first.transfer_out.mint;
+        let mut closed = false;
+        for next in start + 1..trades.len() {
+            let (_, traded) = trades[next];
+            if used[next] || traded.transfer_in.mint != *mint {
+                continue;
+            }
+            route.push(next);
+            mint = &traded.transfer_out.mint;
+            if *mint == first.transfer_in.mint {
+                closed = true;
+                break;
+            }
+        }
+
+        if !closed || route.iter().all(|&index| trades[index].1.whirlpool == first.whirlpool) {
+            continue;
+        }
+
+        route.iter().for_each(|&index| used[index] = true);
+        let (_, last) = trades[*route.last().unwrap()];
+        cycles.push(ArbitrageCycle {
+            event_indexes: route.iter().map(|&index| trades[index].0).collect(),
+            mint: first.transfer_in.mint.clone(),
+            input_amount: first.transfer_in.amount,
+            output_amount: last.transfer_out.amount,
+        });
+    }
+    cycles
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::event::fixtures::{traded_entry, transaction_with, WHIRLPOOLS};
+
+    const MINT_A: &str = "2b1kV6DkPAnxd5ixfnxCpjxmKwqjjaYmCZfHsFu24GXo";
+    const MINT_B: &str = "So11111111111111111111111111111111111111112";
+    const MINT_C: &str = "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v";
+
+    #[test]
+    fn closed_cycle_is_detected() {
+        let transaction = transaction_with(vec![
+            traded_entry(WHIRLPOOLS[0], MINT_A, 1000, MINT_B, 500),
+            traded_entry(WHIRLPOOLS[1], MINT_B, 500, MINT_A, 1010),
+        ]);
+        let cycles = detect_arbitrage_cycles(&transaction);
+        assert_eq!(
+            cycles,
+            vec![ArbitrageCycle {
+                event_indexes: vec![0, 1],
+                mint: MINT_A.to_string(),
+                input_amount: 1000,
+                output_amount: 1010,
+            }]
+        );
+        assert_eq!(cycles[0].profit(), 10);
+    }
+
+    #[test]
+    fn unclosed_route_is_not_a_cycle() {
+        let transaction = transaction_with(vec![
+            traded_entry(WHIRLPOOLS[0], MINT_A, 1000, MINT_B, 500),
+            traded_entry(WHIRLPOOLS[1], MINT_B, 500, MINT_C, 2000),
+        ]);
+        assert!(detect_arbitrage_cycles(&transaction).is_empty());
+    }
+
+    #[test]
+    fn unrelated_trades_are_skipped() {
+        let transaction = transaction_with(vec![
+            traded_entry(WHIRLPOOLS[0], MINT_A, 1000, MINT_B, 500),
+            traded_entry(WHIRLPOOLS[2], MINT_C, 300, MINT_A, 200),
+            traded_entry(WHIRLPOOLS[1], MINT_B, 500, MINT_C, 400),
+            traded_entry(WHIRLPOOLS[2], MINT_C, 400, MINT_A, 990),
+        ]);
+        let cycles = detect_arbitrage_cycles(&transaction);
+        assert_eq!(cycles.len(), 1);
+        assert_eq!(cycles[0].event_indexes, vec![0, 2, 3]);
+        assert_eq!(cycles[0].profit(), -10);
+    }
+
+    #[test]
+    fn round_trip_in_one_pool_is_not_a_cycle() {
+        let transaction = transaction_with(vec![
+            traded_entry(WHIRLPOOLS[0], MINT_A, 1000, MINT_B, 500),
+            traded_entry(WHIRLPOOLS[0], MINT_B, 500, MINT_A, 990),
+        ]);
+        assert!(detect_arbitrage_cycles(&transaction).is_empty());
+    }
+}
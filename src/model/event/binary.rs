@@ -0,0 +1,790 @@
+use super::{WhirlpoolEventBlock, WHIRLPOOL_EVENT_FORMAT_VERSION};
+use anchor_lang::prelude::Pubkey;
+use anyhow::{bail, Result};
+use bincode::Options;
+use serde::{
+    de::{self, DeserializeSeed, EnumAccess, MapAccess, SeqAccess, VariantAccess, Visitor},
+    ser::{
+        SerializeMap, SerializeSeq, SerializeStruct, SerializeStructVariant, SerializeTuple,
+        SerializeTupleStruct, SerializeTupleVariant,
+    },
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+use std::{cell::RefCell, collections::HashMap, fmt, str::FromStr};
+
+/*
+
+Whirlpool Event Binary Format
+
+A compact alternative to JSON Lines for internal pipelines.
+Blocks are grouped into chunks, and each chunk is stored as one record:
+
+  magic(4 bytes, "WEVB") | length(u32, little endian) | formatVersion(u32) | pubkeys([[u8; 32]]) | blocks([WhirlpoolEventBlock])
+
+The record is bincode (with varint integers) of the typed structs, field by field in the order of declaration:
+
+- integers are native (the string_* modules of model/serde.rs write strings to JSON only)
+- canonical base58 pubkeys are indexes into the pubkey dictionary of the chunk, other strings are kept as they are
+- optional fields are always present (None where JSON Lines omits the key)
+- an entry is the tuple (event, instructionIndex, subIndex), and an event is (variant index, payload)
+- decimal prices keep their string form, and raw instruction payloads are JSON text
+
+so the conversion between the two formats is lossless.
+Records are decoded into the current structs, so a record of another format version is rejected
+(older formats are migrated in JSON Lines, see migration.rs).
+
+Records and JSON lines can be mixed in a stream (e.g. a header line followed by binary records).
+
+*/
+
+pub const BINARY_RECORD_MAGIC: [u8; 4] = *b"WEVB";
+
+pub fn encode_binary_record(blocks: &[WhirlpoolEventBlock]) -> Result<Vec<u8>> {
+    // the dictionary is complete only after the blocks are encoded
+    let dictionary = RefCell::new(PubkeyDictionary::default());
+    let mut encoded_blocks = Vec::new();
+    blocks.serialize(BinarySerializer {
+        inner: &mut bincode::Serializer::new(&mut encoded_blocks, binary_options()),
+        dictionary: &dictionary,
+    })?;
+
+    let pubkeys = dictionary.into_inner().pubkeys;
+    let mut payload = binary_options().serialize(&(WHIRLPOOL_EVENT_FORMAT_VERSION, pubkeys))?;
+    payload.extend_from_slice(&encoded_blocks);
+
+    let mut record = Vec::with_capacity(8 + payload.len());
+    record.extend_from_slice(&BINARY_RECORD_MAGIC);
+    record.extend_from_slice(&u32::try_from(payload.len())?.to_le_bytes());
+    record.extend_from_slice(&payload);
+    Ok(record)
+}
+
+pub fn decode_binary_record_payload(payload: &[u8]) -> Result<Vec<WhirlpoolEventBlock>> {
+    let mut deserializer = bincode::Deserializer::from_slice(payload, binary_options());
+
+    let format_version = u32::deserialize(&mut deserializer)?;
+    if format_version != WHIRLPOOL_EVENT_FORMAT_VERSION {
+        bail!(
+            "binary records of event format version {} cannot be decoded (current version: {}), transcode them to jsonl with the converter of that version",
+            format_version, WHIRLPOOL_EVENT_FORMAT_VERSION
+        );
+    }
+
+    let pubkeys = Vec::<[u8; 32]>::deserialize(&mut deserializer)?
+        .into_iter()
+        .map(|pubkey| Pubkey::new_from_array(pubkey).to_string())
+        .collect::<Vec<_>>();
+
+    Ok(Vec::<WhirlpoolEventBlock>::deserialize(BinaryDeserializer {
+        inner: &mut deserializer,
+        pubkeys: &pubkeys,
+    })?)
+}
+
+fn binary_options() -> impl Options + Copy {
+    bincode::DefaultOptions::new()
+}
+
+#[derive(Default)]
+struct PubkeyDictionary {
+    pubkeys: Vec<[u8; 32]>,
+    indexes: HashMap<String, u32>,
+}
+
+impl PubkeyDictionary {
+    // None if the string is not a canonical pubkey
+    fn index_of(&mut self, s: &str) -> Option<u32> {
+        if let Some(index) = self.indexes.get(s) {
+            return Some(*index);
+        }
+        let pubkey = to_pubkey_bytes(s)?;
+        let index = self.pubkeys.len() as u32;
+        self.pubkeys.push(pubkey);
+        self.indexes.insert(s.to_string(), index);
+        Some(index)
+    }
+}
+
+// only canonical base58 encodings are dictionary-coded, so decoding always restores the original string
+fn to_pubkey_bytes(s: &str) -> Option<[u8; 32]> {
+    if s.len() < 32 || s.len() > 44 {
+        return None;
+    }
+    let pubkey = Pubkey::from_str(s).ok()?;
+    if pubkey.to_string() != s {
+        return None;
+    }
+    Some(pubkey.to_bytes())
+}
+
+// every string in a record (the variant indexes are those of BinarySerializer::serialize_str)
+#[derive(serde_derive::Deserialize)]
+enum BinaryString {
+    Pubkey(u32),
+    String(String),
+}
+
+// serialize
+
+// passes everything to bincode, except strings (dictionary-coded) and skipped fields (written as None),
+// and wraps the nested values so that the same applies to them
+struct BinarySerializer<'a, S> {
+    inner: S,
+    dictionary: &'a RefCell<PubkeyDictionary>,
+}
+
+struct BinaryValue<'a, T: ?Sized> {
+    value: &'a T,
+    dictionary: &'a RefCell<PubkeyDictionary>,
+}
+
+impl<T: ?Sized + Serialize> Serialize for BinaryValue<'_, T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.value.serialize(BinarySerializer {
+            inner: serializer,
+            dictionary: self.dictionary,
+        })
+    }
+}
+
+macro_rules! forward_serialize {
+    ($($method:ident($ty:ty)),* $(,)?) => {
+        $(
+            fn $method(self, v: $ty) -> Result<S::Ok, S::Error> {
+                self.inner.$method(v)
+            }
+        )*
+    };
+}
+
+impl<'a, S: Serializer> Serializer for BinarySerializer<'a, S> {
+    type Ok = S::Ok;
+    type Error = S::Error;
+    type SerializeSeq = BinarySerializer<'a, S::SerializeSeq>;
+    type SerializeTuple = BinarySerializer<'a, S::SerializeTuple>;
+    type SerializeTupleStruct = BinarySerializer<'a, S::SerializeTupleStruct>;
+    type SerializeTupleVariant = BinarySerializer<'a, S::SerializeTupleVariant>;
+    type SerializeMap = BinarySerializer<'a, S::SerializeMap>;
+    type SerializeStruct = BinarySerializer<'a, S::SerializeStruct>;
+    type SerializeStructVariant = BinarySerializer<'a, S::SerializeStructVariant>;
+
+    forward_serialize!(
+        serialize_bool(bool),
+        serialize_i8(i8),
+        serialize_i16(i16),
+        serialize_i32(i32),
+        serialize_i64(i64),
+        serialize_i128(i128),
+        serialize_u8(u8),
+        serialize_u16(u16),
+        serialize_u32(u32),
+        serialize_u64(u64),
+        serialize_u128(u128),
+        serialize_f32(f32),
+        serialize_f64(f64),
+        serialize_char(char),
+        serialize_bytes(&[u8]),
+        serialize_unit_struct(&'static str),
+    );
+
+    fn serialize_str(self, v: &str) -> Result<S::Ok, S::Error> {
+        let index = self.dictionary.borrow_mut().index_of(v);
+        match index {
+            Some(index) => self.inner.serialize_newtype_variant("BinaryString", 0, "Pubkey", &index),
+            None => self.inner.serialize_newtype_variant("BinaryString", 1, "String", v),
+        }
+    }
+
+    fn serialize_none(self) -> Result<S::Ok, S::Error> {
+        self.inner.serialize_none()
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> Result<S::Ok, S::Error> {
+        let value = BinaryValue { value, dictionary: self.dictionary };
+        self.inner.serialize_some(&value)
+    }
+
+    fn serialize_unit(self) -> Result<S::Ok, S::Error> {
+        self.inner.serialize_unit()
+    }
+
+    fn serialize_unit_variant(self, name: &'static str, variant_index: u32, variant: &'static str) -> Result<S::Ok, S::Error> {
+        self.inner.serialize_unit_variant(name, variant_index, variant)
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(self, name: &'static str, value: &T) -> Result<S::Ok, S::Error> {
+        let value = BinaryValue { value, dictionary: self.dictionary };
+        self.inner.serialize_newtype_struct(name, &value)
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
+        self,
+        name: &'static str,
+        variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<S::Ok, S::Error> {
+        let value = BinaryValue { value, dictionary: self.dictionary };
+        self.inner.serialize_newtype_variant(name, variant_index, variant, &value)
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<Self::SerializeSeq, S::Error> {
+        let dictionary = self.dictionary;
+        Ok(BinarySerializer { inner: self.inner.serialize_seq(len)?, dictionary })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple, S::Error> {
+        let dictionary = self.dictionary;
+        Ok(BinarySerializer { inner: self.inner.serialize_tuple(len)?, dictionary })
+    }
+
+    fn serialize_tuple_struct(self, name: &'static str, len: usize) -> Result<Self::SerializeTupleStruct, S::Error> {
+        let dictionary = self.dictionary;
+        Ok(BinarySerializer { inner: self.inner.serialize_tuple_struct(name, len)?, dictionary })
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        name: &'static str,
+        variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleVariant, S::Error> {
+        let dictionary = self.dictionary;
+        Ok(BinarySerializer { inner: self.inner.serialize_tuple_variant(name, variant_index, variant, len)?, dictionary })
+    }
+
+    fn serialize_map(self, len: Option<usize>) -> Result<Self::SerializeMap, S::Error> {
+        let dictionary = self.dictionary;
+        Ok(BinarySerializer { inner: self.inner.serialize_map(len)?, dictionary })
+    }
+
+    fn serialize_struct(self, name: &'static str, len: usize) -> Result<Self::SerializeStruct, S::Error> {
+        let dictionary = self.dictionary;
+        Ok(BinarySerializer { inner: self.inner.serialize_struct(name, len)?, dictionary })
+    }
+
+    fn serialize_struct_variant(
+        self,
+        name: &'static str,
+        variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeStructVariant, S::Error> {
+        let dictionary = self.dictionary;
+        Ok(BinarySerializer { inner: self.inner.serialize_struct_variant(name, variant_index, variant, len)?, dictionary })
+    }
+
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+}
+
+impl<S: SerializeSeq> SerializeSeq for BinarySerializer<'_, S> {
+    type Ok = S::Ok;
+    type Error = S::Error;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), S::Error> {
+        let value = BinaryValue { value, dictionary: self.dictionary };
+        self.inner.serialize_element(&value)
+    }
+
+    fn end(self) -> Result<S::Ok, S::Error> {
+        self.inner.end()
+    }
+}
+
+impl<S: SerializeTuple> SerializeTuple for BinarySerializer<'_, S> {
+    type Ok = S::Ok;
+    type Error = S::Error;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), S::Error> {
+        let value = BinaryValue { value, dictionary: self.dictionary };
+        self.inner.serialize_element(&value)
+    }
+
+    fn end(self) -> Result<S::Ok, S::Error> {
+        self.inner.end()
+    }
+}
+
+impl<S: SerializeTupleStruct> SerializeTupleStruct for BinarySerializer<'_, S> {
+    type Ok = S::Ok;
+    type Error = S::Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), S::Error> {
+        let value = BinaryValue { value, dictionary: self.dictionary };
+        self.inner.serialize_field(&value)
+    }
+
+    fn end(self) -> Result<S::Ok, S::Error> {
+        self.inner.end()
+    }
+}
+
+impl<S: SerializeTupleVariant> SerializeTupleVariant for BinarySerializer<'_, S> {
+    type Ok = S::Ok;
+    type Error = S::Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), S::Error> {
+        let value = BinaryValue { value, dictionary: self.dictionary };
+        self.inner.serialize_field(&value)
+    }
+
+    fn end(self) -> Result<S::Ok, S::Error> {
+        self.inner.end()
+    }
+}
+
+impl<S: SerializeMap> SerializeMap for BinarySerializer<'_, S> {
+    type Ok = S::Ok;
+    type Error = S::Error;
+
+    fn serialize_key<T: ?Sized + Serialize>(&mut self, key: &T) -> Result<(), S::Error> {
+        let key = BinaryValue { value: key, dictionary: self.dictionary };
+        self.inner.serialize_key(&key)
+    }
+
+    fn serialize_value<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), S::Error> {
+        let value = BinaryValue { value, dictionary: self.dictionary };
+        self.inner.serialize_value(&value)
+    }
+
+    fn end(self) -> Result<S::Ok, S::Error> {
+        self.inner.end()
+    }
+}
+
+// fields are decoded by position, so a field skipped by skip_serializing_if (always an Option) is written as None
+impl<S: SerializeStruct> SerializeStruct for BinarySerializer<'_, S> {
+    type Ok = S::Ok;
+    type Error = S::Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, key: &'static str, value: &T) -> Result<(), S::Error> {
+        let value = BinaryValue { value, dictionary: self.dictionary };
+        self.inner.serialize_field(key, &value)
+    }
+
+    fn skip_field(&mut self, key: &'static str) -> Result<(), S::Error> {
+        self.inner.serialize_field(key, &None::<()>)
+    }
+
+    fn end(self) -> Result<S::Ok, S::Error> {
+        self.inner.end()
+    }
+}
+
+impl<S: SerializeStructVariant> SerializeStructVariant for BinarySerializer<'_, S> {
+    type Ok = S::Ok;
+    type Error = S::Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, key: &'static str, value: &T) -> Result<(), S::Error> {
+        let value = BinaryValue { value, dictionary: self.dictionary };
+        self.inner.serialize_field(key, &value)
+    }
+
+    fn skip_field(&mut self, key: &'static str) -> Result<(), S::Error> {
+        self.inner.serialize_field(key, &None::<()>)
+    }
+
+    fn end(self) -> Result<S::Ok, S::Error> {
+        self.inner.end()
+    }
+}
+
+// deserialize
+
+// the counterpart of BinarySerializer: it wraps every deserializer, visitor, access and seed that bincode
+// hands to the derived impls, so that nested strings are resolved from the dictionary too.
+// bincode cannot decode identifiers, so the tag of adjacently tagged enums is read as the variant index.
+struct BinaryDeserializer<'a, T> {
+    inner: T,
+    pubkeys: &'a [String],
+}
+
+impl<'a, T> BinaryDeserializer<'a, T> {
+    fn wrap<U>(&self, inner: U) -> BinaryDeserializer<'a, U> {
+        BinaryDeserializer {
+            inner,
+            pubkeys: self.pubkeys,
+        }
+    }
+}
+
+macro_rules! forward_deserialize {
+    ($($method:ident),* $(,)?) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, D::Error> {
+                self.inner.$method(visitor)
+            }
+        )*
+    };
+}
+
+macro_rules! wrap_deserialize {
+    ($($method:ident($($arg:ident: $ty:ty),*)),* $(,)?) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, $($arg: $ty,)* visitor: V) -> Result<V::Value, D::Error> {
+                let visitor = self.wrap(visitor);
+                self.inner.$method($($arg,)* visitor)
+            }
+        )*
+    };
+}
+
+impl<'de, D: Deserializer<'de>> Deserializer<'de> for BinaryDeserializer<'_, D> {
+    type Error = D::Error;
+
+    forward_deserialize!(
+        deserialize_bool,
+        deserialize_i8,
+        deserialize_i16,
+        deserialize_i32,
+        deserialize_i64,
+        deserialize_i128,
+        deserialize_u8,
+        deserialize_u16,
+        deserialize_u32,
+        deserialize_u64,
+        deserialize_u128,
+        deserialize_f32,
+        deserialize_f64,
+        deserialize_char,
+        deserialize_bytes,
+        deserialize_byte_buf,
+        deserialize_unit,
+        deserialize_ignored_any,
+    );
+
+    wrap_deserialize!(
+        deserialize_any(),
+        deserialize_option(),
+        deserialize_seq(),
+        deserialize_map(),
+        deserialize_unit_struct(name: &'static str),
+        deserialize_newtype_struct(name: &'static str),
+        deserialize_tuple(len: usize),
+        deserialize_tuple_struct(name: &'static str, len: usize),
+        deserialize_struct(name: &'static str, fields: &'static [&'static str]),
+        deserialize_enum(name: &'static str, variants: &'static [&'static str]),
+    );
+
+    fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, D::Error> {
+        match BinaryString::deserialize(self.inner)? {
+            BinaryString::Pubkey(index) => match self.pubkeys.get(index as usize) {
+                Some(pubkey) => visitor.visit_str(pubkey),
+                None => Err(de::Error::custom(format!("pubkey index out of range: {}", index))),
+            },
+            BinaryString::String(s) => visitor.visit_string(s),
+        }
+    }
+
+    fn deserialize_string<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, D::Error> {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_identifier<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, D::Error> {
+        self.inner.deserialize_u32(visitor)
+    }
+
+    fn is_human_readable(&self) -> bool {
+        false
+    }
+}
+
+impl<'de, V: Visitor<'de>> Visitor<'de> for BinaryDeserializer<'_, V> {
+    type Value = V::Value;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        self.inner.expecting(formatter)
+    }
+
+    fn visit_none<E: de::Error>(self) -> Result<V::Value, E> {
+        self.inner.visit_none()
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<V::Value, D::Error> {
+        let deserializer = self.wrap(deserializer);
+        self.inner.visit_some(deserializer)
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<V::Value, E> {
+        self.inner.visit_unit()
+    }
+
+    fn visit_newtype_struct<D: Deserializer<'de>>(self, deserializer: D) -> Result<V::Value, D::Error> {
+        let deserializer = self.wrap(deserializer);
+        self.inner.visit_newtype_struct(deserializer)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, seq: A) -> Result<V::Value, A::Error> {
+        let seq = self.wrap(seq);
+        self.inner.visit_seq(seq)
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<V::Value, A::Error> {
+        let map = self.wrap(map);
+        self.inner.visit_map(map)
+    }
+
+    fn visit_enum<A: EnumAccess<'de>>(self, data: A) -> Result<V::Value, A::Error> {
+        let data = self.wrap(data);
+        self.inner.visit_enum(data)
+    }
+}
+
+impl<'de, A: SeqAccess<'de>> SeqAccess<'de> for BinaryDeserializer<'_, A> {
+    type Error = A::Error;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(&mut self, seed: T) -> Result<Option<T::Value>, A::Error> {
+        let seed = self.wrap(seed);
+        self.inner.next_element_seed(seed)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        self.inner.size_hint()
+    }
+}
+
+impl<'de, A: MapAccess<'de>> MapAccess<'de> for BinaryDeserializer<'_, A> {
+    type Error = A::Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>, A::Error> {
+        let seed = self.wrap(seed);
+        self.inner.next_key_seed(seed)
+    }
+
+    fn next_value_seed<T: DeserializeSeed<'de>>(&mut self, seed: T) -> Result<T::Value, A::Error> {
+        let seed = self.wrap(seed);
+        self.inner.next_value_seed(seed)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        self.inner.size_hint()
+    }
+}
+
+impl<'a, 'de, A: EnumAccess<'de>> EnumAccess<'de> for BinaryDeserializer<'a, A> {
+    type Error = A::Error;
+    type Variant = BinaryDeserializer<'a, A::Variant>;
+
+    // the variant index is decoded by bincode itself
+    fn variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> Result<(T::Value, Self::Variant), A::Error> {
+        let pubkeys = self.pubkeys;
+        let (value, variant) = self.inner.variant_seed(seed)?;
+        Ok((value, BinaryDeserializer { inner: variant, pubkeys }))
+    }
+}
+
+impl<'de, A: VariantAccess<'de>> VariantAccess<'de> for BinaryDeserializer<'_, A> {
+    type Error = A::Error;
+
+    fn unit_variant(self) -> Result<(), A::Error> {
+        self.inner.unit_variant()
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value, A::Error> {
+        let seed = self.wrap(seed);
+        self.inner.newtype_variant_seed(seed)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, len: usize, visitor: V) -> Result<V::Value, A::Error> {
+        let visitor = self.wrap(visitor);
+        self.inner.tuple_variant(len, visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(self, fields: &'static [&'static str], visitor: V) -> Result<V::Value, A::Error> {
+        let visitor = self.wrap(visitor);
+        self.inner.struct_variant(fields, visitor)
+    }
+}
+
+impl<'de, T: DeserializeSeed<'de>> DeserializeSeed<'de> for BinaryDeserializer<'_, T> {
+    type Value = T::Value;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<T::Value, D::Error> {
+        let deserializer = self.wrap(deserializer);
+        self.inner.deserialize(deserializer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        io::event::BLOCKS_PER_CHUNK,
+        model::event::{fixtures, RawInstruction},
+    };
+    use std::time::Instant;
+
+    // strings that look like pubkeys but must not be dictionary-coded
+    const NON_CANONICAL_STRINGS: [&str; 6] = [
+        // leading zero byte dropped (decodes to 31 bytes)
+        "1111111111111111111111111111111",
+        // too long for 32 bytes
+        "4rHDRkP7oXcBA3wVz9f4Sn4pUQ7YkBYPx4JeA7kH4mZKqZNhLE7Ns7fSP3PBjPSdWgCCVwJumAZyhi6WEq5uJXXv",
+        // characters outside of the base58 alphabet
+        "0OIlCzfq3xZZDmsdGdUyrNLtRhGc47cXcZtLG4crry",
+        // digits only (a u128 amount)
+        "340282366920938463463374607431768211455",
+        // padded with a space
+        " Czfq3xZZDmsdGdUyrNLtRhGc47cXcZtLG4crryfu44zE",
+        "",
+    ];
+
+    fn blocks_with_non_canonical_strings() -> Vec<WhirlpoolEventBlock> {
+        let mut blocks = fixtures::generate_blocks(3);
+        for block in blocks.iter_mut() {
+            for transaction in block.transactions.iter_mut() {
+                transaction.raw_instructions = Some(
+                    NON_CANONICAL_STRINGS
+                        .iter()
+                        .map(|s| RawInstruction {
+                            name: s.to_string(),
+                            payload: serde_json::json!({ "s": s, "f": 0.5, "i": -1, "n": null, "b": true }),
+                        })
+                        .collect(),
+                );
+            }
+        }
+        blocks
+    }
+
+    fn decode_record(record: &[u8]) -> Vec<WhirlpoolEventBlock> {
+        assert_eq!(record[..4], BINARY_RECORD_MAGIC);
+        let length = u32::from_le_bytes(record[4..8].try_into().unwrap()) as usize;
+        assert_eq!(record.len(), 8 + length);
+        decode_binary_record_payload(&record[8..]).unwrap()
+    }
+
+    // the format version and the pubkey dictionary, and the encoded blocks after them
+    fn split_payload(record: &[u8]) -> ((u32, Vec<[u8; 32]>), &[u8]) {
+        let payload = &record[8..];
+        let prefix: (u32, Vec<[u8; 32]>) = binary_options().allow_trailing_bytes().deserialize(payload).unwrap();
+        let length = binary_options().serialized_size(&prefix).unwrap() as usize;
+        (prefix, &payload[length..])
+    }
+
+    fn payload_of(prefix: &(u32, Vec<[u8; 32]>), encoded_blocks: &[u8]) -> Vec<u8> {
+        let mut payload = binary_options().serialize(prefix).unwrap();
+        payload.extend_from_slice(encoded_blocks);
+        payload
+    }
+
+    fn jsonl_of(blocks: &[WhirlpoolEventBlock]) -> Vec<u8> {
+        let mut jsonl = Vec::new();
+        for block in blocks {
+            serde_json::to_writer(&mut jsonl, block).unwrap();
+            jsonl.push(b'\n');
+        }
+        jsonl
+    }
+
+    #[test]
+    fn binary_round_trip_is_lossless() {
+        let blocks = blocks_with_non_canonical_strings();
+        let decoded = decode_record(&encode_binary_record(&blocks).unwrap());
+        assert_eq!(decoded, blocks);
+
+        // JSON lines re-encoded from the binary record are byte-identical
+        for (decoded, block) in decoded.iter().zip(blocks.iter()) {
+            assert_eq!(serde_json::to_string(decoded).unwrap(), serde_json::to_string(block).unwrap());
+        }
+    }
+
+    #[test]
+    fn only_canonical_pubkeys_are_dictionary_coded() {
+        for s in NON_CANONICAL_STRINGS {
+            assert_eq!(to_pubkey_bytes(s), None, "{:?}", s);
+        }
+        for whirlpool in fixtures::WHIRLPOOLS {
+            assert!(to_pubkey_bytes(whirlpool).is_some(), "{}", whirlpool);
+        }
+        // the zero pubkey is canonical
+        assert_eq!(to_pubkey_bytes("11111111111111111111111111111111"), Some([0u8; 32]));
+    }
+
+    #[test]
+    fn pubkey_dictionary_is_shared_in_a_chunk() {
+        let blocks = fixtures::generate_blocks(6);
+        let record = encode_binary_record(&blocks).unwrap();
+        let ((format_version, dictionary), _) = split_payload(&record);
+        assert_eq!(format_version, WHIRLPOOL_EVENT_FORMAT_VERSION);
+
+        // each distinct pubkey is stored once
+        let mut pubkeys = dictionary.clone();
+        pubkeys.sort();
+        pubkeys.dedup();
+        assert_eq!(pubkeys.len(), dictionary.len());
+        for whirlpool in fixtures::WHIRLPOOLS {
+            assert!(dictionary.contains(&to_pubkey_bytes(whirlpool).unwrap()), "{}", whirlpool);
+        }
+    }
+
+    #[test]
+    fn binary_record_is_smaller_than_jsonl() {
+        let blocks = fixtures::generate_blocks(BLOCKS_PER_CHUNK);
+        let jsonl = jsonl_of(&blocks);
+        let record = encode_binary_record(&blocks).unwrap();
+
+        // native integers and dictionary-coded pubkeys take less than half of the JSON text
+        assert!(record.len() * 2 < jsonl.len(), "binary: {} bytes, jsonl: {} bytes", record.len(), jsonl.len());
+    }
+
+    // cargo test --release binary_record_throughput -- --ignored --nocapture
+    #[test]
+    #[ignore = "timing depends on the build profile and the machine"]
+    fn binary_record_throughput() {
+        let blocks = fixtures::generate_blocks(BLOCKS_PER_CHUNK);
+        let rounds = 100;
+
+        let start = Instant::now();
+        for _ in 0..rounds {
+            let jsonl = jsonl_of(&blocks);
+            let decoded = jsonl
+                .split(|b| *b == b'\n')
+                .filter(|line| !line.is_empty())
+                .map(|line| serde_json::from_slice::<WhirlpoolEventBlock>(line).unwrap())
+                .collect::<Vec<_>>();
+            assert_eq!(decoded.len(), blocks.len());
+        }
+        let jsonl_elapsed = start.elapsed();
+
+        let start = Instant::now();
+        for _ in 0..rounds {
+            let record = encode_binary_record(&blocks).unwrap();
+            assert_eq!(decode_record(&record).len(), blocks.len());
+        }
+        let binary_elapsed = start.elapsed();
+
+        let jsonl_bytes = jsonl_of(&blocks).len();
+        let binary_bytes = encode_binary_record(&blocks).unwrap().len();
+        println!("jsonl:  {} bytes per chunk, {:?} per encode and decode", jsonl_bytes, jsonl_elapsed / rounds);
+        println!("binary: {} bytes per chunk, {:?} per encode and decode", binary_bytes, binary_elapsed / rounds);
+        assert!(binary_elapsed < jsonl_elapsed);
+    }
+
+    #[test]
+    fn empty_chunk_round_trip() {
+        assert_eq!(decode_record(&encode_binary_record(&[]).unwrap()), vec![]);
+    }
+
+    #[test]
+    fn out_of_range_pubkey_index_is_rejected() {
+        let record = encode_binary_record(&fixtures::generate_blocks(1)).unwrap();
+        let ((format_version, mut dictionary), encoded_blocks) = split_payload(&record);
+        assert!(decode_binary_record_payload(&payload_of(&(format_version, dictionary.clone()), encoded_blocks)).is_ok());
+
+        dictionary.pop();
+        let error = decode_binary_record_payload(&payload_of(&(format_version, dictionary), encoded_blocks)).unwrap_err();
+        assert!(error.to_string().contains("pubkey index out of range"), "{}", error);
+    }
+
+    #[test]
+    fn other_format_versions_are_rejected() {
+        let record = encode_binary_record(&fixtures::generate_blocks(1)).unwrap();
+        let ((_, dictionary), encoded_blocks) = split_payload(&record);
+        let older = payload_of(&(WHIRLPOOL_EVENT_FORMAT_VERSION - 1, dictionary), encoded_blocks);
+        assert!(decode_binary_record_payload(&older).is_err());
+    }
+}
@@ -1,7 +1,6 @@
 use core::panic;
 use std::collections::HashMap;
 
-use bigdecimal::BigDecimal;
 use replay_engine::{
     account_data_store::AccountDataStore,
     decoded_instructions::TransferAmountWithTransferFeeConfig, types::WritableAccountSnapshot,
@@ -9,6 +8,7 @@ use replay_engine::{
 use whirlpool_replayer::schema::DecodedWhirlpoolInstruction;
 
 use super::{definition::*, WhirlpoolEvent};
+use super::super::{account::DecodedTickArray, transfer_fee};
 use anchor_lang::prelude::*;
 use whirlpool_base::{
     math::sqrt_price_from_tick_index,
@@ -71,6 +71,12 @@ pub fn build_whirlpool_events(
                 protocol_fee_rate: old_whirlpool.protocol_fee_rate,
                 transfer_in: from_v1_transfer(params.transfer_amount_0, mint_in, decimals),
                 transfer_out: from_v1_transfer(params.transfer_amount_1, mint_out, decimals),
+                crossed_ticks: Some(get_crossed_ticks(
+                    writable_account_snapshot,
+                    &params.key_whirlpool,
+                    &old_whirlpool,
+                    &new_whirlpool,
+                )),
             }));
         }
         DecodedWhirlpoolInstruction::SwapV2(params) => {
@@ -117,6 +123,12 @@ pub fn build_whirlpool_events(
                 protocol_fee_rate: old_whirlpool.protocol_fee_rate,
                 transfer_in: from_v2_transfer(&params.transfer_0, mint_in, decimals),
                 transfer_out: from_v2_transfer(&params.transfer_1, mint_out, decimals),
+                crossed_ticks: Some(get_crossed_ticks(
+                    writable_account_snapshot,
+                    &params.key_whirlpool,
+                    &old_whirlpool,
+                    &new_whirlpool,
+                )),
             }));
         }
         DecodedWhirlpoolInstruction::TwoHopSwap(params) => {
@@ -170,6 +182,12 @@ pub fn build_whirlpool_events(
                 protocol_fee_rate: old_whirlpool_one.protocol_fee_rate,
                 transfer_in: from_v1_transfer(params.transfer_amount_0, mint_in_one, decimals),
                 transfer_out: from_v1_transfer(params.transfer_amount_1, mint_out_one, decimals),
+                crossed_ticks: Some(get_crossed_ticks(
+                    writable_account_snapshot,
+                    &params.key_whirlpool_one,
+                    &old_whirlpool_one,
+                    &new_whirlpool_one,
+                )),
             }));
 
             let old_whirlpool_two =
@@ -222,6 +240,12 @@ pub fn build_whirlpool_events(
                 protocol_fee_rate: old_whirlpool_two.protocol_fee_rate,
                 transfer_in: from_v1_transfer(params.transfer_amount_2, mint_in_two, decimals),
                 transfer_out: from_v1_transfer(params.transfer_amount_3, mint_out_two, decimals),
+                crossed_ticks: Some(get_crossed_ticks(
+                    writable_account_snapshot,
+                    &params.key_whirlpool_two,
+                    &old_whirlpool_two,
+                    &new_whirlpool_two,
+                )),
             }));
         }
         DecodedWhirlpoolInstruction::TwoHopSwapV2(params) => {
@@ -275,6 +299,12 @@ pub fn build_whirlpool_events(
                 protocol_fee_rate: old_whirlpool_one.protocol_fee_rate,
                 transfer_in: from_v2_transfer(&params.transfer_0, mint_in_one, decimals),
                 transfer_out: from_v2_transfer(&params.transfer_1, mint_out_one, decimals),
+                crossed_ticks: Some(get_crossed_ticks(
+                    writable_account_snapshot,
+                    &params.key_whirlpool_one,
+                    &old_whirlpool_one,
+                    &new_whirlpool_one,
+                )),
             }));
 
             let old_whirlpool_two =
@@ -327,6 +357,12 @@ pub fn build_whirlpool_events(
                 protocol_fee_rate: old_whirlpool_two.protocol_fee_rate,
                 transfer_in: from_v2_transfer(&params.transfer_1, mint_in_two, decimals),
                 transfer_out: from_v2_transfer(&params.transfer_2, mint_out_two, decimals),
+                crossed_ticks: Some(get_crossed_ticks(
+                    writable_account_snapshot,
+                    &params.key_whirlpool_two,
+                    &old_whirlpool_two,
+                    &new_whirlpool_two,
+                )),
             }));
         }
         ////////////////////////////////////////////////////////////////////////////////
@@ -474,6 +510,8 @@ pub fn build_whirlpool_events(
                     ),
                     old_position_liquidity: old_position.liquidity,
                     new_position_liquidity: new_position.liquidity,
+                    old_position_fees_and_rewards: Some(position_fees_and_rewards(&old_position)),
+                    new_position_fees_and_rewards: Some(position_fees_and_rewards(&new_position)),
                     transfer_a: from_v1_transfer(
                         params.transfer_amount_0,
                         &new_whirlpool.token_mint_a,
@@ -528,6 +566,8 @@ pub fn build_whirlpool_events(
                     ),
                     old_position_liquidity: old_position.liquidity,
                     new_position_liquidity: new_position.liquidity,
+                    old_position_fees_and_rewards: Some(position_fees_and_rewards(&old_position)),
+                    new_position_fees_and_rewards: Some(position_fees_and_rewards(&new_position)),
                     transfer_a: from_v2_transfer(
                         &params.transfer_0,
                         &new_whirlpool.token_mint_a,
@@ -982,6 +1022,7 @@ pub fn build_whirlpool_events(
                 position_bundle_mint: None,
                 position_bundle: None,
                 position_bundle_index: None,
+                old_position_fees_and_rewards: Some(position_fees_and_rewards(&old_position)),
             }));
         }
         DecodedWhirlpoolInstruction::CloseBundledPosition(params) => {
@@ -1013,6 +1054,7 @@ pub fn build_whirlpool_events(
                 position_bundle_mint: Some(old_position.position_mint.to_string()),
                 position_bundle: Some(params.key_position_bundle.clone()),
                 position_bundle_index: Some(params.data_bundle_index),
+                old_position_fees_and_rewards: Some(position_fees_and_rewards(&old_position)),
             }));
         }
         DecodedWhirlpoolInstruction::ClosePositionWithTokenExtensions(params) => {
@@ -1043,6 +1085,7 @@ pub fn build_whirlpool_events(
                 position_bundle_mint: None,
                 position_bundle: None,
                 position_bundle_index: None,
+                old_position_fees_and_rewards: Some(position_fees_and_rewards(&old_position)),
             }));
         }
         ////////////////////////////////////////////////////////////////////////////////
@@ -1436,11 +1479,16 @@ pub fn build_whirlpool_events(
         // PositionHarvestUpdated: UpdateFeesAndRewards
         ////////////////////////////////////////////////////////////////////////////////
         DecodedWhirlpoolInstruction::UpdateFeesAndRewards(params) => {
+            let old_position = get_old_position(writable_account_snapshot, &params.key_position);
+            let new_position = get_new_position(accounts, &params.key_position);
+
             events.push(WhirlpoolEvent::PositionHarvestUpdated(
                 PositionHarvestUpdatedEventPayload {
                     origin: PositionHarvestUpdatedEventOrigin::UpdateFeesAndRewards,
                     whirlpool: params.key_whirlpool.clone(),
                     position: params.key_position.clone(),
+                    old_position_fees_and_rewards: Some(position_fees_and_rewards(&old_position)),
+                    new_position_fees_and_rewards: Some(position_fees_and_rewards(&new_position)),
                 },
             ));
         }
@@ -1483,6 +1531,8 @@ fn from_v1_transfer(
         decimals,
         transfer_fee_bps: None,
         transfer_fee_max: None,
+        transfer_fee_amount: None,
+        post_transfer_fee_amount: None,
     }
 }
 
@@ -1493,20 +1543,36 @@ fn from_v2_transfer(
 ) -> TransferInfo {
     let mint = mint.to_string();
     let decimals = *decimals_map.get(&mint).unwrap();
+    if !transfer.transfer_fee_config_opt {
+        return TransferInfo {
+            mint,
+            amount: transfer.amount,
+            decimals,
+            transfer_fee_bps: None,
+            transfer_fee_max: None,
+            transfer_fee_amount: None,
+            post_transfer_fee_amount: None,
+        };
+    }
+
+    // both amounts are left unknown if the fee config is invalid (e.g. the fee exceeds the amount)
+    let bps = transfer.transfer_fee_config_bps;
+    let max = transfer.transfer_fee_config_max;
+    let (transfer_fee_amount, post_transfer_fee_amount) = match (
+        transfer_fee::calculate_fee(transfer.amount, bps, max),
+        transfer_fee::calculate_post_fee_amount(transfer.amount, bps, max),
+    ) {
+        (Some(fee), Some(post_fee_amount)) => (Some(fee), Some(post_fee_amount)),
+        _ => (None, None),
+    };
     TransferInfo {
         mint,
         amount: transfer.amount,
         decimals,
-        transfer_fee_bps: if transfer.transfer_fee_config_opt {
-            Some(transfer.transfer_fee_config_bps)
-        } else {
-            None
-        },
-        transfer_fee_max: if transfer.transfer_fee_config_opt {
-            Some(transfer.transfer_fee_config_max)
-        } else {
-            None
-        },
+        transfer_fee_bps: Some(bps),
+        transfer_fee_max: Some(max),
+        transfer_fee_amount,
+        post_transfer_fee_amount,
     }
 }
 
@@ -1546,6 +1612,20 @@ fn get_new_position(accounts: &AccountDataStore, pubkey: &PubkeyString) -> Posit
     Position::try_deserialize(&mut post_data.as_slice()).unwrap()
 }
 
+// boxed to keep WhirlpoolEvent small
+fn position_fees_and_rewards(position: &Position) -> Box<PositionFeesAndRewards> {
+    Box::new(PositionFeesAndRewards {
+        fee_growth_checkpoint_a: position.fee_growth_checkpoint_a,
+        fee_owed_a: position.fee_owed_a,
+        fee_growth_checkpoint_b: position.fee_growth_checkpoint_b,
+        fee_owed_b: position.fee_owed_b,
+        reward_infos: position.reward_infos.map(|reward_info| PositionRewardInfoSnapshot {
+            growth_inside_checkpoint: reward_info.growth_inside_checkpoint,
+            amount_owed: reward_info.amount_owed,
+        }),
+    })
+}
+
 fn get_old_config(
     writable_account_snapshot: &WritableAccountSnapshot,
     pubkey: &PubkeyString,
@@ -1588,6 +1668,56 @@ fn get_new_config_extension(
     WhirlpoolsConfigExtension::try_deserialize(&mut post_data.as_slice()).unwrap()
 }
 
+// initialized ticks between the old and new current tick index, in the order of crossing
+// (tick arrays traversed by a swap are writable, so they are in the pre-snapshot)
+fn get_crossed_ticks(
+    writable_account_snapshot: &WritableAccountSnapshot,
+    whirlpool: &PubkeyString,
+    old_whirlpool: &Whirlpool,
+    new_whirlpool: &Whirlpool,
+) -> Vec<CrossedTick> {
+    let old_tick_index = old_whirlpool.tick_current_index;
+    let new_tick_index = new_whirlpool.tick_current_index;
+
+    // a_to_b crosses ticks in (new, old], b_to_a crosses ticks in (old, new]
+    let a_to_b = new_tick_index < old_tick_index;
+    let (lower, upper) = if a_to_b {
+        (new_tick_index, old_tick_index)
+    } else {
+        (old_tick_index, new_tick_index)
+    };
+    if lower == upper {
+        return vec![];
+    }
+
+    let mut crossed_ticks = writable_account_snapshot
+        .pre_snapshot
+        .values()
+        .filter_map(|data| DecodedTickArray::decode(data))
+        .filter(|tick_array| tick_array.whirlpool.to_string() == *whirlpool)
+        .flat_map(|tick_array| {
+            tick_array
+                .ticks
+                .iter()
+                .enumerate()
+                .filter(|(_, tick)| tick.initialized)
+                .map(|(offset, tick)| CrossedTick {
+                    tick_index: tick_array.tick_index_of(offset, old_whirlpool.tick_spacing),
+                    liquidity_net: tick.liquidity_net,
+                })
+                .collect::<Vec<_>>()
+        })
+        .filter(|tick| lower < tick.tick_index && tick.tick_index <= upper)
+        .collect::<Vec<_>>();
+
+    if a_to_b {
+        crossed_ticks.sort_by_key(|tick| std::cmp::Reverse(tick.tick_index));
+    } else {
+        crossed_ticks.sort_by_key(|tick| tick.tick_index);
+    }
+    crossed_ticks
+}
+
 fn tick_index_to_decimal_price(
     tick_index: i32,
     mint_a: &Pubkey,
@@ -1598,19 +1728,112 @@ fn tick_index_to_decimal_price(
     sqrt_price_to_decimal_price(sqrt_price, mint_a, mint_b, decimals_map)
 }
 
-static X64: std::sync::OnceLock<BigDecimal> = std::sync::OnceLock::new();
 fn sqrt_price_to_decimal_price(
     sqrt_price: u128,
     mint_a: &Pubkey,
     mint_b: &Pubkey,
     decimals_map: &HashMap<String, u8>,
 ) -> DecimalPrice {
-    let x64 = X64.get_or_init(|| BigDecimal::from(1u128 << 64));
-    let price = (BigDecimal::from(sqrt_price) / x64).square();
+    let decimals_a = *decimals_map.get(&mint_a.to_string()).unwrap();
+    let decimals_b = *decimals_map.get(&mint_b.to_string()).unwrap();
 
-    let decimals_a = i64::from(*decimals_map.get(&mint_a.to_string()).unwrap());
-    let decimals_b = i64::from(*decimals_map.get(&mint_b.to_string()).unwrap());
+    super::super::price::sqrt_price_to_decimal_price(sqrt_price, decimals_a, decimals_b)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::account::{TICK_ARRAY_LEN, TICK_ARRAY_SIZE};
+    use anchor_lang::Discriminator;
+    use whirlpool_base::state::TickArray;
+
+    const TICK_SPACING: u16 = 64;
+    const TICK_ARRAY_SPAN: i32 = TICK_ARRAY_SIZE as i32 * TICK_SPACING as i32;
+
+    // a tick array account with initialized ticks at the given offsets (liquidity_net = tick index)
+    fn tick_array_data(whirlpool: &Pubkey, start_tick_index: i32, initialized_offsets: &[usize]) -> Vec<u8> {
+        let tick_len = (TICK_ARRAY_LEN - 8 - 4 - 32) / TICK_ARRAY_SIZE;
+        let mut data = vec![0u8; TICK_ARRAY_LEN];
+        data[..8].copy_from_slice(&TickArray::DISCRIMINATOR);
+        data[8..12].copy_from_slice(&start_tick_index.to_le_bytes());
+        for offset in initialized_offsets {
+            let tick = 12 + offset * tick_len;
+            let tick_index = start_tick_index + *offset as i32 * TICK_SPACING as i32;
+            data[tick] = 1;
+            data[tick + 1..tick + 17].copy_from_slice(&(tick_index as i128).to_le_bytes());
+        }
+        data[TICK_ARRAY_LEN - 32..].copy_from_slice(whirlpool.as_ref());
+        data
+    }
 
-    let (i, scale) = price.as_bigint_and_exponent();
-    BigDecimal::new(i, scale - (decimals_a - decimals_b))
+    // initialized ticks at both edges of the boundaries between three adjacent tick arrays:
+    // -64 | 0, 5568 | 5632
+    fn snapshot(whirlpool: &Pubkey) -> WritableAccountSnapshot {
+        let other_whirlpool = Pubkey::new_unique();
+        let last = TICK_ARRAY_SIZE - 1;
+        let tick_arrays = [
+            ("lower", tick_array_data(whirlpool, -TICK_ARRAY_SPAN, &[last])),
+            ("middle", tick_array_data(whirlpool, 0, &[0, last])),
+            ("upper", tick_array_data(whirlpool, TICK_ARRAY_SPAN, &[0])),
+            // ticks of other pools are not crossed
+            ("other", tick_array_data(&other_whirlpool, 0, &[0, 1, last])),
+            // accounts other than tick arrays are ignored
+            ("whirlpool", vec![0u8; 100]),
+        ];
+        WritableAccountSnapshot {
+            pre_snapshot: tick_arrays
+                .into_iter()
+                .map(|(pubkey, data)| (pubkey.to_string(), data))
+                .collect(),
+            post_snapshot: Default::default(),
+        }
+    }
+
+    fn crossed_tick_indexes(old_tick_index: i32, new_tick_index: i32) -> Vec<i32> {
+        let whirlpool = Pubkey::new_unique();
+        let whirlpool_of = |tick_current_index| Whirlpool {
+            tick_spacing: TICK_SPACING,
+            tick_current_index,
+            ..Default::default()
+        };
+        let crossed_ticks = get_crossed_ticks(
+            &snapshot(&whirlpool),
+            &whirlpool.to_string(),
+            &whirlpool_of(old_tick_index),
+            &whirlpool_of(new_tick_index),
+        );
+        // liquidity_net of the fixture identifies the tick
+        for tick in crossed_ticks.iter() {
+            assert_eq!(tick.liquidity_net, tick.tick_index as i128);
+        }
+        crossed_ticks.iter().map(|tick| tick.tick_index).collect()
+    }
+
+    #[test]
+    fn a_to_b_crosses_ticks_in_descending_order() {
+        let last = TICK_ARRAY_SPAN - TICK_SPACING as i32;
+        // a tick is crossed when the price reaches it going down, the current tick becomes the one below it
+        assert_eq!(crossed_tick_indexes(TICK_ARRAY_SPAN, -65), vec![TICK_ARRAY_SPAN, last, 0, -64]);
+        // the old current tick is crossed, the new one is not
+        assert_eq!(crossed_tick_indexes(TICK_ARRAY_SPAN - 1, -64), vec![last, 0]);
+        assert_eq!(crossed_tick_indexes(0, -1), vec![0]);
+        assert_eq!(crossed_tick_indexes(-1, -64), Vec::<i32>::new());
+    }
+
+    #[test]
+    fn b_to_a_crosses_ticks_in_ascending_order() {
+        let last = TICK_ARRAY_SPAN - TICK_SPACING as i32;
+        // a tick is crossed when the price reaches it going up, the current tick becomes the crossed one
+        assert_eq!(crossed_tick_indexes(-65, TICK_ARRAY_SPAN), vec![-64, 0, last, TICK_ARRAY_SPAN]);
+        // the old current tick is not crossed, the new one is
+        assert_eq!(crossed_tick_indexes(-64, TICK_ARRAY_SPAN - 1), vec![0, last]);
+        assert_eq!(crossed_tick_indexes(-1, 0), vec![0]);
+        assert_eq!(crossed_tick_indexes(1, 63), Vec::<i32>::new());
+    }
+
+    #[test]
+    fn no_ticks_are_crossed_without_tick_change() {
+        assert_eq!(crossed_tick_indexes(0, 0), Vec::<i32>::new());
+        assert_eq!(crossed_tick_indexes(-64, -64), Vec::<i32>::new());
+    }
 }
@@ -1,7 +1,8 @@
 use super::PubkeyString;
 use serde_derive::{Serialize, Deserialize};
+use crate::model::schema::Schema;
 
-#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Schema)]
 pub struct ConfigExtensionInitializedEventPayload {
     // origin
     #[serde(rename = "o")]
@@ -20,7 +21,7 @@ pub struct ConfigExtensionInitializedEventPayload {
     pub token_badge_authority: PubkeyString,
 }
 
-#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Schema)]
 pub enum ConfigExtensionInitializedEventOrigin {
     #[serde(rename = "ice")]
     InitializeConfigExtension,
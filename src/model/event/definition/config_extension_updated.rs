@@ -1,7 +1,8 @@
 use super::PubkeyString;
 use serde_derive::{Serialize, Deserialize};
+use crate::model::schema::Schema;
 
-#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Schema)]
 pub struct ConfigExtensionUpdatedEventPayload {
     // origin
     #[serde(rename = "o")]
@@ -24,7 +25,7 @@ pub struct ConfigExtensionUpdatedEventPayload {
     pub new_token_badge_authority: PubkeyString,
 }
 
-#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Schema)]
 pub enum ConfigExtensionUpdatedEventOrigin {
     #[serde(rename = "scea")]
     SetConfigExtensionAuthority,
@@ -1,7 +1,8 @@
 use super::PubkeyString;
 use serde_derive::{Serialize, Deserialize};
+use crate::model::schema::Schema;
 
-#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Schema)]
 pub struct ConfigInitializedEventPayload {
     // origin
     #[serde(rename = "o")]
@@ -23,7 +24,7 @@ pub struct ConfigInitializedEventPayload {
     pub default_protocol_fee_rate: u16,
 }
 
-#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Schema)]
 pub enum ConfigInitializedEventOrigin {
     #[serde(rename = "ic")]
     InitializeConfig,
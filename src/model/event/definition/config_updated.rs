@@ -1,7 +1,8 @@
 use super::PubkeyString;
 use serde_derive::{Serialize, Deserialize};
+use crate::model::schema::Schema;
 
-#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Schema)]
 pub struct ConfigUpdatedEventPayload {
     // origin
     #[serde(rename = "o")]
@@ -32,7 +33,7 @@ pub struct ConfigUpdatedEventPayload {
 }
 
 #[allow(clippy::enum_variant_names)]
-#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Schema)]
 pub enum ConfigUpdatedEventOrigin {
     #[serde(rename = "sfa")]
     SetFeeAuthority,
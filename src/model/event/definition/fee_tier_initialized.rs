@@ -1,7 +1,8 @@
 use super::PubkeyString;
 use serde_derive::{Serialize, Deserialize};
+use crate::model::schema::Schema;
 
-#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Schema)]
 pub struct FeeTierInitializedEventPayload {
     // origin
     #[serde(rename = "o")]
@@ -20,7 +21,7 @@ pub struct FeeTierInitializedEventPayload {
     pub default_fee_rate: u16,
 }
 
-#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Schema)]
 pub enum FeeTierInitializedEventOrigin {
     #[serde(rename = "ift")]
     InitializeFeeTier,
@@ -1,7 +1,8 @@
 use super::PubkeyString;
 use serde_derive::{Serialize, Deserialize};
+use crate::model::schema::Schema;
 
-#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Schema)]
 pub struct FeeTierUpdatedEventPayload {
     // origin
     #[serde(rename = "o")]
@@ -22,7 +23,7 @@ pub struct FeeTierUpdatedEventPayload {
     pub new_default_fee_rate: u16,
 }
 
-#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Schema)]
 pub enum FeeTierUpdatedEventOrigin {
     #[serde(rename = "sdfr")]
     SetDefaultFeeRate,
@@ -1,8 +1,9 @@
 use super::super::super::serde::{string_u128, string_decimal_price};
 use super::{DecimalPrice, PubkeyString, TransferInfo};
 use serde_derive::{Serialize, Deserialize};
+use crate::model::schema::Schema;
 
-#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Schema)]
 pub struct LiquidityDepositedEventPayload {
     // origin
     #[serde(rename = "o")]
@@ -55,7 +56,7 @@ pub struct LiquidityDepositedEventPayload {
     pub whirlpool_decimal_price: DecimalPrice,
 }
 
-#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Schema)]
 pub enum LiquidityDepositedEventOrigin {
     #[serde(rename = "il")]
     IncreaseLiquidity,
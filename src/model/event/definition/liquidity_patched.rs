@@ -1,8 +1,9 @@
 use super::super::super::serde::string_u128;
 use super::PubkeyString;
 use serde_derive::{Serialize, Deserialize};
+use crate::model::schema::Schema;
 
-#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Schema)]
 pub struct LiquidityPatchedEventPayload {
     // origin
     #[serde(rename = "o")]
@@ -20,7 +21,7 @@ pub struct LiquidityPatchedEventPayload {
     pub new_whirlpool_liquidity: u128,
 }
 
-#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Schema)]
 pub enum LiquidityPatchedEventOrigin {
     #[serde(rename = "ail")]
     AdminIncreaseLiquidity,
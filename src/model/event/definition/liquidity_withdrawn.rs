@@ -1,8 +1,9 @@
 use super::super::super::serde::{string_decimal_price, string_u128};
-use super::{DecimalPrice, PubkeyString, TransferInfo};
+use super::{DecimalPrice, PositionFeesAndRewards, PubkeyString, TransferInfo};
 use serde_derive::{Serialize, Deserialize};
+use crate::model::schema::Schema;
 
-#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Schema)]
 pub struct LiquidityWithdrawnEventPayload {
     // origin
     #[serde(rename = "o")]
@@ -53,9 +54,15 @@ pub struct LiquidityWithdrawnEventPayload {
     pub whirlpool_current_tick_index: i32,
     #[serde(rename = "wdp", with = "string_decimal_price")]
     pub whirlpool_decimal_price: DecimalPrice,
+
+    // position fees and rewards (None for blocks converted before format version 3)
+    #[serde(rename = "opfr", skip_serializing_if = "Option::is_none", default = "Option::default")]
+    pub old_position_fees_and_rewards: Option<Box<PositionFeesAndRewards>>,
+    #[serde(rename = "npfr", skip_serializing_if = "Option::is_none", default = "Option::default")]
+    pub new_position_fees_and_rewards: Option<Box<PositionFeesAndRewards>>,
 }
 
-#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Schema)]
 pub enum LiquidityWithdrawnEventOrigin {
     #[serde(rename = "dl")]
     DecreaseLiquidity,
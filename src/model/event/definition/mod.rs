@@ -57,15 +57,16 @@ pub use traded::*;
 mod program_deployed;
 pub use program_deployed::*;
 
-use super::super::serde::{string_option_u64, string_u64};
+use super::super::serde::{string_option_u64, string_u128, string_u64};
 use bigdecimal::BigDecimal;
 use serde::{Serialize, Deserialize};
+use crate::model::schema::Schema;
 
 pub type PubkeyString = String;
 pub type DecimalPrice = BigDecimal;
 pub type Decimals = u8;
 
-#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Schema)]
 pub struct TransferInfo {
     #[serde(rename = "m")]
     pub mint: PubkeyString,
@@ -85,12 +86,51 @@ pub struct TransferInfo {
         with = "string_option_u64"
     )]
     pub transfer_fee_max: Option<u64>,
+
+    // computed from amount, transfer_fee_bps and transfer_fee_max (present only if the fee config is present)
+    #[serde(
+        rename = "tfa",
+        skip_serializing_if = "Option::is_none",
+        default = "Option::default",
+        with = "string_option_u64"
+    )]
+    pub transfer_fee_amount: Option<u64>,
+    #[serde(
+        rename = "pfa",
+        skip_serializing_if = "Option::is_none",
+        default = "Option::default",
+        with = "string_option_u64"
+    )]
+    pub post_transfer_fee_amount: Option<u64>,
 }
 
-#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Schema)]
 pub enum TokenProgram {
     #[serde(rename = "t")]
     Token,
     #[serde(rename = "t2")]
     Token2022,
 }
+
+// fee and reward accounting of a position account
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Schema)]
+pub struct PositionFeesAndRewards {
+    #[serde(rename = "fgca", with = "string_u128")]
+    pub fee_growth_checkpoint_a: u128,
+    #[serde(rename = "foa", with = "string_u64")]
+    pub fee_owed_a: u64,
+    #[serde(rename = "fgcb", with = "string_u128")]
+    pub fee_growth_checkpoint_b: u128,
+    #[serde(rename = "fob", with = "string_u64")]
+    pub fee_owed_b: u64,
+    #[serde(rename = "ri")]
+    pub reward_infos: [PositionRewardInfoSnapshot; 3],
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Schema)]
+pub struct PositionRewardInfoSnapshot {
+    #[serde(rename = "gic", with = "string_u128")]
+    pub growth_inside_checkpoint: u128,
+    #[serde(rename = "ao", with = "string_u64")]
+    pub amount_owed: u64,
+}
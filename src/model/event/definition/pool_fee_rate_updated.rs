@@ -1,7 +1,8 @@
 use super::PubkeyString;
 use serde_derive::{Serialize, Deserialize};
+use crate::model::schema::Schema;
 
-#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Schema)]
 pub struct PoolFeeRateUpdatedEventPayload {
     // origin
     #[serde(rename = "o")]
@@ -18,7 +19,7 @@ pub struct PoolFeeRateUpdatedEventPayload {
     pub new_fee_rate: u16,
 }
 
-#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Schema)]
 pub enum PoolFeeRateUpdatedEventOrigin {
     #[serde(rename = "sfr")]
     SetFeeRate,
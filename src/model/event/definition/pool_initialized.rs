@@ -3,8 +3,9 @@ use super::{
     DecimalPrice, Decimals, PubkeyString, TokenProgram,
 };
 use serde_derive::{Serialize, Deserialize};
+use crate::model::schema::Schema;
 
-#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Schema)]
 pub struct PoolInitializedEventPayload {
     // origin
     #[serde(rename = "o")]
@@ -50,7 +51,7 @@ pub struct PoolInitializedEventPayload {
     pub protocol_fee_rate: u16,
 }
 
-#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Schema)]
 pub enum PoolInitializedEventOrigin {
     #[serde(rename = "ip")]
     InitializePool,
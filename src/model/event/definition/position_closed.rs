@@ -1,9 +1,10 @@
 use super::super::super::serde::string_decimal_price;
 use super::position_opened::PositionType;
-use super::{DecimalPrice, PubkeyString};
+use super::{DecimalPrice, PositionFeesAndRewards, PubkeyString};
 use serde_derive::{Serialize, Deserialize};
+use crate::model::schema::Schema;
 
-#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Schema)]
 pub struct PositionClosedEventPayload {
     // origin
     #[serde(rename = "o")]
@@ -40,10 +41,14 @@ pub struct PositionClosedEventPayload {
     pub position_bundle: Option<PubkeyString>,
     #[serde(rename = "pbi", skip_serializing_if = "Option::is_none")]
     pub position_bundle_index: Option<u16>,
+
+    // position fees and rewards just before the close (None for blocks converted before format version 3)
+    #[serde(rename = "opfr", skip_serializing_if = "Option::is_none", default = "Option::default")]
+    pub old_position_fees_and_rewards: Option<Box<PositionFeesAndRewards>>,
 }
 
 #[allow(clippy::enum_variant_names)]
-#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Schema)]
 pub enum PositionClosedEventOrigin {
     #[serde(rename = "cp")]
     ClosePosition,
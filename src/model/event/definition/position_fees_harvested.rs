@@ -1,7 +1,8 @@
 use super::{PubkeyString, TransferInfo};
 use serde_derive::{Serialize, Deserialize};
+use crate::model::schema::Schema;
 
-#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Schema)]
 pub struct PositionFeesHarvestedEventPayload {
     // origin
     #[serde(rename = "o")]
@@ -21,7 +22,7 @@ pub struct PositionFeesHarvestedEventPayload {
     pub transfer_b: TransferInfo,
 }
 
-#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Schema)]
 pub enum PositionFeesHarvestedEventOrigin {
     #[serde(rename = "cf")]
     CollectFees,
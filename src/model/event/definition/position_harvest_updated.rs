@@ -1,7 +1,8 @@
-use super::PubkeyString;
+use super::{PositionFeesAndRewards, PubkeyString};
 use serde_derive::{Serialize, Deserialize};
+use crate::model::schema::Schema;
 
-#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Schema)]
 pub struct PositionHarvestUpdatedEventPayload {
     // origin
     #[serde(rename = "o")]
@@ -11,9 +12,15 @@ pub struct PositionHarvestUpdatedEventPayload {
     pub whirlpool: PubkeyString,
     #[serde(rename = "p")]
     pub position: PubkeyString,
+
+    // position fees and rewards (None for blocks converted before format version 3)
+    #[serde(rename = "opfr", skip_serializing_if = "Option::is_none", default = "Option::default")]
+    pub old_position_fees_and_rewards: Option<Box<PositionFeesAndRewards>>,
+    #[serde(rename = "npfr", skip_serializing_if = "Option::is_none", default = "Option::default")]
+    pub new_position_fees_and_rewards: Option<Box<PositionFeesAndRewards>>,
 }
 
-#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Schema)]
 pub enum PositionHarvestUpdatedEventOrigin {
     #[serde(rename = "ufar")]
     UpdateFeesAndRewards,
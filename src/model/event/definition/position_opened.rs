@@ -1,8 +1,9 @@
 use super::super::super::serde::string_decimal_price;
 use super::{DecimalPrice, PubkeyString};
 use serde_derive::{Serialize, Deserialize};
+use crate::model::schema::Schema;
 
-#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Schema)]
 pub struct PositionOpenedEventPayload {
     // origin
     #[serde(rename = "o")]
@@ -42,7 +43,7 @@ pub struct PositionOpenedEventPayload {
 }
 
 #[allow(clippy::enum_variant_names)]
-#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Schema)]
 pub enum PositionOpenedEventOrigin {
     #[serde(rename = "op")]
     OpenPosition,
@@ -54,7 +55,7 @@ pub enum PositionOpenedEventOrigin {
     OpenPositionWithTokenExtensions,
 }
 
-#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Schema)]
 pub enum PositionType {
     #[serde(rename = "p")]
     Position,
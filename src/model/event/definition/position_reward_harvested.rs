@@ -1,7 +1,8 @@
 use super::{PubkeyString, TransferInfo};
 use serde_derive::{Serialize, Deserialize};
+use crate::model::schema::Schema;
 
-#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Schema)]
 pub struct PositionRewardHarvestedEventPayload {
     // origin
     #[serde(rename = "o")]
@@ -22,7 +23,7 @@ pub struct PositionRewardHarvestedEventPayload {
     pub transfer_reward: TransferInfo,
 }
 
-#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Schema)]
 pub enum PositionRewardHarvestedEventOrigin {
     #[serde(rename = "cr")]
     CollectReward,
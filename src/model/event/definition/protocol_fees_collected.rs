@@ -1,7 +1,8 @@
 use super::{PubkeyString, TransferInfo};
 use serde_derive::{Serialize, Deserialize};
+use crate::model::schema::Schema;
 
-#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Schema)]
 pub struct ProtocolFeesCollectedEventPayload {
     // origin
     #[serde(rename = "o")]
@@ -21,7 +22,7 @@ pub struct ProtocolFeesCollectedEventPayload {
     pub transfer_b: TransferInfo,
 }
 
-#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Schema)]
 pub enum ProtocolFeesCollectedEventOrigin {
     #[serde(rename = "cpf")]
     CollectProtocolFees,
@@ -1,7 +1,8 @@
 use super::PubkeyString;
 use serde_derive::{Serialize, Deserialize};
+use crate::model::schema::Schema;
 
-#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Schema)]
 pub struct RewardAuthorityUpdatedEventPayload {
     // origin
     #[serde(rename = "o")]
@@ -19,7 +20,7 @@ pub struct RewardAuthorityUpdatedEventPayload {
     pub new_reward_authority: PubkeyString,
 }
 
-#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Schema)]
 pub enum RewardAuthorityUpdatedEventOrigin {
     #[serde(rename = "sra")]
     SetRewardAuthority,
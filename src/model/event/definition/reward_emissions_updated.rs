@@ -1,8 +1,9 @@
 use super::super::super::serde::string_u128;
 use super::{Decimals, PubkeyString};
 use serde_derive::{Serialize, Deserialize};
+use crate::model::schema::Schema;
 
-#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Schema)]
 pub struct RewardEmissionsUpdatedEventPayload {
     // origin
     #[serde(rename = "o")]
@@ -27,7 +28,7 @@ pub struct RewardEmissionsUpdatedEventPayload {
     pub new_emissions_per_second_x64: u128,
 }
 
-#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Schema)]
 pub enum RewardEmissionsUpdatedEventOrigin {
     #[serde(rename = "sre")]
     SetRewardEmissions,
@@ -1,7 +1,8 @@
 use super::{Decimals, PubkeyString, TokenProgram};
 use serde_derive::{Serialize, Deserialize};
+use crate::model::schema::Schema;
 
-#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Schema)]
 pub struct RewardInitializedEventPayload {
     // origin
     #[serde(rename = "o")]
@@ -24,7 +25,7 @@ pub struct RewardInitializedEventPayload {
     pub reward_decimal: Decimals,
 }
 
-#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Schema)]
 pub enum RewardInitializedEventOrigin {
     #[serde(rename = "ir")]
     InitializeReward,
@@ -1,7 +1,8 @@
 use super::PubkeyString;
 use serde_derive::{Serialize, Deserialize};
+use crate::model::schema::Schema;
 
-#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Schema)]
 pub struct TickArrayInitializedEventPayload {
     // origin
     #[serde(rename = "o")]
@@ -17,7 +18,7 @@ pub struct TickArrayInitializedEventPayload {
     pub tick_array: PubkeyString,
 }
 
-#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Schema)]
 pub enum TickArrayInitializedEventOrigin {
     #[serde(rename = "ita")]
     InitializeTickArray,
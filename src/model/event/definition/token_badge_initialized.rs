@@ -1,7 +1,8 @@
 use super::PubkeyString;
 use serde_derive::{Serialize, Deserialize};
+use crate::model::schema::Schema;
 
-#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Schema)]
 pub struct TokenBadgeInitializedEventPayload {
     // origin
     #[serde(rename = "o")]
@@ -20,7 +21,7 @@ pub struct TokenBadgeInitializedEventPayload {
     pub token_badge: PubkeyString,
 }
 
-#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Schema)]
 pub enum TokenBadgeInitializedEventOrigin {
     #[serde(rename = "itb")]
     InitializeTokenBadge,
@@ -1,8 +1,9 @@
-use super::super::super::serde::{string_decimal_price, string_u128};
+use super::super::super::serde::{string_decimal_price, string_i128, string_u128};
 use super::{DecimalPrice, PubkeyString, TransferInfo};
 use serde_derive::{Serialize, Deserialize};
+use crate::model::schema::Schema;
 
-#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Schema)]
 pub struct TradedEventPayload {
     // origin
     #[serde(rename = "o")]
@@ -41,9 +42,22 @@ pub struct TradedEventPayload {
     pub fee_rate: u16,
     #[serde(rename = "pfr")]
     pub protocol_fee_rate: u16,
+
+    // initialized ticks crossed by the trade, in the order of crossing
+    // (None for blocks converted before format version 2)
+    #[serde(rename = "ct", skip_serializing_if = "Option::is_none", default = "Option::default")]
+    pub crossed_ticks: Option<Vec<CrossedTick>>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Schema)]
+pub struct CrossedTick {
+    #[serde(rename = "ti")]
+    pub tick_index: i32,
+    #[serde(rename = "ln", with = "string_i128")]
+    pub liquidity_net: i128,
 }
 
-#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Schema)]
 pub enum TradedEventOrigin {
     #[serde(rename = "s")]
     Swap,
@@ -59,7 +73,7 @@ pub enum TradedEventOrigin {
     TwoHopSwapV2Two,
 }
 
-#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Schema)]
 pub enum TradeMode {
     #[serde(rename = "ei")]
     ExactInput,
@@ -67,7 +81,7 @@ pub enum TradeMode {
     ExactOutput,
 }
 
-#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Schema)]
 pub enum TradeDirection {
     #[serde(rename = "ab")]
     AtoB,
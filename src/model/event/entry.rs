@@ -0,0 +1,372 @@
+use super::{WhirlpoolEvent, WhirlpoolEventEntry};
+use serde::{
+    de::{self, value::StrDeserializer, DeserializeSeed, IgnoredAny, IntoDeserializer, MapAccess, Visitor},
+    ser::{self, Impossible, SerializeMap, SerializeStruct},
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+use std::fmt;
+
+/*
+
+Serialization of WhirlpoolEventEntry
+
+An entry is a single object: { n: name, p: payload, ii: instructionIndex, si: subIndex }.
+The name and payload are the adjacently tagged WhirlpoolEvent, and ii/si are fields of the entry.
+
+serde(flatten) would buffer every payload into an intermediate value before decoding it,
+so the entry is (de)serialized by hand, delegating n and p to the derived impl of WhirlpoolEvent:
+
+- serialize: the fields of WhirlpoolEvent are written into the map of the entry
+- deserialize: the payload is decoded in place when n precedes p (as the converter writes it),
+  and is buffered only if p comes first
+
+Binary formats have no keys, so the entry is the tuple (event, ii, si) there (see binary.rs).
+
+*/
+
+const NAME_KEY: &str = "n";
+const PAYLOAD_KEY: &str = "p";
+
+impl Serialize for WhirlpoolEventEntry {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if !serializer.is_human_readable() {
+            return (&self.event, &self.instruction_index, &self.sub_index).serialize(serializer);
+        }
+
+        let len = 2 + self.instruction_index.is_some() as usize + self.sub_index.is_some() as usize;
+        let mut map = serializer.serialize_map(Some(len))?;
+        self.event.serialize(InlineStructSerializer { map: &mut map })?;
+        if let Some(instruction_index) = &self.instruction_index {
+            map.serialize_entry("ii", instruction_index)?;
+        }
+        if let Some(sub_index) = &self.sub_index {
+            map.serialize_entry("si", sub_index)?;
+        }
+        map.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for WhirlpoolEventEntry {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        if !deserializer.is_human_readable() {
+            let (event, instruction_index, sub_index) = <(WhirlpoolEvent, Option<u32>, Option<u32>)>::deserialize(deserializer)?;
+            return Ok(WhirlpoolEventEntry {
+                event,
+                instruction_index,
+                sub_index,
+            });
+        }
+
+        deserializer.deserialize_map(EntryVisitor)
+    }
+}
+
+// serialize
+
+// accepts a struct only, and writes its fields into the map of the parent
+struct InlineStructSerializer<'a, M> {
+    map: &'a mut M,
+}
+
+impl<M: SerializeMap> SerializeStruct for InlineStructSerializer<'_, M> {
+    type Ok = ();
+    type Error = M::Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, key: &'static str, value: &T) -> Result<(), M::Error> {
+        self.map.serialize_entry(key, value)
+    }
+
+    fn end(self) -> Result<(), M::Error> {
+        Ok(())
+    }
+}
+
+macro_rules! unsupported {
+    ($($method:ident($($arg:ty),*)),* $(,)?) => {
+        $(
+            fn $method(self, $(_: $arg),*) -> Result<(), M::Error> {
+                Err(ser::Error::custom("event must be serialized as a struct"))
+            }
+        )*
+    };
+}
+
+impl<'a, M: SerializeMap> Serializer for InlineStructSerializer<'a, M> {
+    type Ok = ();
+    type Error = M::Error;
+    type SerializeSeq = Impossible<(), M::Error>;
+    type SerializeTuple = Impossible<(), M::Error>;
+    type SerializeTupleStruct = Impossible<(), M::Error>;
+    type SerializeTupleVariant = Impossible<(), M::Error>;
+    type SerializeMap = Impossible<(), M::Error>;
+    type SerializeStruct = Self;
+    type SerializeStructVariant = Impossible<(), M::Error>;
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self, M::Error> {
+        Ok(self)
+    }
+
+    unsupported!(
+        serialize_bool(bool),
+        serialize_i8(i8),
+        serialize_i16(i16),
+        serialize_i32(i32),
+        serialize_i64(i64),
+        serialize_u8(u8),
+        serialize_u16(u16),
+        serialize_u32(u32),
+        serialize_u64(u64),
+        serialize_f32(f32),
+        serialize_f64(f64),
+        serialize_char(char),
+        serialize_str(&str),
+        serialize_bytes(&[u8]),
+        serialize_none(),
+        serialize_unit(),
+        serialize_unit_struct(&'static str),
+        serialize_unit_variant(&'static str, u32, &'static str),
+    );
+
+    fn serialize_some<T: ?Sized + Serialize>(self, _: &T) -> Result<(), M::Error> {
+        Err(ser::Error::custom("event must be serialized as a struct"))
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(self, _: &'static str, _: &T) -> Result<(), M::Error> {
+        Err(ser::Error::custom("event must be serialized as a struct"))
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: &T,
+    ) -> Result<(), M::Error> {
+        Err(ser::Error::custom("event must be serialized as a struct"))
+    }
+
+    fn serialize_seq(self, _: Option<usize>) -> Result<Self::SerializeSeq, M::Error> {
+        Err(ser::Error::custom("event must be serialized as a struct"))
+    }
+
+    fn serialize_tuple(self, _: usize) -> Result<Self::SerializeTuple, M::Error> {
+        Err(ser::Error::custom("event must be serialized as a struct"))
+    }
+
+    fn serialize_tuple_struct(self, _: &'static str, _: usize) -> Result<Self::SerializeTupleStruct, M::Error> {
+        Err(ser::Error::custom("event must be serialized as a struct"))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeTupleVariant, M::Error> {
+        Err(ser::Error::custom("event must be serialized as a struct"))
+    }
+
+    fn serialize_map(self, _: Option<usize>) -> Result<Self::SerializeMap, M::Error> {
+        Err(ser::Error::custom("event must be serialized as a struct"))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        _: &'static str,
+        _: usize,
+    ) -> Result<Self::SerializeStructVariant, M::Error> {
+        Err(ser::Error::custom("event must be serialized as a struct"))
+    }
+}
+
+// deserialize
+
+#[derive(serde_derive::Deserialize)]
+enum EntryKey {
+    #[serde(rename = "n")]
+    Name,
+    #[serde(rename = "p")]
+    Payload,
+    #[serde(rename = "ii")]
+    InstructionIndex,
+    #[serde(rename = "si")]
+    SubIndex,
+    // unknown keys are ignored
+    #[serde(other)]
+    Other,
+}
+
+struct EntryVisitor;
+
+impl<'de> Visitor<'de> for EntryVisitor {
+    type Value = WhirlpoolEventEntry;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a whirlpool event entry")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut name: Option<String> = None;
+        let mut event = None;
+        let mut buffered_payload: Option<serde_json::Value> = None;
+        let mut instruction_index = None;
+        let mut sub_index = None;
+
+        while let Some(key) = map.next_key::<EntryKey>()? {
+            match key {
+                EntryKey::Name => name = Some(map.next_value()?),
+                EntryKey::Payload => match &name {
+                    Some(name) => event = Some(map.next_value_seed(PayloadSeed { name })?),
+                    None => buffered_payload = Some(map.next_value()?),
+                },
+                EntryKey::InstructionIndex => instruction_index = map.next_value()?,
+                EntryKey::SubIndex => sub_index = map.next_value()?,
+                EntryKey::Other => {
+                    map.next_value::<IgnoredAny>()?;
+                }
+            }
+        }
+
+        let event = match (event, name, buffered_payload) {
+            (Some(event), _, _) => event,
+            (None, Some(name), Some(payload)) => PayloadSeed { name: &name }
+                .deserialize(payload.into_deserializer())
+                .map_err(de::Error::custom)?,
+            (None, None, _) => return Err(de::Error::missing_field(NAME_KEY)),
+            (None, Some(_), None) => return Err(de::Error::missing_field(PAYLOAD_KEY)),
+        };
+
+        Ok(WhirlpoolEventEntry {
+            event,
+            instruction_index,
+            sub_index,
+        })
+    }
+}
+
+// decodes the payload of the named event with the derived impl of WhirlpoolEvent
+struct PayloadSeed<'a> {
+    name: &'a str,
+}
+
+impl<'de> DeserializeSeed<'de> for PayloadSeed<'_> {
+    type Value = WhirlpoolEvent;
+
+    fn deserialize<D: Deserializer<'de>>(self, payload: D) -> Result<WhirlpoolEvent, D::Error> {
+        WhirlpoolEvent::deserialize(TaggedEventDeserializer {
+            name: self.name,
+            payload: Some(payload),
+            next_key: Some(NAME_KEY),
+        })
+    }
+}
+
+// presents the name and the payload as the adjacently tagged object { n, p }
+struct TaggedEventDeserializer<'a, D> {
+    name: &'a str,
+    payload: Option<D>,
+    next_key: Option<&'static str>,
+}
+
+impl<'de, D: Deserializer<'de>> Deserializer<'de> for TaggedEventDeserializer<'_, D> {
+    type Error = D::Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, D::Error> {
+        visitor.visit_map(self)
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct newtype_struct seq tuple
+        tuple_struct map struct enum identifier ignored_any
+    }
+}
+
+impl<'de, D: Deserializer<'de>> MapAccess<'de> for TaggedEventDeserializer<'_, D> {
+    type Error = D::Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>, D::Error> {
+        let Some(key) = self.next_key else {
+            return Ok(None);
+        };
+        seed.deserialize(StrDeserializer::new(key)).map(Some)
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, D::Error> {
+        match self.next_key {
+            Some(NAME_KEY) => {
+                self.next_key = Some(PAYLOAD_KEY);
+                seed.deserialize(StrDeserializer::new(self.name))
+            }
+            Some(_) => {
+                self.next_key = None;
+                let payload = self.payload.take().ok_or_else(|| de::Error::custom("payload is already consumed"))?;
+                seed.deserialize(payload)
+            }
+            None => Err(de::Error::custom("no more values")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::event::{definition::ProgramDeployedEventPayload, fixtures};
+
+    #[test]
+    fn entries_round_trip_through_json() {
+        let block = fixtures::current_block();
+        for entry in block.transactions[0].events.iter() {
+            let json = serde_json::to_string(entry).unwrap();
+            assert!(json.starts_with(r#"{"n":"#), "{}", json);
+            assert_eq!(&serde_json::from_str::<WhirlpoolEventEntry>(&json).unwrap(), entry);
+        }
+    }
+
+    #[test]
+    fn indexes_are_omitted_if_unknown() {
+        let entry = WhirlpoolEventEntry {
+            event: WhirlpoolEvent::ProgramDeployed(ProgramDeployedEventPayload {}),
+            instruction_index: None,
+            sub_index: None,
+        };
+        let json = serde_json::to_string(&entry).unwrap();
+        assert_eq!(json, r#"{"n":"PD","p":{}}"#);
+        assert_eq!(serde_json::from_str::<WhirlpoolEventEntry>(&json).unwrap(), entry);
+    }
+
+    #[test]
+    fn keys_are_accepted_in_any_order() {
+        let expected = WhirlpoolEventEntry {
+            event: WhirlpoolEvent::ProgramDeployed(ProgramDeployedEventPayload {}),
+            instruction_index: Some(3),
+            sub_index: Some(1),
+        };
+        for json in [
+            r#"{"n":"PD","p":{},"ii":3,"si":1}"#,
+            r#"{"ii":3,"si":1,"n":"PD","p":{}}"#,
+            // the payload is buffered if it precedes the name
+            r#"{"p":{},"si":1,"n":"PD","ii":3}"#,
+            // unknown keys are ignored
+            r#"{"n":"PD","x":[1,2],"p":{},"ii":3,"si":1}"#,
+        ] {
+            assert_eq!(serde_json::from_str::<WhirlpoolEventEntry>(json).unwrap(), expected, "{}", json);
+        }
+    }
+
+    #[test]
+    fn invalid_entries_are_rejected() {
+        for json in [
+            r#"{"p":{}}"#,
+            r#"{"n":"PD"}"#,
+            r#"{"n":"UNKNOWN","p":{}}"#,
+            r#"{"n":"T","p":{}}"#,
+            r#"{"p":{},"n":"T"}"#,
+        ] {
+            assert!(serde_json::from_str::<WhirlpoolEventEntry>(json).is_err(), "{}", json);
+        }
+    }
+}
@@ -0,0 +1,86 @@
+use super::{definition::TransferInfo, WhirlpoolEvent, WhirlpoolEventBlock, WhirlpoolEventEntry, WhirlpoolEventTransaction};
+use crate::model::header::WhirlpoolFileHeader;
+
+// event files of each format version, in the form written by the converter of that version.
+// each file has one block with a trade (with a transfer fee config) and a harvest update.
+pub const EVENT_FILE_FIXTURES: [(u32, &str); 6] = [
+    (1, include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/event/v1.jsonl"))),
+    (2, include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/event/v2.jsonl"))),
+    (3, include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/event/v3.jsonl"))),
+    (4, include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/event/v4.jsonl"))),
+    (5, include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/event/v5.jsonl"))),
+    (6, include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/event/v6.jsonl"))),
+];
+
+pub const WHIRLPOOLS: [&str; 3] = [
+    "Czfq3xZZDmsdGdUyrNLtRhGc47cXcZtLG4crryfu44zE",
+    "HJPjoWUrhoZzkNfRpHuieeFk9WcZWjwy6PBjZ81ngndJ",
+    "83v8iPyZihDEjDdY8RdZddyZNyUtXngz69Lgo9Kt5d6d",
+];
+
+pub fn current_header() -> WhirlpoolFileHeader {
+    let (_, fixture) = EVENT_FILE_FIXTURES[EVENT_FILE_FIXTURES.len() - 1];
+    WhirlpoolFileHeader::from_jsonl(fixture.lines().next().unwrap()).unwrap()
+}
+
+pub fn current_block() -> WhirlpoolEventBlock {
+    let (_, fixture) = EVENT_FILE_FIXTURES[EVENT_FILE_FIXTURES.len() - 1];
+    serde_json::from_str(fixture.lines().nth(1).unwrap()).unwrap()
+}
+
+// blocks of the current fixture with increasing (non-contiguous) slots, distinct signatures,
+// and events of WHIRLPOOLS[i % 3] in the i-th block
+pub fn generate_blocks(count: usize) -> Vec<WhirlpoolEventBlock> {
+    (0..count)
+        .map(|i| {
+            let mut block = current_block();
+            block.slot += 2 * i as u64;
+            block.block_height += i as u64;
+            block.block_time += i as i64 / 4;
+            for transaction in block.transactions.iter_mut() {
+                transaction.signature = format!("{}{}", &transaction.signature[..80], i);
+                for entry in transaction.events.iter_mut() {
+                    let whirlpool = WHIRLPOOLS[i % WHIRLPOOLS.len()].to_string();
+                    match &mut entry.event {
+                        WhirlpoolEvent::Traded(payload) => payload.whirlpool = whirlpool,
+                        WhirlpoolEvent::PositionHarvestUpdated(payload) => payload.whirlpool = whirlpool,
+                        _ => {}
+                    }
+                }
+            }
+            block
+        })
+        .collect()
+}
+
+// the trade of the current fixture in the whirlpool, paying amount_in of mint_in for amount_out of mint_out
+// (without transfer fees)
+pub fn traded_entry(whirlpool: &str, mint_in: &str, amount_in: u64, mint_out: &str, amount_out: u64) -> WhirlpoolEventEntry {
+    let transfer = |mint: &str, amount: u64| TransferInfo {
+        mint: mint.to_string(),
+        amount,
+        decimals: 6,
+        transfer_fee_bps: None,
+        transfer_fee_max: None,
+        transfer_fee_amount: None,
+        post_transfer_fee_amount: None,
+    };
+    let mut entry = current_block().transactions[0].events[0].clone();
+    match &mut entry.event {
+        WhirlpoolEvent::Traded(payload) => {
+            payload.whirlpool = whirlpool.to_string();
+            payload.transfer_in = transfer(mint_in, amount_in);
+            payload.transfer_out = transfer(mint_out, amount_out);
+        }
+        _ => unreachable!("the first event of the fixture is a trade"),
+    }
+    entry
+}
+
+// the transaction of the current fixture with the events
+pub fn transaction_with(events: Vec<WhirlpoolEventEntry>) -> WhirlpoolEventTransaction {
+    WhirlpoolEventTransaction {
+        events,
+        ..current_block().transactions[0].clone()
+    }
+}
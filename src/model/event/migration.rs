@@ -0,0 +1,292 @@
+use super::{WhirlpoolEventBlock, WHIRLPOOL_EVENT_FORMAT_VERSION};
+use crate::model::transfer_fee;
+use anyhow::{bail, Result};
+use serde_json::Value;
+
+/*
+
+Event Format Versions
+
+Files without a header line are treated as LEGACY_EVENT_FORMAT_VERSION.
+When a field is added to the format, bump WHIRLPOOL_EVENT_FORMAT_VERSION and register a migration
+that fills the field of older blocks with its default (or derived) value.
+Migrations only fill absent fields, so blocks of a newer version than the file declares
+(e.g. written by the current converter without --with-header) are decoded as they are.
+
+version 1: initial format
+version 2: crossedTicks(ct) added to Traded (absent for older blocks, because tick arrays are not recorded)
+version 3: old/newPositionFeesAndRewards(opfr/npfr) added to LiquidityWithdrawn, PositionClosed and PositionHarvestUpdated
+           (absent for older blocks, because position accounts are not recorded)
+version 4: index(i) added to transactions (derived from the position in the block),
+           instructionIndex(ii) and subIndex(si) added to events (absent for older blocks)
+version 5: rawInstructions(ri) added to transactions (opt-in, absent for older blocks)
+version 6: transferFeeAmount(tfa) and postTransferFeeAmount(pfa) added to transfers with a fee config
+           (derived from amount, transferFeeBps and transferFeeMax)
+
+*/
+
+pub const LEGACY_EVENT_FORMAT_VERSION: u32 = 1;
+
+type Migration = fn(&mut Value);
+
+// MIGRATIONS[i] upgrades a block from version (LEGACY_EVENT_FORMAT_VERSION + i) to the next version
+const MIGRATIONS: &[Migration] = &[
+    keep_underivable_fields_absent,
+    keep_underivable_fields_absent,
+    migrate_v3_to_v4,
+    keep_underivable_fields_absent,
+    migrate_v5_to_v6,
+];
+
+// fields that cannot be derived from a block are left as None (unknown)
+fn keep_underivable_fields_absent(_block: &mut Value) {}
+
+pub fn decode_whirlpool_event_block(jsonl: &str, format_version: u32) -> Result<WhirlpoolEventBlock> {
+    if format_version == WHIRLPOOL_EVENT_FORMAT_VERSION {
+        return Ok(serde_json::from_str(jsonl)?);
+    }
+
+    let mut block: Value = serde_json::from_str(jsonl)?;
+    migrate_whirlpool_event_block(&mut block, format_version)?;
+    Ok(serde_json::from_value(block)?)
+}
+
+pub fn migrate_whirlpool_event_block(block: &mut Value, from_format_version: u32) -> Result<()> {
+    if !(LEGACY_EVENT_FORMAT_VERSION..=WHIRLPOOL_EVENT_FORMAT_VERSION).contains(&from_format_version) {
+        bail!("unsupported event format version: {}", from_format_version);
+    }
+
+    let start = (from_format_version - LEGACY_EVENT_FORMAT_VERSION) as usize;
+    MIGRATIONS[start..].iter().for_each(|migration| migration(block));
+    Ok(())
+}
+
+fn migrate_v3_to_v4(block: &mut Value) {
+    for_each_transaction(block, |index, transaction| {
+        if let Some(transaction) = transaction.as_object_mut() {
+            transaction.entry("i").or_insert_with(|| Value::from(index));
+        }
+    });
+}
+
+fn migrate_v5_to_v6(block: &mut Value) {
+    let names = ["T", "LD", "LW", "PFH", "PRH", "PFC"];
+    for_each_event_payload(block, &names, |payload| {
+        for key in ["ti", "to", "ta", "tb", "tr"] {
+            if let Some(transfer) = payload.get_mut(key).and_then(Value::as_object_mut) {
+                let amount = transfer.get("a").and_then(Value::as_str).and_then(|s| s.parse::<u64>().ok());
+                let bps = transfer.get("tfb").and_then(Value::as_u64).and_then(|bps| u16::try_from(bps).ok());
+                let max = transfer.get("tfm").and_then(Value::as_str).and_then(|s| s.parse::<u64>().ok());
+                // transfers without a fee config (or with an invalid one, as in the converter) have no fee amount
+                if let (Some(amount), Some(bps), Some(max)) = (amount, bps, max) {
+                    let fee = transfer_fee::calculate_fee(amount, bps, max);
+                    let post_fee_amount = transfer_fee::calculate_post_fee_amount(amount, bps, max);
+                    if let (Some(fee), Some(post_fee_amount)) = (fee, post_fee_amount) {
+                        transfer.entry("tfa").or_insert_with(|| Value::from(fee.to_string()));
+                        transfer.entry("pfa").or_insert_with(|| Value::from(post_fee_amount.to_string()));
+                    }
+                }
+            }
+        }
+    });
+}
+
+// helpers for migrations
+
+fn for_each_transaction(block: &mut Value, mut f: impl FnMut(usize, &mut Value)) {
+    if let Some(transactions) = block.get_mut("x").and_then(Value::as_array_mut) {
+        transactions
+            .iter_mut()
+            .enumerate()
+            .for_each(|(index, transaction)| f(index, transaction));
+    }
+}
+
+fn for_each_event_payload(block: &mut Value, names: &[&str], mut f: impl FnMut(&mut Value)) {
+    for_each_transaction(block, |_, transaction| {
+        if let Some(events) = transaction.get_mut("e").and_then(Value::as_array_mut) {
+            events
+                .iter_mut()
+                .filter(|event| {
+                    event
+                        .get("n")
+                        .and_then(Value::as_str)
+                        .map(|name| names.contains(&name))
+                        .unwrap_or(false)
+                })
+                .filter_map(|event| event.get_mut("p"))
+                .for_each(&mut f);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::{
+        event::{definition::*, fixtures::EVENT_FILE_FIXTURES, WhirlpoolEvent},
+        header::WhirlpoolFileHeader,
+    };
+
+    // decodes a fixture as readers do: the format version comes from the header line if present
+    fn decode_fixture(fixture: &str) -> (Option<WhirlpoolFileHeader>, Vec<WhirlpoolEventBlock>) {
+        let mut lines = fixture.lines().peekable();
+        let header = lines.peek().and_then(|jsonl| WhirlpoolFileHeader::from_jsonl(jsonl));
+        if header.is_some() {
+            lines.next();
+        }
+        let format_version = header
+            .as_ref()
+            .map(|header| header.format_version)
+            .unwrap_or(LEGACY_EVENT_FORMAT_VERSION);
+        let blocks = lines
+            .map(|jsonl| decode_whirlpool_event_block(jsonl, format_version).unwrap())
+            .collect();
+        (header, blocks)
+    }
+
+    fn traded(block: &WhirlpoolEventBlock) -> &TradedEventPayload {
+        match &block.transactions[0].events[0].event {
+            WhirlpoolEvent::Traded(payload) => payload,
+            event => panic!("unexpected event: {:?}", event),
+        }
+    }
+
+    fn position_harvest_updated(block: &WhirlpoolEventBlock) -> &PositionHarvestUpdatedEventPayload {
+        match &block.transactions[0].events[1].event {
+            WhirlpoolEvent::PositionHarvestUpdated(payload) => payload,
+            event => panic!("unexpected event: {:?}", event),
+        }
+    }
+
+    #[test]
+    fn fixtures_cover_all_format_versions() {
+        let versions = EVENT_FILE_FIXTURES.iter().map(|(version, _)| *version).collect::<Vec<_>>();
+        let expected = (LEGACY_EVENT_FORMAT_VERSION..=WHIRLPOOL_EVENT_FORMAT_VERSION).collect::<Vec<_>>();
+        assert_eq!(versions, expected);
+
+        for (version, fixture) in EVENT_FILE_FIXTURES {
+            let (header, _) = decode_fixture(fixture);
+            match header {
+                Some(header) => assert_eq!(header.format_version, version),
+                None => assert_eq!(version, LEGACY_EVENT_FORMAT_VERSION),
+            }
+        }
+    }
+
+    #[test]
+    fn decode_all_format_versions_into_current_structs() {
+        for (version, fixture) in EVENT_FILE_FIXTURES {
+            let (_, blocks) = decode_fixture(fixture);
+            assert_eq!(blocks.len(), 1, "v{}", version);
+            let block = &blocks[0];
+            let transaction = &block.transactions[0];
+
+            // fields present since version 1
+            assert_eq!(block.slot, 245000123, "v{}", version);
+            assert_eq!(transaction.events.len(), 2, "v{}", version);
+            let traded = traded(block);
+            assert_eq!(traded.transfer_in.amount, 12345, "v{}", version);
+            assert_eq!(traded.old_sqrt_price, 7448058919009443725, "v{}", version);
+
+            // version 2: crossed ticks are not derivable
+            assert_eq!(traded.crossed_ticks.is_some(), version >= 2, "v{}", version);
+
+            // version 3: position fees and rewards are not derivable
+            let harvest = position_harvest_updated(block);
+            assert_eq!(harvest.old_position_fees_and_rewards.is_some(), version >= 3, "v{}", version);
+            assert_eq!(harvest.new_position_fees_and_rewards.is_some(), version >= 3, "v{}", version);
+
+            // version 4: the transaction index is derived, the instruction indexes are not
+            assert_eq!(transaction.index, 0, "v{}", version);
+            let instruction_indexes = transaction
+                .events
+                .iter()
+                .map(|entry| (entry.instruction_index, entry.sub_index))
+                .collect::<Vec<_>>();
+            if version >= 4 {
+                assert_eq!(instruction_indexes, vec![(Some(1), Some(0)), (Some(2), Some(0))], "v{}", version);
+            } else {
+                assert_eq!(instruction_indexes, vec![(None, None), (None, None)], "v{}", version);
+            }
+
+            // version 5: raw instructions are opt-in and not derivable
+            assert_eq!(transaction.raw_instructions.is_some(), version >= 5, "v{}", version);
+
+            // version 6: the transfer fee is derived from the fee config
+            assert_eq!(traded.transfer_in.transfer_fee_amount, Some(100), "v{}", version);
+            assert_eq!(traded.transfer_in.post_transfer_fee_amount, Some(12245), "v{}", version);
+            assert_eq!(traded.transfer_out.transfer_fee_amount, None, "v{}", version);
+            assert_eq!(traded.transfer_out.post_transfer_fee_amount, None, "v{}", version);
+        }
+    }
+
+    #[test]
+    fn current_format_version_is_decoded_as_is() {
+        let (_, fixture) = EVENT_FILE_FIXTURES[EVENT_FILE_FIXTURES.len() - 1];
+        let (_, blocks) = decode_fixture(fixture);
+        let jsonl = fixture.lines().nth(1).unwrap();
+        assert_eq!(serde_json::to_string(&blocks[0]).unwrap(), jsonl);
+    }
+
+    #[test]
+    fn headerless_current_format_version_is_decoded_as_is() {
+        // the converter writes the current version without a header line unless --with-header is given
+        let (_, fixture) = EVENT_FILE_FIXTURES[EVENT_FILE_FIXTURES.len() - 1];
+        let mut block: Value = serde_json::from_str(fixture.lines().nth(1).unwrap()).unwrap();
+        // an index and a fee amount a migration would derive differently
+        block["x"][0]["i"] = Value::from(7);
+        block["x"][0]["e"][0]["p"]["ti"]["tfa"] = Value::from("99");
+        let expected: WhirlpoolEventBlock = serde_json::from_value(block.clone()).unwrap();
+
+        let decoded = decode_whirlpool_event_block(&block.to_string(), LEGACY_EVENT_FORMAT_VERSION).unwrap();
+        assert_eq!(decoded, expected);
+    }
+
+    #[test]
+    fn migrated_blocks_match_current_format_except_underivable_fields() {
+        let (_, current) = decode_fixture(EVENT_FILE_FIXTURES[EVENT_FILE_FIXTURES.len() - 1].1);
+        let mut current = current.into_iter().next().unwrap();
+        // strip the fields absent in version 1
+        for transaction in current.transactions.iter_mut() {
+            transaction.raw_instructions = None;
+            for entry in transaction.events.iter_mut() {
+                entry.instruction_index = None;
+                entry.sub_index = None;
+                match &mut entry.event {
+                    WhirlpoolEvent::Traded(payload) => payload.crossed_ticks = None,
+                    WhirlpoolEvent::PositionHarvestUpdated(payload) => {
+                        payload.old_position_fees_and_rewards = None;
+                        payload.new_position_fees_and_rewards = None;
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        let (_, legacy) = decode_fixture(EVENT_FILE_FIXTURES[0].1);
+        assert_eq!(legacy[0], current);
+    }
+
+    #[test]
+    fn transfer_fee_is_not_derived_from_an_invalid_fee_config() {
+        // a fee rate over 100% takes more than the amount
+        let jsonl = EVENT_FILE_FIXTURES[EVENT_FILE_FIXTURES.len() - 2].1.lines().nth(1).unwrap();
+        let mut block: Value = serde_json::from_str(jsonl).unwrap();
+        block["x"][0]["e"][0]["p"]["ti"]["tfb"] = Value::from(20000);
+        block["x"][0]["e"][0]["p"]["ti"]["tfm"] = Value::from(u64::MAX.to_string());
+
+        let block = decode_whirlpool_event_block(&block.to_string(), WHIRLPOOL_EVENT_FORMAT_VERSION - 1).unwrap();
+        let traded = traded(&block);
+        assert_eq!(traded.transfer_in.transfer_fee_bps, Some(20000));
+        assert_eq!(traded.transfer_in.transfer_fee_amount, None);
+        assert_eq!(traded.transfer_in.post_transfer_fee_amount, None);
+    }
+
+    #[test]
+    fn unsupported_format_version_is_rejected() {
+        let jsonl = EVENT_FILE_FIXTURES[EVENT_FILE_FIXTURES.len() - 1].1.lines().nth(1).unwrap();
+        assert!(decode_whirlpool_event_block(jsonl, 0).is_err());
+        assert!(decode_whirlpool_event_block(jsonl, WHIRLPOOL_EVENT_FORMAT_VERSION + 1).is_err());
+    }
+}
@@ -1,9 +1,15 @@
+pub mod binary;
 pub mod convert;
 pub mod definition;
+mod entry;
+#[cfg(any(test, feature = "test-support"))]
+pub mod fixtures;
 pub mod group;
+pub mod migration;
 
 use definition::*;
 use serde_derive::{Serialize, Deserialize};
+use crate::model::{schema::Schema, serde::binary_json_value};
 
 /*
 
@@ -18,21 +24,40 @@ Each line is a JSON object with the following schema:
   blockTime(t): i64,
   transactions(x): [
     {
+      index(i): u32(position of the transaction in the block),
       signature(s): String(base58 encoding),
       payer(p): String(base58 encoding),
       events(e): [
-        { name(n): String, payload(p): Value },
-        { name(n): String, payload(p): Value },
+        { name(n): String, payload(p): Value, instructionIndex(ii): u32, subIndex(si): u32 },
+        { name(n): String, payload(p): Value, instructionIndex(ii): u32, subIndex(si): u32 },
         ...
       ],
+      rawInstructions(ri): [
+        { name(n): String, payload(p): Value(as in the transaction file) },
+        ...
+      ] (optional, only if the converter ran with raw instructions),
     },
     ...
   ]
 }
 
+instructionIndex is the position of the source instruction in the transaction,
+and subIndex is the position of the event among the events of that instruction (e.g. the legs of a two-hop swap).
+Both are absent in blocks converted before format version 4.
+rawInstructions keeps all instructions of the transaction in order, so instructionIndex points into it.
+
+The file may start with a header line (see model/header.rs).
+JSON Schema and TypeScript definitions of this format are generated from the types by the schema command (see model/schema/mod.rs).
+With --long-keys, the converter writes descriptive keys instead (e.g. oldSqrtPrice for osp), and records it in the header.
+Values are not affected, so event names and enum values stay short (e.g. "n": "T" becomes "name": "T").
+Other commands rekey long-key blocks to short keys while reading (the index command requires short keys),
+and the rekey command converts files between both key styles.
+
 */
 
-#[derive(Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub const WHIRLPOOL_EVENT_FORMAT_VERSION: u32 = 6;
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Schema)]
 pub struct WhirlpoolEventBlock {
     #[serde(rename = "s")]
     pub slot: u64,
@@ -44,17 +69,41 @@ pub struct WhirlpoolEventBlock {
     pub transactions: Vec<WhirlpoolEventTransaction>,
 }
 
-#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Schema)]
 pub struct WhirlpoolEventTransaction {
+    #[serde(rename = "i")]
+    pub index: u32,
     #[serde(rename = "s")]
     pub signature: String,
     #[serde(rename = "p")]
     pub payer: String,
     #[serde(rename = "e")]
-    pub events: Vec<WhirlpoolEvent>,
+    pub events: Vec<WhirlpoolEventEntry>,
+    #[serde(rename = "ri", skip_serializing_if = "Option::is_none", default = "Option::default")]
+    pub raw_instructions: Option<Vec<RawInstruction>>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Schema)]
+pub struct RawInstruction {
+    #[serde(rename = "n")]
+    pub name: String,
+    #[serde(rename = "p", with = "binary_json_value")]
+    pub payload: serde_json::Value,
 }
 
-#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+// the event (n, p) and the indexes (ii, si) share one object, see entry.rs for Serialize and Deserialize
+// (the serde attributes below only describe that object to derive(Schema))
+#[derive(Debug, PartialEq, Eq, Clone, Schema)]
+pub struct WhirlpoolEventEntry {
+    #[serde(flatten)]
+    pub event: WhirlpoolEvent,
+    #[serde(rename = "ii", skip_serializing_if = "Option::is_none")]
+    pub instruction_index: Option<u32>,
+    #[serde(rename = "si", skip_serializing_if = "Option::is_none")]
+    pub sub_index: Option<u32>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Schema)]
 #[serde(tag = "n", content = "p")]
 pub enum WhirlpoolEvent {
     // Program Deploy or Upgrade
@@ -145,3 +194,50 @@ pub enum WhirlpoolEvent {
     #[serde(rename = "LP")]
     LiquidityPatched(LiquidityPatchedEventPayload),
 }
+
+impl WhirlpoolEventEntry {
+    // "<instructionIndex>.<subIndex>" locates the event in its transaction in CSV exports
+    // (empty for blocks converted before format version 4)
+    pub fn location(&self) -> String {
+        match (self.instruction_index, self.sub_index) {
+            (Some(instruction_index), Some(sub_index)) => format!("{}.{}", instruction_index, sub_index),
+            _ => String::new(),
+        }
+    }
+}
+
+impl WhirlpoolEvent {
+    // the whirlpool the event is bound to (config, fee tier, token badge, etc. are not bound to a whirlpool)
+    pub fn whirlpool(&self) -> Option<&str> {
+        match self {
+            WhirlpoolEvent::Traded(payload) => Some(&payload.whirlpool),
+            WhirlpoolEvent::LiquidityDeposited(payload) => Some(&payload.whirlpool),
+            WhirlpoolEvent::LiquidityWithdrawn(payload) => Some(&payload.whirlpool),
+            WhirlpoolEvent::PoolInitialized(payload) => Some(&payload.whirlpool),
+            WhirlpoolEvent::RewardInitialized(payload) => Some(&payload.whirlpool),
+            WhirlpoolEvent::RewardEmissionsUpdated(payload) => Some(&payload.whirlpool),
+            WhirlpoolEvent::RewardAuthorityUpdated(payload) => Some(&payload.whirlpool),
+            WhirlpoolEvent::PositionHarvestUpdated(payload) => Some(&payload.whirlpool),
+            WhirlpoolEvent::PositionFeesHarvested(payload) => Some(&payload.whirlpool),
+            WhirlpoolEvent::PositionRewardHarvested(payload) => Some(&payload.whirlpool),
+            WhirlpoolEvent::ProtocolFeesCollected(payload) => Some(&payload.whirlpool),
+            WhirlpoolEvent::PositionOpened(payload) => Some(&payload.whirlpool),
+            WhirlpoolEvent::PositionClosed(payload) => Some(&payload.whirlpool),
+            WhirlpoolEvent::PoolFeeRateUpdated(payload) => Some(&payload.whirlpool),
+            WhirlpoolEvent::PoolProtocolFeeRateUpdated(payload) => Some(&payload.whirlpool),
+            WhirlpoolEvent::TickArrayInitialized(payload) => Some(&payload.whirlpool),
+            WhirlpoolEvent::LiquidityPatched(payload) => Some(&payload.whirlpool),
+            WhirlpoolEvent::ProgramDeployed(_)
+            | WhirlpoolEvent::PositionBundleInitialized(_)
+            | WhirlpoolEvent::PositionBundleDeleted(_)
+            | WhirlpoolEvent::ConfigInitialized(_)
+            | WhirlpoolEvent::ConfigUpdated(_)
+            | WhirlpoolEvent::FeeTierInitialized(_)
+            | WhirlpoolEvent::FeeTierUpdated(_)
+            | WhirlpoolEvent::ConfigExtensionInitialized(_)
+            | WhirlpoolEvent::ConfigExtensionUpdated(_)
+            | WhirlpoolEvent::TokenBadgeInitialized(_)
+            | WhirlpoolEvent::TokenBadgeDeleted(_) => None,
+        }
+    }
+}
@@ -0,0 +1,87 @@
+use serde_derive::{Deserialize, Serialize};
+use crate::model::schema::{KeyStyle, Schema};
+
+/*
+
+Whirlpool Archive File Header JSON Format
+
+Event, OHLCV and wallet activity files may optionally start with a header line (--with-header).
+The header line never shares a key with data lines, so readers can detect it by trying to parse the first line as a header.
+
+{
+  formatVersion(fv): u32,
+  kind(k): "event(e)" | "ohlcvDaily(od)" | "ohlcvMinutely(om)" | "wallet(w)",
+  converterVersion(cv): String,
+  sourceStateSlot(ss): u64,
+  inputFiles(i): [
+    { name(n): String(file name), crc32(c): String(hex encoding) },
+    ...
+  ],
+  compressionChunkBlocks(ccb): u32 (seekable event files only, absent if uncompressed),
+  keyStyle(ks): "short(s)" | "long(l)" (key style of the data lines, absent if short),
+}
+
+The header line itself is always in short keys, so readers can detect the key style before reading data lines.
+
+Compressed files are a single gzip member or zstd frame,
+except for event files written with --seekable, which are a sequence of independently compressed chunks:
+the header line, then one chunk per compressionChunkBlocks blocks.
+Readers of seekable files must decode all of them (e.g. MultiGzDecoder, gzip -d, zstd -d).
+
+*/
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Schema)]
+#[serde(deny_unknown_fields)]
+pub struct WhirlpoolFileHeader {
+    #[serde(rename = "fv")]
+    pub format_version: u32,
+    #[serde(rename = "k")]
+    pub kind: WhirlpoolFileKind,
+    #[serde(rename = "cv")]
+    pub converter_version: String,
+    #[serde(rename = "ss")]
+    pub source_state_slot: u64,
+    #[serde(rename = "i")]
+    pub input_files: Vec<InputFileChecksum>,
+    #[serde(rename = "ccb", skip_serializing_if = "Option::is_none", default = "Option::default")]
+    pub compression_chunk_blocks: Option<u32>,
+    #[serde(rename = "ks", skip_serializing_if = "Option::is_none", default = "Option::default")]
+    pub key_style: Option<KeyStyle>,
+}
+
+#[derive(clap::ValueEnum, Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Copy, Schema)]
+pub enum WhirlpoolFileKind {
+    #[serde(rename = "e")]
+    Event,
+    #[serde(rename = "od")]
+    OhlcvDaily,
+    #[serde(rename = "om")]
+    OhlcvMinutely,
+    #[serde(rename = "w")]
+    Wallet,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Schema)]
+#[serde(deny_unknown_fields)]
+pub struct InputFileChecksum {
+    #[serde(rename = "n")]
+    pub name: String,
+    #[serde(rename = "c")]
+    pub crc32: String,
+}
+
+pub const CONVERTER_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+impl WhirlpoolFileHeader {
+    pub fn from_jsonl(jsonl: &str) -> Option<Self> {
+        serde_json::from_str(jsonl).ok()
+    }
+
+    pub fn key_style(&self) -> KeyStyle {
+        self.key_style.unwrap_or(KeyStyle::Short)
+    }
+
+    pub fn is_compatible_with(&self, kind: WhirlpoolFileKind, max_format_version: u32) -> bool {
+        self.kind == kind && self.format_version <= max_format_version
+    }
+}
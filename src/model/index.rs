@@ -0,0 +1,92 @@
+use super::header::InputFileChecksum;
+use serde_derive::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/*
+
+Whirlpool Event Index Format
+
+A sidecar file of an event file, stored as gzip-compressed bincode.
+
+An event file written with --seekable is compressed in chunks (independent gzip members or zstd frames),
+so a block can be read by seeking to the start of its chunk and decoding only that chunk.
+Other compressed files are a single chunk, so reading a block decodes the file from the start (transcode with --seekable to avoid it).
+Uncompressed files have no chunk boundaries, so each record (JSON line or binary record) is located instead.
+
+{
+  formatVersion: u32,
+  eventFile: { name: String(file name), crc32: String(hex encoding) },
+  eventFormatVersion: u32,
+  blocks: [
+    { slot: u64, blockHeight: u64, blockTime: i64, offset: u64(byte offset of chunk), skip: u32(blocks before it in the chunk) },
+    ...
+  ],
+  signatures: { signature: { block: u32(index of blocks), transaction: u32(index in block) }, ... },
+  whirlpools: { whirlpool: [u32(index of blocks), ...], ... },
+}
+
+*/
+
+pub const WHIRLPOOL_EVENT_INDEX_FORMAT_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct WhirlpoolEventIndex {
+    pub format_version: u32,
+    pub event_file: InputFileChecksum,
+    pub event_format_version: u32,
+    pub blocks: Vec<BlockLocation>,
+    pub signatures: HashMap<String, TransactionLocation>,
+    pub whirlpools: HashMap<String, Vec<u32>>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Copy)]
+pub struct BlockLocation {
+    pub slot: u64,
+    pub block_height: u64,
+    pub block_time: i64,
+    pub offset: u64,
+    pub skip: u32,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone, Copy)]
+pub struct TransactionLocation {
+    pub block: u32,
+    pub transaction: u32,
+}
+
+impl WhirlpoolEventIndex {
+    // blocks are ordered by slot
+    pub fn block_of_slot(&self, slot: u64) -> Option<u32> {
+        self.blocks
+            .binary_search_by_key(&slot, |block| block.slot)
+            .ok()
+            .map(|index| index as u32)
+    }
+
+    pub fn blocks_in_slot_range(&self, start_slot: u64, end_slot: u64) -> Vec<u32> {
+        let start = self.blocks.partition_point(|block| block.slot < start_slot);
+        let end = self.blocks.partition_point(|block| block.slot <= end_slot);
+        (start..end.max(start)).map(|index| index as u32).collect()
+    }
+
+    // block_time is not guaranteed to be monotonic, so all blocks are checked
+    pub fn blocks_in_time_range(&self, start_block_time: i64, end_block_time: i64) -> Vec<u32> {
+        self.blocks
+            .iter()
+            .enumerate()
+            .filter(|(_, block)| block.block_time >= start_block_time && block.block_time <= end_block_time)
+            .map(|(index, _)| index as u32)
+            .collect()
+    }
+
+    pub fn transaction_of_signature(&self, signature: &str) -> Option<TransactionLocation> {
+        self.signatures.get(signature).copied()
+    }
+
+    pub fn blocks_of_whirlpool(&self, whirlpool: &str) -> &[u32] {
+        self.whirlpools
+            .get(whirlpool)
+            .map(|blocks| blocks.as_slice())
+            .unwrap_or(&[])
+    }
+}
@@ -1,3 +1,12 @@
+pub mod account;
+pub mod arbitrage;
 pub mod event;
+pub mod header;
+pub mod index;
 pub mod ohlcv;
+pub mod pool;
+pub mod price;
+pub mod schema;
 pub mod serde;
+pub mod transfer_fee;
+pub mod wallet;
@@ -1,4 +1,5 @@
-use serde_derive::Serialize;
+use serde_derive::{Deserialize, Serialize};
+use crate::model::schema::Schema;
 use super::serde::{string_decimal_price, string_u128, string_u64};
 
 /*
@@ -17,7 +18,7 @@ Each line is a JSON object with the following schema:
   tickSpacing(ts): u16,
   initialState(is):
     { t: "existing(e)", p: { previousCloseSqrtPrice(pcsp): String, previousCloseDecimalPrice(pcdp): String } } |
-    { t: "new(n)", p: { initialSqrtPrice(isp): String, initialDecimalPrice(idp): String, initializedSlot: u64, initializedBlockTime(ibt): i64 } },
+    { t: "new(n)", p: { initialSqrtPrice(isp): String, initialDecimalPrice(idp): String, initializedSlot(is): u64, initializedBlockTime(ibt): i64 } },
   estimatedFees(ef): {
     liquidityProviderFeeA(lpfa): u64,
     liquidityProviderFeeB(lpfb): u64,
@@ -31,6 +32,7 @@ Each line is a JSON object with the following schema:
       ab: { totalIn(ti): String, totalOut(to): String, count(c): u64 },
       ba: { totalIn(ti): String, totalOut(to): String, count(c): u64 },
     },
+    tickCrossings(tc): u64 (optional, omitted if a trade in the period comes from events without crossed ticks),
   },
 }
 
@@ -49,7 +51,7 @@ Each line is a JSON object with the following schema:
   tickSpacing(ts): u16,
   initialState(is):
     { t: "existing(e)", p: { previousCloseSqrtPrice(pcsp): String, previousCloseDecimalPrice(pcdp): String } } |
-    { t: "new(n)", p: { initialSqrtPrice(isp): String, initialDecimalPrice(idp): String, initializedSlot: u64, initializedBlockTime(ibt): i64 } },
+    { t: "new(n)", p: { initialSqrtPrice(isp): String, initialDecimalPrice(idp): String, initializedSlot(is): u64, initializedBlockTime(ibt): i64 } },
   estimatedFees(ef): {
     liquidityProviderFeeA(lpfa): u64,
     liquidityProviderFeeB(lpfb): u64,
@@ -63,6 +65,7 @@ Each line is a JSON object with the following schema:
       ab: { totalIn(ti): String, totalOut(to): String, count(c): u64 },
       ba: { totalIn(ti): String, totalOut(to): String, count(c): u64 },
     },
+    tickCrossings(tc): u64 (optional, omitted if a trade in the period comes from events without crossed ticks),
   },
   minutely(m): [
     {
@@ -72,18 +75,25 @@ Each line is a JSON object with the following schema:
         ab: { totalIn(ti): String, totalOut(to): String, count(c): u64 },
         ba: { totalIn(ti): String, totalOut(to): String, count(c): u64 },
       },
+      tickCrossings(tc): u64 (optional),
     },
     ...
   ],
 }
 
+If arbitrage is excluded, trades in cyclic arbitrage (see model/arbitrage.rs) update prices and fees but not volume.
+Both files may start with a header line (see model/header.rs).
+JSON Schema and TypeScript definitions of these formats are generated from the types by the schema command (see model/schema/mod.rs).
+
 */
 
+pub const WHIRLPOOL_OHLCV_FORMAT_VERSION: u32 = 2;
+
 pub type PubkeyString = String;
 pub type DecimalPrice = bigdecimal::BigDecimal;
 pub type Decimals = u8;
 
-#[derive(Serialize, Debug, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Schema)]
 pub struct WhirlpoolOhlcvDailyData {
   #[serde(flatten)]
   pub metadata: WhirlpoolOhlcvMetadata,
@@ -95,7 +105,7 @@ pub struct WhirlpoolOhlcvDailyData {
   pub daily: WhirlpoolOhlcvDataUnit,
 }
 
-#[derive(Serialize, Debug, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Schema)]
 pub struct WhirlpoolOhlcvMinutelyData {
   #[serde(flatten)]
   pub metadata: WhirlpoolOhlcvMetadata,
@@ -109,7 +119,7 @@ pub struct WhirlpoolOhlcvMinutelyData {
   pub minutely: Vec<WhirlpoolOhlcvDataUnit>,
 }
 
-#[derive(Serialize, Debug, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Schema)]
 pub struct WhirlpoolOhlcvMetadata {
   #[serde(rename = "w")]
   pub whirlpool: PubkeyString,
@@ -123,7 +133,7 @@ pub struct WhirlpoolOhlcvMetadata {
   pub tick_spacing: u16,
 }
 
-#[derive(Serialize, Debug, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Schema)]
 pub struct TokenData {
   #[serde(rename = "m")]
   pub mint: PubkeyString,
@@ -131,7 +141,7 @@ pub struct TokenData {
   pub decimals: Decimals,
 }
 
-#[derive(Serialize, Debug, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Schema)]
 #[serde(tag = "t", content = "p")]
 pub enum InitialState {
   #[serde(rename = "e")]
@@ -154,7 +164,7 @@ pub enum InitialState {
   },
 }
 
-#[derive(Serialize, Debug, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Schema)]
 pub struct EstimatedFees {
   #[serde(rename = "lpfa", with = "string_u64")]
   pub liquidity_provider_fee_a: u64,
@@ -166,7 +176,7 @@ pub struct EstimatedFees {
   pub protocol_fee_b: u64,
 }
 
-#[derive(Serialize, Debug, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Schema)]
 pub struct WhirlpoolOhlcvDataUnit {
   #[serde(rename = "t")]
   pub timestamp: i64,
@@ -174,9 +184,11 @@ pub struct WhirlpoolOhlcvDataUnit {
   pub ohlc: WhirlpoolOhlcvData,
   #[serde(rename = "v")]
   pub volume: VolumeData,
+  #[serde(rename = "tc", skip_serializing_if = "Option::is_none", default = "Option::default")]
+  pub tick_crossings: Option<u64>,
 }
 
-#[derive(Serialize, Debug, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Schema)]
 pub struct WhirlpoolOhlcvData {
   #[serde(rename = "sp")]
   pub sqrt_price: SqrtPriceData,
@@ -184,7 +196,7 @@ pub struct WhirlpoolOhlcvData {
   pub decimal_price: DecimalPriceData,
 }
 
-#[derive(Serialize, Debug, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Schema)]
 pub struct SqrtPriceData {
   #[serde(rename = "o", with = "string_u128")]
   pub open: u128,
@@ -196,7 +208,7 @@ pub struct SqrtPriceData {
   pub close: u128,
 }
 
-#[derive(Serialize, Debug, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Schema)]
 pub struct DecimalPriceData {
   #[serde(rename = "o", with = "string_decimal_price")]
   pub open: DecimalPrice,
@@ -208,13 +220,13 @@ pub struct DecimalPriceData {
   pub close: DecimalPrice,
 }
 
-#[derive(Serialize, Debug, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Schema)]
 pub struct VolumeData {
   pub ab: VolumeDirectionData,
   pub ba: VolumeDirectionData,
 }
 
-#[derive(Serialize, Debug, PartialEq, Eq)]
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Schema)]
 pub struct VolumeDirectionData {
   #[serde(rename = "ti", with = "string_u128")]
   pub total_in: u128,
@@ -0,0 +1,253 @@
+use super::event::{
+    definition::{CrossedTick, TradeDirection},
+    WhirlpoolEvent, WhirlpoolEventBlock,
+};
+use anchor_lang::{prelude::Pubkey, AccountDeserialize, Discriminator};
+use anyhow::Result;
+use replay_engine::account_data_store::AccountDataStore;
+use std::{collections::HashMap, str::FromStr};
+use whirlpool_base::state::Whirlpool;
+
+/*
+
+Event-sourced Pool State
+
+The state of each pool is folded from an initial state (accounts in a state file) and events.
+With history enabled, a snapshot is kept for every slot in which the pool changed,
+so the state of a pool can be queried as of any slot or block time.
+
+*/
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PoolState {
+    pub token_mint_a: Pubkey,
+    pub token_mint_b: Pubkey,
+    pub tick_spacing: u16,
+    pub sqrt_price: u128,
+    pub tick_current_index: i32,
+    // None after a trade moved the current tick without crossed ticks (blocks before format version 2)
+    pub liquidity: Option<u128>,
+    pub fee_rate: u16,
+    pub protocol_fee_rate: u16,
+    pub reward_infos: [PoolRewardInfo; 3],
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct PoolRewardInfo {
+    pub mint: Pubkey,
+    pub authority: Pubkey,
+    pub emissions_per_second_x64: u128,
+}
+
+impl PoolRewardInfo {
+    pub fn initialized(&self) -> bool {
+        self.mint != Pubkey::default()
+    }
+}
+
+impl From<&Whirlpool> for PoolState {
+    fn from(whirlpool: &Whirlpool) -> Self {
+        Self {
+            token_mint_a: whirlpool.token_mint_a,
+            token_mint_b: whirlpool.token_mint_b,
+            tick_spacing: whirlpool.tick_spacing,
+            sqrt_price: whirlpool.sqrt_price,
+            tick_current_index: whirlpool.tick_current_index,
+            liquidity: Some(whirlpool.liquidity),
+            fee_rate: whirlpool.fee_rate,
+            protocol_fee_rate: whirlpool.protocol_fee_rate,
+            reward_infos: whirlpool.reward_infos.map(|reward_info| PoolRewardInfo {
+                mint: reward_info.mint,
+                authority: reward_info.authority,
+                emissions_per_second_x64: reward_info.emissions_per_second_x64,
+            }),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PoolStateSnapshot {
+    pub slot: u64,
+    pub block_time: i64,
+    pub state: PoolState,
+    // true once the price was set by an event (initialization or trade), false while it is the price of the initial state
+    pub price_from_event: bool,
+}
+
+#[derive(Default)]
+pub struct PoolStateTracker {
+    keep_history: bool,
+    // the last snapshot is the current state
+    pools: HashMap<String, Vec<PoolStateSnapshot>>,
+}
+
+impl PoolStateTracker {
+    pub fn new(keep_history: bool) -> Self {
+        Self {
+            keep_history,
+            pools: HashMap::new(),
+        }
+    }
+
+    // initializes pools with Whirlpool accounts in a state
+    pub fn from_accounts(accounts: &AccountDataStore, slot: u64, block_time: i64, keep_history: bool) -> Result<Self> {
+        let mut tracker = Self::new(keep_history);
+        accounts.traverse(|pubkey, data| {
+            if data.starts_with(&Whirlpool::DISCRIMINATOR) {
+                let whirlpool = Whirlpool::try_deserialize(&mut data.as_slice())?;
+                tracker.insert(pubkey, slot, block_time, PoolState::from(&whirlpool));
+            }
+            Ok(())
+        })?;
+        Ok(tracker)
+    }
+
+    pub fn insert(&mut self, whirlpool: &str, slot: u64, block_time: i64, state: PoolState) {
+        self.insert_snapshot(whirlpool, PoolStateSnapshot { slot, block_time, state, price_from_event: false });
+    }
+
+    fn insert_snapshot(&mut self, whirlpool: &str, snapshot: PoolStateSnapshot) {
+        let slot = snapshot.slot;
+        let snapshots = self.pools.entry(whirlpool.to_string()).or_default();
+
+        match snapshots.last_mut() {
+            // one snapshot per slot is enough
+            Some(last) if last.slot == slot || !self.keep_history => *last = snapshot,
+            _ => snapshots.push(snapshot),
+        }
+    }
+
+    pub fn apply_block(&mut self, event_block: &WhirlpoolEventBlock) {
+        for transaction in event_block.transactions.iter() {
+            for entry in transaction.events.iter() {
+                self.apply_event(event_block.slot, event_block.block_time, &entry.event);
+            }
+        }
+    }
+
+    pub fn apply_event(&mut self, slot: u64, block_time: i64, event: &WhirlpoolEvent) {
+        if let WhirlpoolEvent::PoolInitialized(payload) = event {
+            let state = PoolState {
+                token_mint_a: Pubkey::from_str(&payload.token_mint_a).unwrap(),
+                token_mint_b: Pubkey::from_str(&payload.token_mint_b).unwrap(),
+                tick_spacing: payload.tick_spacing,
+                sqrt_price: payload.sqrt_price,
+                tick_current_index: payload.current_tick_index,
+                liquidity: Some(0),
+                fee_rate: payload.fee_rate,
+                protocol_fee_rate: payload.protocol_fee_rate,
+                reward_infos: Default::default(),
+            };
+            self.insert_snapshot(&payload.whirlpool, PoolStateSnapshot { slot, block_time, state, price_from_event: true });
+            return;
+        }
+
+        let Some(whirlpool) = event.whirlpool() else {
+            return;
+        };
+        let Some(current) = self.pools.get(whirlpool).and_then(|snapshots| snapshots.last()) else {
+            return;
+        };
+        let mut state = current.state;
+        // liquidity changes report the price, but do not change it
+        let price_from_event = current.price_from_event || matches!(event, WhirlpoolEvent::Traded(_));
+
+        match event {
+            WhirlpoolEvent::Traded(payload) => {
+                state.sqrt_price = payload.new_sqrt_price;
+                state.tick_current_index = payload.new_current_tick_index;
+                state.liquidity = match &payload.crossed_ticks {
+                    Some(crossed_ticks) => state
+                        .liquidity
+                        .and_then(|liquidity| apply_crossed_ticks(liquidity, crossed_ticks, &payload.trade_direction)),
+                    None if payload.old_current_tick_index != payload.new_current_tick_index => None,
+                    None => state.liquidity,
+                };
+                state.fee_rate = payload.fee_rate;
+                state.protocol_fee_rate = payload.protocol_fee_rate;
+            }
+            WhirlpoolEvent::LiquidityDeposited(payload) => {
+                state.liquidity = Some(payload.new_whirlpool_liquidity);
+                state.sqrt_price = payload.whirlpool_sqrt_price;
+                state.tick_current_index = payload.whirlpool_current_tick_index;
+            }
+            WhirlpoolEvent::LiquidityWithdrawn(payload) => {
+                state.liquidity = Some(payload.new_whirlpool_liquidity);
+                state.sqrt_price = payload.whirlpool_sqrt_price;
+                state.tick_current_index = payload.whirlpool_current_tick_index;
+            }
+            WhirlpoolEvent::LiquidityPatched(payload) => {
+                state.liquidity = Some(payload.new_whirlpool_liquidity);
+            }
+            WhirlpoolEvent::PoolFeeRateUpdated(payload) => {
+                state.fee_rate = payload.new_fee_rate;
+            }
+            WhirlpoolEvent::PoolProtocolFeeRateUpdated(payload) => {
+                state.protocol_fee_rate = payload.new_protocol_fee_rate;
+            }
+            WhirlpoolEvent::RewardInitialized(payload) => {
+                if let Some(reward_info) = state.reward_infos.get_mut(payload.reward_index as usize) {
+                    reward_info.mint = Pubkey::from_str(&payload.reward_mint).unwrap();
+                }
+            }
+            WhirlpoolEvent::RewardEmissionsUpdated(payload) => {
+                if let Some(reward_info) = state.reward_infos.get_mut(payload.reward_index as usize) {
+                    reward_info.emissions_per_second_x64 = payload.new_emissions_per_second_x64;
+                }
+            }
+            WhirlpoolEvent::RewardAuthorityUpdated(payload) => {
+                if let Some(reward_info) = state.reward_infos.get_mut(payload.reward_index as usize) {
+                    reward_info.authority = Pubkey::from_str(&payload.new_reward_authority).unwrap();
+                }
+            }
+            _ => return,
+        }
+
+        self.insert_snapshot(whirlpool, PoolStateSnapshot { slot, block_time, state, price_from_event });
+    }
+
+    pub fn current(&self, whirlpool: &str) -> Option<&PoolState> {
+        self.pools
+            .get(whirlpool)
+            .and_then(|snapshots| snapshots.last())
+            .map(|snapshot| &snapshot.state)
+    }
+
+    pub fn pools(&self) -> impl Iterator<Item = (&String, &PoolState)> {
+        self.pools
+            .iter()
+            .filter_map(|(whirlpool, snapshots)| snapshots.last().map(|snapshot| (whirlpool, &snapshot.state)))
+    }
+
+    pub fn history(&self, whirlpool: &str) -> &[PoolStateSnapshot] {
+        self.pools
+            .get(whirlpool)
+            .map(|snapshots| snapshots.as_slice())
+            .unwrap_or(&[])
+    }
+
+    // the state after all events in the slot (None if the pool did not exist yet)
+    pub fn state_at_slot(&self, whirlpool: &str, slot: u64) -> Option<&PoolStateSnapshot> {
+        let snapshots = self.pools.get(whirlpool)?;
+        let index = snapshots.partition_point(|snapshot| snapshot.slot <= slot);
+        index.checked_sub(1).map(|index| &snapshots[index])
+    }
+
+    // block times are treated as non-decreasing along slots
+    pub fn state_at_time(&self, whirlpool: &str, block_time: i64) -> Option<&PoolStateSnapshot> {
+        let snapshots = self.pools.get(whirlpool)?;
+        let index = snapshots.partition_point(|snapshot| snapshot.block_time <= block_time);
+        index.checked_sub(1).map(|index| &snapshots[index])
+    }
+}
+
+// liquidity_net is added when a tick is crossed left to right (b to a), and subtracted otherwise
+fn apply_crossed_ticks(liquidity: u128, crossed_ticks: &[CrossedTick], trade_direction: &TradeDirection) -> Option<u128> {
+    crossed_ticks.iter().try_fold(liquidity, |liquidity, tick| {
+        let liquidity_net = match trade_direction {
+            TradeDirection::AtoB => tick.liquidity_net.checked_neg()?,
+            TradeDirection::BtoA => tick.liquidity_net,
+        };
+        liquidity.checked_add_signed(liquidity_net)
+    })
+}
@@ -0,0 +1,11 @@
+use bigdecimal::BigDecimal;
+
+static X64: std::sync::OnceLock<BigDecimal> = std::sync::OnceLock::new();
+
+// price of token A in token B, adjusted by decimals
+pub fn sqrt_price_to_decimal_price(sqrt_price: u128, decimals_a: u8, decimals_b: u8) -> BigDecimal {
+    let x64 = X64.get_or_init(|| BigDecimal::from(1u128 << 64));
+    let price = (BigDecimal::from(sqrt_price) / x64).square();
+    let (i, scale) = price.as_bigint_and_exponent();
+    BigDecimal::new(i, scale - (decimals_a as i64 - decimals_b as i64))
+}
@@ -0,0 +1,321 @@
+use super::{FileSchema, KeyStyle, ObjectSchema, TaggedEnumSchema, TypeSchema};
+use serde_json::{json, Map, Value};
+
+// JSON Schema (draft 2020-12) of the data lines, the header line is defined in $defs (always in short keys).
+// the title of a property is its key in the opposite style.
+pub fn build_json_schema(file_schema: &FileSchema, style: KeyStyle) -> Value {
+    let definitions = file_schema.definitions.iter().map(|(name, schema)| (name.to_string(), build_type(schema, style)));
+    let header_definitions = file_schema
+        .header_definitions
+        .iter()
+        .map(|(name, schema)| (name.to_string(), build_type(schema, KeyStyle::Short)));
+    let definitions = definitions.chain(header_definitions).collect::<Map<String, Value>>();
+
+    let TypeSchema::Ref(root) = file_schema.root else {
+        unreachable!("root must be a named type");
+    };
+
+    json!({
+        "$schema": "https://json-schema.org/draft/2020-12/schema",
+        "title": root,
+        "$ref": format!("#/$defs/{}", root),
+        "$defs": definitions,
+    })
+}
+
+fn build_type(schema: &TypeSchema, style: KeyStyle) -> Value {
+    match schema {
+        TypeSchema::Boolean => json!({ "type": "boolean" }),
+        TypeSchema::Integer(kind) => {
+            let mut integer = json!({ "type": "integer" });
+            // 128-bit integers are always encoded as string
+            if kind.bits <= 64 {
+                if kind.signed {
+                    integer["minimum"] = json!(i64::MIN >> (64 - kind.bits));
+                    integer["maximum"] = json!(i64::MAX >> (64 - kind.bits));
+                } else {
+                    integer["minimum"] = json!(0);
+                    integer["maximum"] = json!(u64::MAX >> (64 - kind.bits));
+                }
+            }
+            integer
+        }
+        TypeSchema::StringInteger(kind) => json!({
+            "type": "string",
+            "pattern": if kind.signed { "^-?[0-9]+$" } else { "^[0-9]+$" },
+            "description": format!("{}{} encoded as a decimal string", if kind.signed { "i" } else { "u" }, kind.bits),
+        }),
+        TypeSchema::StringDecimal => json!({
+            "type": "string",
+            "description": "decimal number encoded as a string",
+        }),
+        TypeSchema::String => json!({ "type": "string" }),
+        TypeSchema::Any => json!({}),
+        TypeSchema::Nullable(schema) => json!({ "anyOf": [build_type(schema, style), { "type": "null" }] }),
+        TypeSchema::Array(schema, length) => {
+            let mut array = json!({ "type": "array", "items": build_type(schema, style) });
+            if let Some(length) = length {
+                array["minItems"] = json!(length);
+                array["maxItems"] = json!(length);
+            }
+            array
+        }
+        TypeSchema::Ref(name) => json!({ "$ref": format!("#/$defs/{}", name) }),
+        TypeSchema::Object(object) => build_object(object, style),
+        TypeSchema::Enum(values) => json!({ "type": "string", "enum": values }),
+        TypeSchema::TaggedEnum(tagged_enum) => build_tagged_enum(tagged_enum, style),
+    }
+}
+
+fn build_object(object: &ObjectSchema, style: KeyStyle) -> Value {
+    let mut properties = Map::new();
+    let mut required = vec![];
+    let mut flattened = vec![];
+    for field in object.fields.iter() {
+        let (Some(key), Some(title)) = (field.key_in(style), field.key_in(style.opposite())) else {
+            flattened.push(build_type(&field.schema, style));
+            continue;
+        };
+
+        // optional fields are omitted rather than null
+        let schema = match (&field.schema, field.optional) {
+            (TypeSchema::Nullable(schema), true) => schema.as_ref(),
+            (schema, _) => schema,
+        };
+        let mut property = build_type(schema, style);
+        property["title"] = json!(title);
+        if !field.optional {
+            required.push(key.clone());
+        }
+        properties.insert(key, property);
+    }
+
+    let mut own = json!({
+        "type": "object",
+        "properties": properties,
+        "required": required,
+    });
+    if object.deny_unknown_fields {
+        own["additionalProperties"] = json!(false);
+    }
+
+    if flattened.is_empty() {
+        own
+    } else {
+        flattened.insert(0, own);
+        json!({ "allOf": flattened })
+    }
+}
+
+fn build_tagged_enum(tagged_enum: &TaggedEnumSchema, style: KeyStyle) -> Value {
+    let variants = tagged_enum
+        .variants
+        .iter()
+        .map(|variant| {
+            let mut properties = Map::new();
+            let (tag, content) = (tagged_enum.tag_key_in(style), tagged_enum.content_key_in(style));
+            properties.insert(
+                tag.to_string(),
+                json!({ "const": variant.value, "title": tagged_enum.tag_key_in(style.opposite()), "description": variant.name }),
+            );
+            let mut required = vec![tag];
+            if let Some(payload) = &variant.payload {
+                let mut payload = build_type(payload, style);
+                payload["title"] = json!(tagged_enum.content_key_in(style.opposite()));
+                properties.insert(content.to_string(), payload);
+                required.push(content);
+            }
+            json!({ "type": "object", "properties": properties, "required": required })
+        })
+        .collect::<Vec<_>>();
+
+    json!({ "oneOf": variants })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::event::fixtures;
+    use crate::model::header::{InputFileChecksum, WhirlpoolFileHeader, WhirlpoolFileKind};
+    use crate::model::ohlcv::{
+        DecimalPrice, DecimalPriceData, EstimatedFees, InitialState, SqrtPriceData, TokenData, VolumeData, VolumeDirectionData,
+        WhirlpoolOhlcvDailyData, WhirlpoolOhlcvData, WhirlpoolOhlcvDataUnit, WhirlpoolOhlcvMetadata,
+        WhirlpoolOhlcvMinutelyData,
+    };
+    use crate::model::schema::{file_schema, rekey::Rekeyed};
+    use crate::model::wallet::{MintVolume, WalletActivity, WalletPosition, WhirlpoolWalletData};
+    use jsonschema::{Draft, JSONSchema};
+    use std::str::FromStr;
+
+    // the serialized lines must be valid in both key styles, with the header of a file in that style
+    fn assert_valid(kind: WhirlpoolFileKind, lines: Vec<Value>) {
+        let file_schema = file_schema(kind);
+        for style in [KeyStyle::Short, KeyStyle::Long] {
+            let json_schema = build_json_schema(&file_schema, style);
+            let mut header_json_schema = json_schema.clone();
+            header_json_schema["title"] = json!("WhirlpoolFileHeader");
+            header_json_schema["$ref"] = json!("#/$defs/WhirlpoolFileHeader");
+
+            let lines = lines
+                .iter()
+                .map(|line| serde_json::to_value(Rekeyed::new(line, &file_schema.root, &file_schema.definitions, KeyStyle::Short, style)).unwrap())
+                .collect::<Vec<_>>();
+            let header = serde_json::to_value(header(kind, style)).unwrap();
+
+            for (json_schema, lines) in [(json_schema, lines), (header_json_schema, vec![header])] {
+                let validator = JSONSchema::options().with_draft(Draft::Draft202012).compile(&json_schema).unwrap();
+                for line in lines.iter() {
+                    if let Err(errors) = validator.validate(line) {
+                        let errors = errors.map(|error| format!("{} at {}", error, error.instance_path)).collect::<Vec<_>>();
+                        panic!("{:?} line is invalid in {:?} keys: {:?}\n{}", kind, style, errors, line);
+                    };
+                }
+            }
+        }
+    }
+
+    fn header(kind: WhirlpoolFileKind, key_style: KeyStyle) -> WhirlpoolFileHeader {
+        WhirlpoolFileHeader {
+            format_version: 1,
+            kind,
+            converter_version: "0.1.0".to_string(),
+            source_state_slot: 250_000_000,
+            input_files: vec![InputFileChecksum { name: "whirlpool-transaction-20240101.jsonl.gz".to_string(), crc32: "0a1b2c3d".to_string() }],
+            compression_chunk_blocks: matches!(kind, WhirlpoolFileKind::Event).then_some(256),
+            key_style: (key_style == KeyStyle::Long).then_some(key_style),
+        }
+    }
+
+    fn ohlcv_metadata() -> WhirlpoolOhlcvMetadata {
+        WhirlpoolOhlcvMetadata {
+            whirlpool: fixtures::WHIRLPOOLS[0].to_string(),
+            whirlpools_config: "2LecshUwdy9xi7meFgHtFJQNSKk4KdTrcpvaB56dP2NQ".to_string(),
+            token_a: TokenData { mint: "So11111111111111111111111111111111111111112".to_string(), decimals: 9 },
+            token_b: TokenData { mint: "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v".to_string(), decimals: 6 },
+            tick_spacing: 64,
+        }
+    }
+
+    fn ohlcv_data_unit(timestamp: i64, tick_crossings: Option<u64>) -> WhirlpoolOhlcvDataUnit {
+        let price = |price: &str| DecimalPrice::from_str(price).unwrap();
+        WhirlpoolOhlcvDataUnit {
+            timestamp,
+            ohlc: WhirlpoolOhlcvData {
+                sqrt_price: SqrtPriceData { open: 7_378_908_147_574_224_556, high: 7_400_000_000_000_000_000, low: 7_300_000_000_000_000_000, close: 7_350_000_000_000_000_000 },
+                decimal_price: DecimalPriceData { open: price("160.01"), high: price("160.93"), low: price("156.60"), close: price("158.76") },
+            },
+            volume: VolumeData {
+                ab: VolumeDirectionData { total_in: 1_000_000_000, total_out: 158_000_000, count: 3 },
+                ba: VolumeDirectionData { total_in: 0, total_out: 0, count: 0 },
+            },
+            tick_crossings,
+        }
+    }
+
+    fn ohlcv_estimated_fees() -> EstimatedFees {
+        EstimatedFees { liquidity_provider_fee_a: 3_000_000, liquidity_provider_fee_b: 0, protocol_fee_a: 390_000, protocol_fee_b: 0 }
+    }
+
+    fn ohlcv_initial_states() -> [InitialState; 2] {
+        [
+            InitialState::Existing {
+                previous_close_sqrt_price: 7_378_908_147_574_224_556,
+                previous_close_decimal_price: DecimalPrice::from_str("160.01").unwrap(),
+            },
+            InitialState::New {
+                initial_sqrt_price: 18_446_744_073_709_551_616,
+                initial_decimal_price: DecimalPrice::from_str("1").unwrap(),
+                initialized_slot: 250_000_123,
+                initialized_block_time: 1_704_067_200,
+            },
+        ]
+    }
+
+    #[test]
+    fn event_lines_match_json_schema() {
+        let mut lines = fixtures::generate_blocks(3)
+            .iter()
+            .map(|block| serde_json::to_value(block).unwrap())
+            .collect::<Vec<_>>();
+        // blocks converted before format version 4 have no indexes and raw instructions
+        let mut block = fixtures::current_block();
+        for transaction in block.transactions.iter_mut() {
+            transaction.raw_instructions = None;
+            for entry in transaction.events.iter_mut() {
+                entry.instruction_index = None;
+                entry.sub_index = None;
+            }
+        }
+        lines.push(serde_json::to_value(&block).unwrap());
+
+        assert_valid(WhirlpoolFileKind::Event, lines);
+    }
+
+    #[test]
+    fn ohlcv_daily_lines_match_json_schema() {
+        let lines = ohlcv_initial_states()
+            .into_iter()
+            .map(|initial_state| {
+                serde_json::to_value(WhirlpoolOhlcvDailyData {
+                    metadata: ohlcv_metadata(),
+                    initial_state,
+                    estimated_fees: ohlcv_estimated_fees(),
+                    daily: ohlcv_data_unit(1_704_067_200, Some(12)),
+                })
+                .unwrap()
+            })
+            .collect();
+
+        assert_valid(WhirlpoolFileKind::OhlcvDaily, lines);
+    }
+
+    #[test]
+    fn ohlcv_minutely_lines_match_json_schema() {
+        let lines = ohlcv_initial_states()
+            .into_iter()
+            .map(|initial_state| {
+                serde_json::to_value(WhirlpoolOhlcvMinutelyData {
+                    metadata: ohlcv_metadata(),
+                    initial_state,
+                    estimated_fees: ohlcv_estimated_fees(),
+                    daily: ohlcv_data_unit(1_704_067_200, None),
+                    minutely: vec![ohlcv_data_unit(1_704_067_200, Some(1)), ohlcv_data_unit(1_704_067_260, None)],
+                })
+                .unwrap()
+            })
+            .collect();
+
+        assert_valid(WhirlpoolFileKind::OhlcvMinutely, lines);
+    }
+
+    #[test]
+    fn wallet_lines_match_json_schema() {
+        let activity = || WalletActivity {
+            transactions: 5,
+            trades: 3,
+            volume: vec![MintVolume { mint: "So11111111111111111111111111111111111111112".to_string(), total_in: 1_000_000_000, total_out: 0 }],
+            whirlpools: vec![fixtures::WHIRLPOOLS[0].to_string()],
+            first_seen_block_time: 1_704_067_200,
+            last_seen_block_time: 1_704_067_800,
+        };
+        let wallet = |as_payer: Option<WalletActivity>, as_token_authority: Option<WalletActivity>| WhirlpoolWalletData {
+            wallet: "9WzDXwBbmkg8ZTbNMqUxvQRAyrZzDsGYdLVL9zYtAWWM".to_string(),
+            as_payer,
+            as_token_authority,
+            positions: vec![WalletPosition {
+                position: "5V5ZAHW1yLdr4K3zFX7tYkWbcDTqcjN2TzfQLpNHsR7k".to_string(),
+                whirlpool: fixtures::WHIRLPOOLS[0].to_string(),
+                first_seen_block_time: 1_704_067_200,
+                last_seen_block_time: 1_704_067_800,
+                opened: true,
+                closed: false,
+            }],
+        };
+        let lines = [wallet(Some(activity()), Some(activity())), wallet(Some(activity()), None), wallet(None, None)]
+            .iter()
+            .map(|wallet| serde_json::to_value(wallet).unwrap())
+            .collect();
+
+        assert_valid(WhirlpoolFileKind::Wallet, lines);
+    }
+}
@@ -0,0 +1,281 @@
+use super::header::{WhirlpoolFileHeader, WhirlpoolFileKind};
+use bigdecimal::BigDecimal;
+use serde_derive::{Deserialize, Serialize};
+
+pub mod json_schema;
+pub mod rekey;
+pub mod typescript;
+
+pub use whirlpool_archive_converter_derive::Schema;
+
+/*
+
+Output Format Schema
+
+Types of the output formats derive Schema to describe their serialized form.
+The derive (in the derive crate) implements Schema from the serde attributes:
+
+  rename = "key"                  key of a field or value of a variant (Rust name if absent)
+  with = "string_*"               integers encoded as decimal strings (see model/serde.rs)
+  skip_serializing_if = "..."     optional field (omitted if None)
+  flatten                         fields of the type are inlined into the parent object
+  tag = "...", content = "..."    adjacently tagged enum
+  deny_unknown_fields             no other keys are allowed
+
+Other serde attributes are rejected at compile time.
+
+The descriptive (long) key of a field is its Rust name in camelCase (e.g. old_sqrt_price: oldSqrtPrice),
+and the tag and content of tagged enums are described as name and payload.
+Values (e.g. variant names of enums) are the same in both key styles.
+Both key styles are converted by rekey.rs in the order of the definitions.
+The header line is always in short keys, and records the key style of the data lines (keyStyle(ks)).
+
+*/
+
+pub trait Schema {
+    fn schema(definitions: &mut Definitions) -> TypeSchema;
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TypeSchema {
+    Boolean,
+    Integer(IntegerKind),
+    // integers serialized as decimal strings
+    StringInteger(IntegerKind),
+    StringDecimal,
+    String,
+    Any,
+    Nullable(Box<TypeSchema>),
+    // fixed length if the length is Some
+    Array(Box<TypeSchema>, Option<usize>),
+    Ref(&'static str),
+    Object(ObjectSchema),
+    Enum(Vec<&'static str>),
+    TaggedEnum(TaggedEnumSchema),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IntegerKind {
+    pub signed: bool,
+    pub bits: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ObjectSchema {
+    pub fields: Vec<Field>,
+    pub deny_unknown_fields: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Field {
+    pub name: &'static str,
+    // None if flattened
+    pub key: Option<&'static str>,
+    pub schema: TypeSchema,
+    pub optional: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TaggedEnumSchema {
+    pub tag: &'static str,
+    pub content: &'static str,
+    pub variants: Vec<Variant>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Variant {
+    pub name: &'static str,
+    pub value: &'static str,
+    pub payload: Option<TypeSchema>,
+}
+
+const TAG_LONG_KEY: &str = "name";
+const CONTENT_LONG_KEY: &str = "payload";
+
+// recorded in the header of long-key files (see model/header.rs)
+#[derive(clap::ValueEnum, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Schema)]
+pub enum KeyStyle {
+    #[serde(rename = "s")]
+    Short,
+    #[serde(rename = "l")]
+    Long,
+}
+
+impl KeyStyle {
+    pub fn opposite(self) -> Self {
+        match self {
+            KeyStyle::Short => KeyStyle::Long,
+            KeyStyle::Long => KeyStyle::Short,
+        }
+    }
+}
+
+// named types in the order of first reference
+#[derive(Debug, Default)]
+pub struct Definitions {
+    definitions: Vec<(&'static str, TypeSchema)>,
+}
+
+impl Definitions {
+    pub fn define(&mut self, name: &'static str, build: impl FnOnce(&mut Self) -> TypeSchema) -> TypeSchema {
+        if self.get(name).is_none() {
+            // placeholder for recursive references
+            self.definitions.push((name, TypeSchema::Any));
+            let schema = build(self);
+            self.definitions.iter_mut().find(|(n, _)| *n == name).unwrap().1 = schema;
+        }
+        TypeSchema::Ref(name)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&TypeSchema> {
+        self.definitions.iter().find(|(n, _)| *n == name).map(|(_, schema)| schema)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &(&'static str, TypeSchema)> {
+        self.definitions.iter()
+    }
+
+    // follows references to a non-reference schema
+    pub fn resolve<'a>(&'a self, schema: &'a TypeSchema) -> &'a TypeSchema {
+        match schema {
+            TypeSchema::Ref(name) => self.resolve(self.get(name).unwrap()),
+            schema => schema,
+        }
+    }
+}
+
+// schema of the lines of a file.
+// the header line is always in short keys, so its types are defined separately.
+pub struct FileSchema {
+    pub root: TypeSchema,
+    pub definitions: Definitions,
+    pub header: TypeSchema,
+    pub header_definitions: Definitions,
+}
+
+pub fn file_schema(kind: WhirlpoolFileKind) -> FileSchema {
+    let mut definitions = Definitions::default();
+    let root = match kind {
+        WhirlpoolFileKind::Event => super::event::WhirlpoolEventBlock::schema(&mut definitions),
+        WhirlpoolFileKind::OhlcvDaily => super::ohlcv::WhirlpoolOhlcvDailyData::schema(&mut definitions),
+        WhirlpoolFileKind::OhlcvMinutely => super::ohlcv::WhirlpoolOhlcvMinutelyData::schema(&mut definitions),
+        WhirlpoolFileKind::Wallet => super::wallet::WhirlpoolWalletData::schema(&mut definitions),
+    };
+    let mut header_definitions = Definitions::default();
+    let header = WhirlpoolFileHeader::schema(&mut header_definitions);
+    FileSchema { root, definitions, header, header_definitions }
+}
+
+impl Field {
+    // None if flattened
+    pub fn key_in(&self, style: KeyStyle) -> Option<String> {
+        self.key.map(|key| match style {
+            KeyStyle::Short => key.to_string(),
+            KeyStyle::Long => to_camel_case(self.name),
+        })
+    }
+}
+
+impl TaggedEnumSchema {
+    pub fn tag_key_in(&self, style: KeyStyle) -> &'static str {
+        match style {
+            KeyStyle::Short => self.tag,
+            KeyStyle::Long => TAG_LONG_KEY,
+        }
+    }
+
+    pub fn content_key_in(&self, style: KeyStyle) -> &'static str {
+        match style {
+            KeyStyle::Short => self.content,
+            KeyStyle::Long => CONTENT_LONG_KEY,
+        }
+    }
+}
+
+impl TypeSchema {
+    // integers are encoded as decimal strings by the string_* modules of model/serde.rs
+    pub fn encoded_as_string(self) -> Self {
+        match self {
+            TypeSchema::Integer(kind) => TypeSchema::StringInteger(kind),
+            TypeSchema::Nullable(schema) => TypeSchema::Nullable(Box::new(schema.encoded_as_string())),
+            schema => schema,
+        }
+    }
+}
+
+fn to_camel_case(name: &str) -> String {
+    let mut words = name.split('_');
+    let mut camel_case = words.next().unwrap_or_default().to_string();
+    for word in words {
+        let mut chars = word.chars();
+        if let Some(first) = chars.next() {
+            camel_case.extend(first.to_uppercase());
+            camel_case.push_str(chars.as_str());
+        }
+    }
+    camel_case
+}
+
+// schemas of the types used in the output formats
+
+macro_rules! integer_schema {
+    ($($t:ty: $signed:expr),* $(,)?) => {
+        $(
+            impl Schema for $t {
+                fn schema(_: &mut Definitions) -> TypeSchema {
+                    TypeSchema::Integer(IntegerKind { signed: $signed, bits: <$t>::BITS })
+                }
+            }
+        )*
+    };
+}
+
+integer_schema!(u8: false, u16: false, u32: false, u64: false, u128: false, i32: true, i64: true, i128: true);
+
+impl Schema for bool {
+    fn schema(_: &mut Definitions) -> TypeSchema {
+        TypeSchema::Boolean
+    }
+}
+
+impl Schema for String {
+    fn schema(_: &mut Definitions) -> TypeSchema {
+        TypeSchema::String
+    }
+}
+
+impl Schema for BigDecimal {
+    fn schema(_: &mut Definitions) -> TypeSchema {
+        TypeSchema::StringDecimal
+    }
+}
+
+impl Schema for serde_json::Value {
+    fn schema(_: &mut Definitions) -> TypeSchema {
+        TypeSchema::Any
+    }
+}
+
+impl<T: Schema> Schema for Option<T> {
+    fn schema(definitions: &mut Definitions) -> TypeSchema {
+        TypeSchema::Nullable(Box::new(T::schema(definitions)))
+    }
+}
+
+impl<T: Schema> Schema for Box<T> {
+    fn schema(definitions: &mut Definitions) -> TypeSchema {
+        T::schema(definitions)
+    }
+}
+
+impl<T: Schema> Schema for Vec<T> {
+    fn schema(definitions: &mut Definitions) -> TypeSchema {
+        TypeSchema::Array(Box::new(T::schema(definitions)), None)
+    }
+}
+
+impl<T: Schema, const N: usize> Schema for [T; N] {
+    fn schema(definitions: &mut Definitions) -> TypeSchema {
+        TypeSchema::Array(Box::new(T::schema(definitions)), Some(N))
+    }
+}
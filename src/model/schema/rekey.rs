@@ -0,0 +1,111 @@
+use super::{Definitions, KeyStyle, TypeSchema};
+use serde::ser::{Serialize, SerializeMap, SerializeSeq, Serializer};
+use serde_json::{Map, Value};
+
+// converts the keys of a JSON line between short and long keys.
+// only keys are converted, values are kept as they are, including enum values and event names
+// (e.g. "T" for Traded and "sv2" for SwapV2), which are the same in both key styles.
+pub fn rekey_jsonl(
+    jsonl: &str,
+    schema: &TypeSchema,
+    definitions: &Definitions,
+    from: KeyStyle,
+    to: KeyStyle,
+) -> serde_json::Result<String> {
+    let value: Value = serde_json::from_str(jsonl)?;
+    serde_json::to_string(&Rekeyed::new(&value, schema, definitions, from, to))
+}
+
+// serializes a value with the keys of another style, in the order of the definitions.
+// keys unknown to the schema are kept as they are after the known keys.
+pub struct Rekeyed<'a> {
+    value: &'a Value,
+    schema: &'a TypeSchema,
+    definitions: &'a Definitions,
+    from: KeyStyle,
+    to: KeyStyle,
+}
+
+impl<'a> Rekeyed<'a> {
+    pub fn new(value: &'a Value, schema: &'a TypeSchema, definitions: &'a Definitions, from: KeyStyle, to: KeyStyle) -> Self {
+        Rekeyed { value, schema, definitions, from, to }
+    }
+
+    fn nested(&self, value: &'a Value, schema: &'a TypeSchema) -> Self {
+        Rekeyed::new(value, schema, self.definitions, self.from, self.to)
+    }
+
+    fn serialize_entries<M: SerializeMap>(
+        &self,
+        map: &mut M,
+        object: &'a Map<String, Value>,
+        schema: &'a TypeSchema,
+        consumed: &mut Vec<String>,
+    ) -> Result<(), M::Error> {
+        match self.definitions.resolve(schema) {
+            TypeSchema::Object(object_schema) => {
+                for field in object_schema.fields.iter() {
+                    match (field.key_in(self.from), field.key_in(self.to)) {
+                        (Some(from), Some(to)) => {
+                            if let Some(value) = object.get(&from) {
+                                map.serialize_entry(&to, &self.nested(value, &field.schema))?;
+                                consumed.push(from);
+                            }
+                        }
+                        // flattened fields share the parent object
+                        _ => self.serialize_entries(map, object, &field.schema, consumed)?,
+                    }
+                }
+            }
+            TypeSchema::TaggedEnum(tagged_enum) => {
+                let tag = tagged_enum.tag_key_in(self.from);
+                let Some(tag_value) = object.get(tag) else {
+                    return Ok(());
+                };
+                map.serialize_entry(tagged_enum.tag_key_in(self.to), tag_value)?;
+                consumed.push(tag.to_string());
+
+                let content = tagged_enum.content_key_in(self.from);
+                if let Some(content_value) = object.get(content) {
+                    let payload = tagged_enum
+                        .variants
+                        .iter()
+                        .find(|variant| tag_value.as_str() == Some(variant.value))
+                        .and_then(|variant| variant.payload.as_ref());
+                    match payload {
+                        Some(payload) => map.serialize_entry(tagged_enum.content_key_in(self.to), &self.nested(content_value, payload))?,
+                        None => map.serialize_entry(tagged_enum.content_key_in(self.to), content_value)?,
+                    }
+                    consumed.push(content.to_string());
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+impl Serialize for Rekeyed<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match (self.definitions.resolve(self.schema), self.value) {
+            (TypeSchema::Nullable(schema), value) if !value.is_null() => self.nested(value, schema).serialize(serializer),
+            (TypeSchema::Array(schema, _), Value::Array(items)) => {
+                let mut seq = serializer.serialize_seq(Some(items.len()))?;
+                for item in items.iter() {
+                    seq.serialize_element(&self.nested(item, schema))?;
+                }
+                seq.end()
+            }
+            (TypeSchema::Object(_) | TypeSchema::TaggedEnum(_), Value::Object(object)) => {
+                let mut map = serializer.serialize_map(Some(object.len()))?;
+                let mut consumed = vec![];
+                self.serialize_entries(&mut map, object, self.schema, &mut consumed)?;
+                for (key, value) in object.iter().filter(|(key, _)| !consumed.contains(key)) {
+                    map.serialize_entry(key, value)?;
+                }
+                map.end()
+            }
+            (_, value) => value.serialize(serializer),
+        }
+    }
+}
@@ -0,0 +1,99 @@
+use super::{FileSchema, KeyStyle, ObjectSchema, TaggedEnumSchema, TypeSchema};
+
+// TypeScript type definitions of the data lines and the header line.
+// the comment of a member shows its key in the opposite style.
+// the header line is always in short keys.
+pub fn build_typescript(file_schema: &FileSchema, style: KeyStyle) -> String {
+    let mut typescript = String::new();
+    let definitions = file_schema.definitions.iter().map(|definition| (definition, style));
+    let header_definitions = file_schema.header_definitions.iter().map(|definition| (definition, KeyStyle::Short));
+    for ((name, schema), style) in definitions.chain(header_definitions) {
+        // tagged enums start with a line break
+        let body = build_type(schema, style, 0);
+        let separator = if body.starts_with('\n') { "" } else { " " };
+        typescript.push_str(&format!("export type {} ={}{};\n\n", name, separator, body));
+    }
+    typescript.truncate(typescript.trim_end().len());
+    typescript.push('\n');
+    typescript
+}
+
+fn build_type(schema: &TypeSchema, style: KeyStyle, depth: usize) -> String {
+    match schema {
+        TypeSchema::Boolean => "boolean".to_string(),
+        TypeSchema::Integer(_) => "number".to_string(),
+        TypeSchema::StringInteger(_) | TypeSchema::StringDecimal | TypeSchema::String => "string".to_string(),
+        TypeSchema::Any => "unknown".to_string(),
+        TypeSchema::Nullable(schema) => format!("{} | null", build_type(schema, style, depth)),
+        TypeSchema::Array(schema, None) => match schema.as_ref() {
+            TypeSchema::Nullable(_) | TypeSchema::Enum(_) | TypeSchema::TaggedEnum(_) => {
+                format!("({})[]", build_type(schema, style, depth))
+            }
+            schema => format!("{}[]", build_type(schema, style, depth)),
+        },
+        TypeSchema::Array(schema, Some(length)) => {
+            format!("[{}]", vec![build_type(schema, style, depth); *length].join(", "))
+        }
+        TypeSchema::Ref(name) => name.to_string(),
+        TypeSchema::Object(object) => build_object(object, style, depth),
+        TypeSchema::Enum(values) => values.iter().map(|value| format!("\"{}\"", value)).collect::<Vec<_>>().join(" | "),
+        TypeSchema::TaggedEnum(tagged_enum) => build_tagged_enum(tagged_enum, style, depth),
+    }
+}
+
+fn build_object(object: &ObjectSchema, style: KeyStyle, depth: usize) -> String {
+    let indent = "  ".repeat(depth + 1);
+    let mut members = String::new();
+    let mut flattened = vec![];
+    for field in object.fields.iter() {
+        let (Some(key), Some(comment)) = (field.key_in(style), field.key_in(style.opposite())) else {
+            flattened.push(build_type(&field.schema, style, depth));
+            continue;
+        };
+
+        // optional fields are omitted rather than null
+        let (schema, mark) = match (&field.schema, field.optional) {
+            (TypeSchema::Nullable(schema), true) => (schema.as_ref(), "?"),
+            (schema, true) => (schema, "?"),
+            (schema, false) => (schema, ""),
+        };
+        members.push_str(&format!("{}/** {}{} */\n", indent, comment, encoding_note(schema)));
+        members.push_str(&format!("{}{}{}: {};\n", indent, key, mark, build_type(schema, style, depth + 1)));
+    }
+
+    let own = if members.is_empty() {
+        "{}".to_string()
+    } else {
+        format!("{{\n{}{}}}", members, "  ".repeat(depth))
+    };
+    std::iter::once(own).chain(flattened).collect::<Vec<_>>().join(" & ")
+}
+
+fn build_tagged_enum(tagged_enum: &TaggedEnumSchema, style: KeyStyle, depth: usize) -> String {
+    let indent = "  ".repeat(depth + 1);
+    tagged_enum
+        .variants
+        .iter()
+        .map(|variant| {
+            let payload = variant
+                .payload
+                .as_ref()
+                .map(|payload| format!(" {}: {};", tagged_enum.content_key_in(style), build_type(payload, style, depth + 1)))
+                .unwrap_or_default();
+            format!(
+                "\n{}// {}\n{}| {{ {}: \"{}\";{} }}",
+                indent, variant.name, indent, tagged_enum.tag_key_in(style), variant.value, payload
+            )
+        })
+        .collect()
+}
+
+// string encoded numerics keep their original type in the comment
+fn encoding_note(schema: &TypeSchema) -> String {
+    match schema {
+        TypeSchema::StringInteger(kind) => format!(" ({}{} as string)", if kind.signed { "i" } else { "u" }, kind.bits),
+        TypeSchema::StringDecimal => " (decimal as string)".to_string(),
+        TypeSchema::Nullable(schema) => encoding_note(schema),
+        _ => String::new(),
+    }
+}
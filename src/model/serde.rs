@@ -1,11 +1,15 @@
 pub mod string_u64 {
-    use serde::{self, Deserialize, Deserializer, Serializer};
+    use serde::{self, Deserialize, Deserializer, Serialize, Serializer};
     use std::str::FromStr;
 
     pub fn serialize<S>(data: &u64, serializer: S) -> Result<S::Ok, S::Error>
     where
         S: Serializer,
     {
+        // binary formats keep the native integer
+        if !serializer.is_human_readable() {
+            return data.serialize(serializer);
+        }
         serializer.serialize_str(&data.to_string())
     }
 
@@ -13,19 +17,26 @@ pub mod string_u64 {
     where
         D: Deserializer<'de>,
     {
+        if !deserializer.is_human_readable() {
+            return u64::deserialize(deserializer);
+        }
         let s = String::deserialize(deserializer)?;
         u64::from_str(&s).map_err(serde::de::Error::custom)
     }
 }
 
 pub mod string_u128 {
-    use serde::{self, Deserialize, Deserializer, Serializer};
+    use serde::{self, Deserialize, Deserializer, Serialize, Serializer};
     use std::str::FromStr;
 
     pub fn serialize<S>(data: &u128, serializer: S) -> Result<S::Ok, S::Error>
     where
         S: Serializer,
     {
+        // binary formats keep the native integer
+        if !serializer.is_human_readable() {
+            return data.serialize(serializer);
+        }
         serializer.serialize_str(&data.to_string())
     }
 
@@ -33,13 +44,43 @@ pub mod string_u128 {
     where
         D: Deserializer<'de>,
     {
+        if !deserializer.is_human_readable() {
+            return u128::deserialize(deserializer);
+        }
         let s = String::deserialize(deserializer)?;
         u128::from_str(&s).map_err(serde::de::Error::custom)
     }
 }
 
+pub mod string_i128 {
+    use serde::{self, Deserialize, Deserializer, Serialize, Serializer};
+    use std::str::FromStr;
+
+    pub fn serialize<S>(data: &i128, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        // binary formats keep the native integer
+        if !serializer.is_human_readable() {
+            return data.serialize(serializer);
+        }
+        serializer.serialize_str(&data.to_string())
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<i128, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        if !deserializer.is_human_readable() {
+            return i128::deserialize(deserializer);
+        }
+        let s = String::deserialize(deserializer)?;
+        i128::from_str(&s).map_err(serde::de::Error::custom)
+    }
+}
+
 pub mod string_option_u64 {
-    use serde::{self, Deserialize, Deserializer, Serializer};
+    use serde::{self, Deserialize, Deserializer, Serialize, Serializer};
     use std::str::FromStr;
 
     pub fn serialize<S>(data: &Option<u64>, serializer: S) -> Result<S::Ok, S::Error>
@@ -48,6 +89,9 @@ pub mod string_option_u64 {
     {
         // must be Some
         // skip_serializing_if = "Option::is_none" is must
+        if !serializer.is_human_readable() {
+            return data.serialize(serializer);
+        }
         serializer.serialize_str(&data.unwrap().to_string())
     }
 
@@ -57,6 +101,9 @@ pub mod string_option_u64 {
     {
         // must be Some
         // default = "Option::default" is must
+        if !deserializer.is_human_readable() {
+            return Option::<u64>::deserialize(deserializer);
+        }
         let s = String::deserialize(deserializer)?;
         Ok(Some(u64::from_str(&s).map_err(serde::de::Error::custom)?))
     }
@@ -73,11 +120,13 @@ pub mod string_decimal_price {
     where
         S: Serializer,
     {
-        serializer.serialize_str(
-            &data
-                .with_prec(DECIMAL_PRICE_PRECISION)
-                .to_scientific_notation(),
-        )
+        serializer.serialize_str(&to_string(data))
+    }
+
+    // also used for prices computed outside of events (e.g. by the price-at command)
+    pub fn to_string(data: &BigDecimal) -> String {
+        data.with_prec(DECIMAL_PRICE_PRECISION)
+            .to_scientific_notation()
     }
 
     pub fn deserialize<'de, D>(deserializer: D) -> Result<BigDecimal, D::Error>
@@ -88,3 +137,31 @@ pub mod string_decimal_price {
         BigDecimal::from_str(&s).map_err(serde::de::Error::custom)
     }
 }
+
+// JSON values are written as they are, except in binary formats, which cannot decode a value without knowing its type
+// (a value is written as JSON text there)
+pub mod binary_json_value {
+    use serde::{self, Deserialize, Deserializer, Serialize, Serializer};
+    use serde_json::Value;
+
+    pub fn serialize<S>(data: &Value, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        if !serializer.is_human_readable() {
+            return serializer.serialize_str(&data.to_string());
+        }
+        data.serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Value, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        if !deserializer.is_human_readable() {
+            let s = String::deserialize(deserializer)?;
+            return serde_json::from_str(&s).map_err(serde::de::Error::custom);
+        }
+        Value::deserialize(deserializer)
+    }
+}
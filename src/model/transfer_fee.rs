@@ -0,0 +1,29 @@
+// cloned from: https://github.com/solana-labs/solana-program-library/blob/master/token/program-2022/src/extension/transfer_fee/mod.rs
+
+const MAX_FEE_BASIS_POINTS: u16 = 10_000;
+const ONE_IN_BASIS_POINTS: u128 = MAX_FEE_BASIS_POINTS as u128;
+
+fn ceil_div(numerator: u128, denominator: u128) -> Option<u128> {
+    numerator
+        .checked_add(denominator)?
+        .checked_sub(1)?
+        .checked_div(denominator)
+}
+
+pub fn calculate_fee(pre_fee_amount: u64, transfer_fee_bps: u16, transfer_fee_max: u64) -> Option<u64> {
+    let transfer_fee_basis_points = transfer_fee_bps as u128;
+    if transfer_fee_basis_points == 0 || pre_fee_amount == 0 {
+        Some(0)
+    } else {
+        let numerator = (pre_fee_amount as u128).checked_mul(transfer_fee_basis_points)?;
+        let raw_fee: u64 = ceil_div(numerator, ONE_IN_BASIS_POINTS)?
+            .try_into() // guaranteed to be okay
+            .ok()?;
+
+        Some(raw_fee.min(transfer_fee_max))
+    }
+}
+
+pub fn calculate_post_fee_amount(pre_fee_amount: u64, transfer_fee_bps: u16, transfer_fee_max: u64) -> Option<u64> {
+    pre_fee_amount.checked_sub(calculate_fee(pre_fee_amount, transfer_fee_bps, transfer_fee_max)?)
+}
@@ -0,0 +1,439 @@
+use super::{
+    event::{definition::TradedEventPayload, WhirlpoolEvent, WhirlpoolEventBlock},
+    serde::string_u128,
+};
+use serde_derive::{Deserialize, Serialize};
+use crate::model::schema::Schema;
+use std::collections::{BTreeMap, BTreeSet};
+
+/*
+
+Whirlpool Wallet Activity JSON Lines Format
+
+To reduce data size, we use short field names.
+Each line is a JSON object with the following schema (one line per wallet, ordered by wallet):
+
+{
+  wallet(w): String(base58 encoding),
+  asPayer(p): WalletActivity (optional, transactions paid by the wallet),
+  asTokenAuthority(ta): WalletActivity (optional, trades with the wallet as token authority),
+  positions(ps): [
+    {
+      position(p): String(base58 encoding),
+      whirlpool(w): String(base58 encoding),
+      firstSeenBlockTime(fbt): i64,
+      lastSeenBlockTime(lbt): i64,
+      opened(o): bool,
+      closed(c): bool,
+    },
+    ...
+  ] (positions with the wallet as position authority),
+}
+
+WalletActivity:
+{
+  transactions(x): u64,
+  trades(tc): u64,
+  volume(v): [ { mint(m): String(base58 encoding), totalIn(ti): String, totalOut(to): String }, ... ],
+  whirlpools(wp): [ String(base58 encoding), ... ],
+  firstSeenBlockTime(fbt): i64,
+  lastSeenBlockTime(lbt): i64,
+}
+
+totalIn is the amount the wallet paid into pools, and totalOut is the amount it received (before transfer fees).
+The payer of a transaction is credited with all trades in it, and the token authority of a trade with that trade.
+A transaction is counted once per wallet and role, however many events of the wallet it has.
+The file may start with a header line (see model/header.rs).
+
+*/
+
+pub const WHIRLPOOL_WALLET_FORMAT_VERSION: u32 = 1;
+
+pub type PubkeyString = String;
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Schema)]
+pub struct WhirlpoolWalletData {
+    #[serde(rename = "w")]
+    pub wallet: PubkeyString,
+    #[serde(rename = "p", skip_serializing_if = "Option::is_none", default = "Option::default")]
+    pub as_payer: Option<WalletActivity>,
+    #[serde(rename = "ta", skip_serializing_if = "Option::is_none", default = "Option::default")]
+    pub as_token_authority: Option<WalletActivity>,
+    #[serde(rename = "ps")]
+    pub positions: Vec<WalletPosition>,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Schema)]
+pub struct WalletActivity {
+    #[serde(rename = "x")]
+    pub transactions: u64,
+    #[serde(rename = "tc")]
+    pub trades: u64,
+    #[serde(rename = "v")]
+    pub volume: Vec<MintVolume>,
+    #[serde(rename = "wp")]
+    pub whirlpools: Vec<PubkeyString>,
+    #[serde(rename = "fbt")]
+    pub first_seen_block_time: i64,
+    #[serde(rename = "lbt")]
+    pub last_seen_block_time: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Schema)]
+pub struct MintVolume {
+    #[serde(rename = "m")]
+    pub mint: PubkeyString,
+    #[serde(rename = "ti", with = "string_u128")]
+    pub total_in: u128,
+    #[serde(rename = "to", with = "string_u128")]
+    pub total_out: u128,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Schema)]
+pub struct WalletPosition {
+    #[serde(rename = "p")]
+    pub position: PubkeyString,
+    #[serde(rename = "w")]
+    pub whirlpool: PubkeyString,
+    #[serde(rename = "fbt")]
+    pub first_seen_block_time: i64,
+    #[serde(rename = "lbt")]
+    pub last_seen_block_time: i64,
+    #[serde(rename = "o")]
+    pub opened: bool,
+    #[serde(rename = "c")]
+    pub closed: bool,
+}
+
+// folds event blocks into the activity of each wallet
+#[derive(Default)]
+pub struct WalletAggregator {
+    wallets: BTreeMap<String, WalletAggregate>,
+    // sequence number of the transaction being applied
+    transaction_sequence: u64,
+}
+
+#[derive(Default)]
+struct WalletAggregate {
+    as_payer: Option<ActivityAggregate>,
+    as_token_authority: Option<ActivityAggregate>,
+    positions: BTreeMap<String, PositionAggregate>,
+}
+
+struct ActivityAggregate {
+    transactions: u64,
+    // the last counted transaction, to count a transaction with multiple events of the wallet once
+    last_transaction_sequence: u64,
+    trades: u64,
+    // mint -> (total in, total out)
+    volume: BTreeMap<String, (u128, u128)>,
+    whirlpools: BTreeSet<String>,
+    first_seen_block_time: i64,
+    last_seen_block_time: i64,
+}
+
+struct PositionAggregate {
+    whirlpool: String,
+    first_seen_block_time: i64,
+    last_seen_block_time: i64,
+    opened: bool,
+    closed: bool,
+}
+
+impl WalletAggregator {
+    pub fn apply_block(&mut self, event_block: &WhirlpoolEventBlock) {
+        let block_time = event_block.block_time;
+        for transaction in event_block.transactions.iter() {
+            // sequence numbers start at 1, 0 is "not counted yet"
+            self.transaction_sequence += 1;
+            let transaction_sequence = self.transaction_sequence;
+
+            let payer_activity = self
+                .wallets
+                .entry(transaction.payer.clone())
+                .or_default()
+                .as_payer
+                .get_or_insert_with(|| ActivityAggregate::new(block_time));
+            payer_activity.seen(transaction_sequence, block_time);
+            for entry in transaction.events.iter() {
+                if let Some(whirlpool) = entry.event.whirlpool() {
+                    payer_activity.whirlpools.insert(whirlpool.to_string());
+                }
+                if let WhirlpoolEvent::Traded(traded) = &entry.event {
+                    payer_activity.traded(traded);
+                }
+            }
+
+            for entry in transaction.events.iter() {
+                match &entry.event {
+                    WhirlpoolEvent::Traded(traded) => {
+                        let token_authority_activity = self
+                            .wallets
+                            .entry(traded.token_authority.clone())
+                            .or_default()
+                            .as_token_authority
+                            .get_or_insert_with(|| ActivityAggregate::new(block_time));
+                        token_authority_activity.seen(transaction_sequence, block_time);
+                        token_authority_activity.whirlpools.insert(traded.whirlpool.clone());
+                        token_authority_activity.traded(traded);
+                    }
+                    WhirlpoolEvent::PositionOpened(payload) => {
+                        self.position_seen(&payload.position_authority, &payload.position, &payload.whirlpool, block_time).opened = true;
+                    }
+                    WhirlpoolEvent::PositionClosed(payload) => {
+                        self.position_seen(&payload.position_authority, &payload.position, &payload.whirlpool, block_time).closed = true;
+                    }
+                    WhirlpoolEvent::LiquidityDeposited(payload) => {
+                        self.position_seen(&payload.position_authority, &payload.position, &payload.whirlpool, block_time);
+                    }
+                    WhirlpoolEvent::LiquidityWithdrawn(payload) => {
+                        self.position_seen(&payload.position_authority, &payload.position, &payload.whirlpool, block_time);
+                    }
+                    WhirlpoolEvent::PositionFeesHarvested(payload) => {
+                        self.position_seen(&payload.position_authority, &payload.position, &payload.whirlpool, block_time);
+                    }
+                    WhirlpoolEvent::PositionRewardHarvested(payload) => {
+                        self.position_seen(&payload.position_authority, &payload.position, &payload.whirlpool, block_time);
+                    }
+                    _ => { /* ignore */ }
+                }
+            }
+        }
+    }
+
+    // ordered by wallet
+    pub fn into_wallet_data(self) -> impl Iterator<Item = WhirlpoolWalletData> {
+        self.wallets
+            .into_iter()
+            .map(|(wallet, aggregate)| aggregate.into_wallet_data(wallet))
+    }
+
+    fn position_seen(&mut self, position_authority: &str, position: &str, whirlpool: &str, block_time: i64) -> &mut PositionAggregate {
+        let position = self
+            .wallets
+            .entry(position_authority.to_string())
+            .or_default()
+            .positions
+            .entry(position.to_string())
+            .or_insert_with(|| PositionAggregate {
+                whirlpool: whirlpool.to_string(),
+                first_seen_block_time: block_time,
+                last_seen_block_time: block_time,
+                opened: false,
+                closed: false,
+            });
+        position.last_seen_block_time = block_time;
+        position
+    }
+}
+
+impl ActivityAggregate {
+    fn new(block_time: i64) -> Self {
+        Self {
+            transactions: 0,
+            last_transaction_sequence: 0,
+            trades: 0,
+            volume: BTreeMap::new(),
+            whirlpools: BTreeSet::new(),
+            first_seen_block_time: block_time,
+            last_seen_block_time: block_time,
+        }
+    }
+
+    fn seen(&mut self, transaction_sequence: u64, block_time: i64) {
+        if self.last_transaction_sequence != transaction_sequence {
+            self.transactions += 1;
+            self.last_transaction_sequence = transaction_sequence;
+        }
+        self.last_seen_block_time = block_time;
+    }
+
+    fn traded(&mut self, traded: &TradedEventPayload) {
+        self.trades += 1;
+        self.volume.entry(traded.transfer_in.mint.clone()).or_default().0 += traded.transfer_in.amount as u128;
+        self.volume.entry(traded.transfer_out.mint.clone()).or_default().1 += traded.transfer_out.amount as u128;
+    }
+
+    fn into_wallet_activity(self) -> WalletActivity {
+        WalletActivity {
+            transactions: self.transactions,
+            trades: self.trades,
+            volume: self
+                .volume
+                .into_iter()
+                .map(|(mint, (total_in, total_out))| MintVolume { mint, total_in, total_out })
+                .collect(),
+            whirlpools: self.whirlpools.into_iter().collect(),
+            first_seen_block_time: self.first_seen_block_time,
+            last_seen_block_time: self.last_seen_block_time,
+        }
+    }
+}
+
+impl WalletAggregate {
+    fn into_wallet_data(self, wallet: String) -> WhirlpoolWalletData {
+        WhirlpoolWalletData {
+            wallet,
+            as_payer: self.as_payer.map(ActivityAggregate::into_wallet_activity),
+            as_token_authority: self.as_token_authority.map(ActivityAggregate::into_wallet_activity),
+            positions: self
+                .positions
+                .into_iter()
+                .map(|(position, aggregate)| WalletPosition {
+                    position,
+                    whirlpool: aggregate.whirlpool,
+                    first_seen_block_time: aggregate.first_seen_block_time,
+                    last_seen_block_time: aggregate.last_seen_block_time,
+                    opened: aggregate.opened,
+                    closed: aggregate.closed,
+                })
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::event::{
+        definition::{
+            PositionClosedEventOrigin, PositionClosedEventPayload, PositionOpenedEventOrigin, PositionOpenedEventPayload,
+            PositionType,
+        },
+        fixtures::{current_block, traded_entry, transaction_with, WHIRLPOOLS},
+        WhirlpoolEventEntry, WhirlpoolEventTransaction,
+    };
+    use bigdecimal::BigDecimal;
+
+    const PAYER: &str = "9WzDXwBbmkg8ZTbNMqUxvQRAyrZzDsGYdLVL9zYtAWWM";
+    const TRADER: &str = "5oRJnvYkZFbfYjmk1e2LZ2Em7iTBXnYRZGsTnCGm5JXP";
+    const OTHER_TRADER: &str = "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v";
+    const MINT_A: &str = "2b1kV6DkPAnxd5ixfnxCpjxmKwqjjaYmCZfHsFu24GXo";
+    const MINT_B: &str = "So11111111111111111111111111111111111111112";
+
+    fn trade_by(token_authority: &str, amount_in: u64) -> WhirlpoolEventEntry {
+        let mut entry = traded_entry(WHIRLPOOLS[0], MINT_A, amount_in, MINT_B, amount_in / 2);
+        if let WhirlpoolEvent::Traded(traded) = &mut entry.event {
+            traded.token_authority = token_authority.to_string();
+        }
+        entry
+    }
+
+    fn position_event(opened: bool, position: &str) -> WhirlpoolEventEntry {
+        let event = if opened {
+            WhirlpoolEvent::PositionOpened(PositionOpenedEventPayload {
+                origin: PositionOpenedEventOrigin::OpenPosition,
+                whirlpool: WHIRLPOOLS[1].to_string(),
+                position: position.to_string(),
+                lower_tick_index: -64,
+                upper_tick_index: 64,
+                lower_decimal_price: BigDecimal::from(1),
+                upper_decimal_price: BigDecimal::from(2),
+                position_authority: TRADER.to_string(),
+                position_type: PositionType::Position,
+                position_mint: None,
+                position_bundle_mint: None,
+                position_bundle: None,
+                position_bundle_index: None,
+            })
+        } else {
+            WhirlpoolEvent::PositionClosed(PositionClosedEventPayload {
+                origin: PositionClosedEventOrigin::ClosePosition,
+                whirlpool: WHIRLPOOLS[1].to_string(),
+                position: position.to_string(),
+                lower_tick_index: -64,
+                upper_tick_index: 64,
+                lower_decimal_price: BigDecimal::from(1),
+                upper_decimal_price: BigDecimal::from(2),
+                position_authority: TRADER.to_string(),
+                position_type: PositionType::Position,
+                position_mint: None,
+                position_bundle_mint: None,
+                position_bundle: None,
+                position_bundle_index: None,
+                old_position_fees_and_rewards: None,
+            })
+        };
+        WhirlpoolEventEntry {
+            event,
+            instruction_index: Some(0),
+            sub_index: Some(0),
+        }
+    }
+
+    fn block(block_time: i64, transactions: Vec<WhirlpoolEventTransaction>) -> WhirlpoolEventBlock {
+        WhirlpoolEventBlock {
+            block_time,
+            transactions,
+            ..current_block()
+        }
+    }
+
+    fn aggregate(blocks: &[WhirlpoolEventBlock]) -> BTreeMap<String, WhirlpoolWalletData> {
+        let mut aggregator = WalletAggregator::default();
+        blocks.iter().for_each(|block| aggregator.apply_block(block));
+        aggregator.into_wallet_data().map(|data| (data.wallet.clone(), data)).collect()
+    }
+
+    #[test]
+    fn payer_and_token_authority_are_credited_separately() {
+        // the fixture transaction is paid by PAYER
+        let wallets = aggregate(&[block(100, vec![transaction_with(vec![trade_by(TRADER, 1000)])])]);
+
+        let payer = &wallets[PAYER];
+        assert!(payer.as_token_authority.is_none());
+        let as_payer = payer.as_payer.as_ref().unwrap();
+        assert_eq!((as_payer.transactions, as_payer.trades), (1, 1));
+        assert_eq!(as_payer.whirlpools, vec![WHIRLPOOLS[0].to_string()]);
+
+        let trader = &wallets[TRADER];
+        assert!(trader.as_payer.is_none());
+        let as_token_authority = trader.as_token_authority.as_ref().unwrap();
+        assert_eq!((as_token_authority.transactions, as_token_authority.trades), (1, 1));
+        assert_eq!(
+            as_token_authority.volume,
+            vec![
+                MintVolume { mint: MINT_A.to_string(), total_in: 1000, total_out: 0 },
+                MintVolume { mint: MINT_B.to_string(), total_in: 0, total_out: 500 },
+            ]
+        );
+    }
+
+    #[test]
+    fn transaction_is_counted_once_per_wallet() {
+        // another wallet trades between the trades of TRADER in the first transaction,
+        // and the second transaction has the same signature (the fixture's)
+        let wallets = aggregate(&[
+            block(100, vec![transaction_with(vec![trade_by(TRADER, 1000), trade_by(OTHER_TRADER, 10), trade_by(TRADER, 2000)])]),
+            block(200, vec![transaction_with(vec![trade_by(TRADER, 4000)])]),
+        ]);
+
+        let as_token_authority = wallets[TRADER].as_token_authority.as_ref().unwrap();
+        assert_eq!((as_token_authority.transactions, as_token_authority.trades), (2, 3));
+        assert_eq!((as_token_authority.first_seen_block_time, as_token_authority.last_seen_block_time), (100, 200));
+        assert_eq!(as_token_authority.volume[0].total_in, 7000);
+
+        let as_token_authority = wallets[OTHER_TRADER].as_token_authority.as_ref().unwrap();
+        assert_eq!((as_token_authority.transactions, as_token_authority.trades), (1, 1));
+
+        let as_payer = wallets[PAYER].as_payer.as_ref().unwrap();
+        assert_eq!((as_payer.transactions, as_payer.trades), (2, 4));
+    }
+
+    #[test]
+    fn positions_record_open_and_close() {
+        // "old" was opened before the first block
+        let wallets = aggregate(&[
+            block(100, vec![transaction_with(vec![position_event(true, "new")])]),
+            block(200, vec![transaction_with(vec![position_event(false, "new"), position_event(false, "old")])]),
+        ]);
+
+        let positions = &wallets[TRADER].positions;
+        assert_eq!(
+            positions.iter().map(|position| (position.position.as_str(), position.opened, position.closed)).collect::<Vec<_>>(),
+            vec![("new", true, true), ("old", false, true)]
+        );
+        assert_eq!((positions[0].first_seen_block_time, positions[0].last_seen_block_time), (100, 200));
+        assert_eq!(positions[0].whirlpool, WHIRLPOOLS[1]);
+    }
+}
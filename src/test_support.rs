@@ -0,0 +1,63 @@
+use crate::{
+    io::{
+        compression::Compression,
+        event::{EventEncoding, EventFileWriter},
+    },
+    model::{event::WhirlpoolEventBlock, header::WhirlpoolFileHeader, schema::KeyStyle},
+};
+use std::{
+    fs::File,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+/*
+
+Test Support
+
+Helpers shared by the tests of the library and the commands (see also model/event/fixtures.rs).
+The tests of the commands are built with the binary, which links the library without cfg(test),
+so both modules are also built with the test-support feature (enabled by the dev-dependency on this crate).
+
+*/
+
+static TEMP_FILE_SEQUENCE: AtomicU64 = AtomicU64::new(0);
+
+// a new path in the temp directory for each call, so tests running in parallel never share a file
+pub fn temp_file_path(name: &str) -> String {
+    let sequence = TEMP_FILE_SEQUENCE.fetch_add(1, Ordering::Relaxed);
+    std::env::temp_dir()
+        .join(format!("whirlpool-test-{}-{}-{}", std::process::id(), sequence, name))
+        .to_string_lossy()
+        .to_string()
+}
+
+pub fn write_temp_file(name: &str, data: &[u8]) -> String {
+    let path = temp_file_path(name);
+    std::fs::write(&path, data).unwrap();
+    path
+}
+
+// writes the blocks with short keys, as the converter does by default
+pub async fn write_event_file(
+    path: &str,
+    header: Option<WhirlpoolFileHeader>,
+    blocks: impl IntoIterator<Item = WhirlpoolEventBlock>,
+    encoding: EventEncoding,
+    compression: Compression,
+    seekable: bool,
+) {
+    let mut writer = EventFileWriter::new(
+        File::create(path).unwrap(),
+        header,
+        encoding,
+        KeyStyle::Short,
+        compression,
+        None,
+        seekable,
+    )
+    .unwrap();
+    for block in blocks {
+        writer.write(block).await.unwrap();
+    }
+    writer.finish().await.unwrap();
+}